@@ -102,6 +102,13 @@ impl MusicPlayer {
         self.sfx_enabled = !self.sfx_enabled;
     }
 
+    pub fn set_enabled(&mut self, bgm: bool, sfx: bool) {
+        if self.bgm_enabled != bgm {
+            self.toggle_bgm();
+        }
+        self.sfx_enabled = sfx;
+    }
+
     pub fn play(&mut self) {
         self.bgm_paused = false;
         self.sink.clear();
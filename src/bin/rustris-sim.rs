@@ -0,0 +1,214 @@
+//! Headless simulator: runs a scripted input file against a seed and prints
+//! the final board, score and stats as JSON.
+//!
+//! ```text
+//! rustris-sim <script> [--seed N] [--mode marathon|sprint|ultra|endless] [--settings FILE]
+//! ```
+//!
+//! Script lines run in order and `#` starts a comment. `wait <ms>` advances the
+//! clock with no input; any other line is a list of inputs (`MoveLeft`,
+//! `RotateCw`, `HardDrop`, ...) applied together, followed by one frame.
+//! Inputs that land while the game is between pieces wait for the next one.
+//! Stats count every lock, line clears or not.
+
+use serde::Serialize;
+use std::fs;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use rustris::game::piece::{BOARD_HEIGHT, BUFFER_HEIGHT, EMPTY, GARBAGE_CELL};
+use rustris::game::settings::Settings;
+use rustris::game::step::SIM_FRAME;
+use rustris::game::{Game, GameInput, GameMode};
+
+const PIECE_LETTERS: [char; 7] = ['I', 'O', 'T', 'S', 'Z', 'L', 'J'];
+
+enum Command {
+    Wait(Duration),
+    Inputs(Vec<GameInput>),
+}
+
+#[derive(Serialize)]
+struct Summary {
+    seed: u64,
+    mode: GameMode,
+    score: u32,
+    lines: u32,
+    level: u32,
+    time_ms: u64,
+    game_over: bool,
+    cleared: bool,
+    board: Vec<String>,
+    stats: Stats,
+}
+
+#[derive(Default, Serialize)]
+struct Stats {
+    pieces: u32,
+    /// T-spin singles, doubles and triples.
+    tspins: [u32; 3],
+    tspin_minis: u32,
+    max_combo: u32,
+    /// Longest run of back-to-back bonuses.
+    max_b2b: u32,
+    #[serde(skip)]
+    b2b_chain: u32,
+}
+
+impl Stats {
+    fn record_lock(&mut self, game: &Game) {
+        self.pieces += 1;
+        // `last_action` is left over from an earlier clear unless the combo
+        // counter says this lock cleared lines too.
+        let Some(action) = game.last_action.as_ref().filter(|a| game.combo >= 0 && a.cleared_lines > 0) else {
+            return;
+        };
+        self.max_combo = self.max_combo.max(action.combo as u32);
+        self.b2b_chain = if action.is_back_to_back { self.b2b_chain + 1 } else { 0 };
+        self.max_b2b = self.max_b2b.max(self.b2b_chain);
+        if action.is_tspin {
+            if action.is_mini {
+                self.tspin_minis += 1;
+            } else if let Some(count) = self.tspins.get_mut(action.cleared_lines as usize - 1) {
+                *count += 1;
+            }
+        }
+    }
+}
+
+struct Args {
+    script: String,
+    seed: u64,
+    mode: GameMode,
+    settings: Settings,
+}
+
+fn parse_mode(name: &str) -> Result<GameMode, String> {
+    match name.to_ascii_lowercase().as_str() {
+        "marathon" => Ok(GameMode::Marathon),
+        "sprint" => Ok(GameMode::Sprint),
+        "ultra" => Ok(GameMode::Ultra),
+        "endless" => Ok(GameMode::Endless),
+        _ => Err(format!("unknown mode '{}'", name)),
+    }
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut script = None;
+    let mut seed = 0;
+    let mut mode = GameMode::Marathon;
+    let mut settings = Settings::default();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| args.next().ok_or(format!("{} needs a value", flag));
+        match arg.as_str() {
+            "--seed" => {
+                let v = value("--seed")?;
+                seed = v.parse().map_err(|_| format!("bad seed '{}'", v))?;
+            }
+            "--mode" => mode = parse_mode(&value("--mode")?)?,
+            "--settings" => {
+                let path = value("--settings")?;
+                let data = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?;
+                settings = serde_json::from_str(&data).map_err(|e| format!("{}: {}", path, e))?;
+            }
+            _ if script.is_none() && !arg.starts_with("--") => script = Some(arg),
+            _ => return Err(format!("unexpected argument '{}'", arg)),
+        }
+    }
+
+    let script = script.ok_or("usage: rustris-sim <script> [--seed N] [--mode MODE] [--settings FILE]")?;
+    Ok(Args { script, seed, mode, settings })
+}
+
+fn parse_script(text: &str) -> Result<Vec<Command>, String> {
+    let mut commands = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let mut words = line.split_whitespace().peekable();
+        if words.next_if_eq(&"wait").is_some() {
+            let ms: u64 = words
+                .next()
+                .and_then(|w| w.parse().ok())
+                .ok_or(format!("line {}: expected 'wait <ms>'", n + 1))?;
+            commands.push(Command::Wait(Duration::from_millis(ms)));
+            continue;
+        }
+        let inputs = words
+            .map(|w| {
+                serde_json::from_value(serde_json::Value::String(w.to_string()))
+                    .map_err(|_| format!("line {}: unknown input '{}'", n + 1, w))
+            })
+            .collect::<Result<Vec<GameInput>, String>>()?;
+        commands.push(Command::Inputs(inputs));
+    }
+    Ok(commands)
+}
+
+fn board_rows(game: &Game) -> Vec<String> {
+    (BUFFER_HEIGHT..BOARD_HEIGHT)
+        .map(|r| {
+            game.board[r]
+                .iter()
+                .map(|&cell| match cell {
+                    EMPTY => '.',
+                    GARBAGE_CELL => 'G',
+                    id => PIECE_LETTERS.get(id as usize - 1).copied().unwrap_or('?'),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn run() -> Result<Summary, String> {
+    let args = parse_args()?;
+    let text = fs::read_to_string(&args.script).map_err(|e| format!("{}: {}", args.script, e))?;
+    let commands = parse_script(&text)?;
+
+    let mut game = Game::new(args.mode, &args.settings, args.seed);
+    let mut stats = Stats::default();
+    let mut on_lock = |game: &Game| stats.record_lock(game);
+    for command in commands {
+        if game.game_over {
+            break;
+        }
+        match command {
+            Command::Wait(dt) => game.step_with_locks(&[], dt, &mut on_lock),
+            Command::Inputs(inputs) => game.step_with_locks(&inputs, SIM_FRAME, &mut on_lock),
+        };
+    }
+    // Inputs left waiting on the last piece's ARE still get their turn.
+    while game.has_queued_inputs() && !game.game_over {
+        game.step_with_locks(&[], SIM_FRAME, &mut on_lock);
+    }
+
+    Ok(Summary {
+        seed: args.seed,
+        mode: args.mode,
+        score: game.score,
+        lines: game.lines,
+        level: game.level,
+        time_ms: game.elapsed.as_millis() as u64,
+        game_over: game.game_over,
+        cleared: game.cleared,
+        board: board_rows(&game),
+        stats,
+    })
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(summary) => {
+            println!("{}", serde_json::to_string_pretty(&summary).unwrap());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("rustris-sim: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
@@ -1,5 +1,3 @@
-use std::time::Instant;
-
 use crate::game::garbage::GarbageEvent;
 use crate::game::piece::*;
 
@@ -12,12 +10,13 @@ impl Game {
         if let Some(anim) = self.line_clear_anim.take() {
             self.remove_rows(&anim.rows);
         }
-        self.are_timer = Some(Instant::now());
+        self.are_timer = Some(self.now());
     }
 
     pub fn update_animation(&mut self) -> bool {
+        let now = self.now();
         if let Some(ref mut anim) = self.line_clear_anim {
-            let elapsed = anim.started_at.elapsed().as_millis() as u64;
+            let elapsed = now.saturating_duration_since(anim.started_at).as_millis() as u64;
             let total = LINE_CLEAR_ANIM_DURATION.as_millis() as u64;
             let phase_len = total / 3;
             let phase = if elapsed < phase_len {
@@ -46,19 +45,23 @@ impl Game {
 
     pub fn check_are(&mut self) -> bool {
         if let Some(start) = self.are_timer {
-            if start.elapsed() >= ARE_DELAY {
-                self.are_timer = None;
-                if self.cleared {
-                    self.game_over = true;
-                    return true;
-                }
-                self.spawn_next();
+            if self.since(start) >= ARE_DELAY {
+                self.end_are();
                 return true;
             }
         }
         false
     }
 
+    pub fn end_are(&mut self) {
+        self.are_timer = None;
+        if self.cleared {
+            self.game_over = true;
+            return;
+        }
+        self.spawn_next();
+    }
+
     pub fn receive_garbage(&mut self, lines: u32, hole_column: usize) {
         let lines = lines as usize;
         if lines == 0 {
@@ -84,11 +87,29 @@ impl Game {
             .collect();
         self.garbage_rise_anim = Some(GarbageRiseAnimation {
             events: anim_events,
-            started_at: Instant::now(),
+            started_at: self.now(),
             lines_applied: 0,
         });
     }
 
+    /// Raises whatever the current garbage rise has left in one go, as if
+    /// its animation had run out.
+    pub fn finish_garbage_rise(&mut self) {
+        let Some(anim) = self.garbage_rise_anim.take() else {
+            return;
+        };
+        let mut skip = anim.lines_applied;
+        for (lines, hole) in anim.events {
+            for _ in skip.min(lines)..lines {
+                self.receive_garbage(1, hole);
+            }
+            skip = skip.saturating_sub(lines);
+        }
+        if self.has_blocks_in_buffer() {
+            self.game_over = true;
+        }
+    }
+
     pub fn is_garbage_animating(&self) -> bool {
         self.garbage_rise_anim.is_some()
     }
@@ -109,7 +130,7 @@ impl Game {
             None => return false,
         };
 
-        let elapsed_ms = anim.started_at.elapsed().as_millis() as u64;
+        let elapsed_ms = self.since(anim.started_at).as_millis() as u64;
         let target_lines = (elapsed_ms / GARBAGE_RISE_INTERVAL_MS) as u32;
 
         let mut total_lines: u32 = 0;
@@ -1,30 +1,53 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use crate::game::clock::{ManualClock, SharedClock, SystemClock};
+
 use crate::game::piece::*;
 use crate::game::settings::Settings;
 
 use super::{Game, GameMode, LastMove};
 
 impl Game {
-    pub fn new(mode: GameMode, settings: &Settings) -> Self {
+    pub fn new(mode: GameMode, settings: &Settings, seed: u64) -> Self {
+        Self::with_clock(mode, settings, seed, Arc::new(SystemClock))
+    }
+
+    /// Build a game whose timers run off a manual clock. `step` advances the
+    /// same clock, so tests can mix both.
+    pub fn with_manual_clock(
+        mode: GameMode,
+        settings: &Settings,
+        seed: u64,
+        clock: Arc<ManualClock>,
+    ) -> Self {
+        let mut game = Self::with_clock(mode, settings, seed, clock.clone());
+        game.step_clock = Some(clock);
+        game
+    }
+
+    pub fn with_clock(mode: GameMode, settings: &Settings, seed: u64, clock: SharedClock) -> Self {
         let start_level = match mode {
             GameMode::Marathon | GameMode::Endless => settings.level,
             GameMode::Sprint | GameMode::Ultra => 1,
             GameMode::Versus => settings.level,
         };
-        let mut bag = Bag::new(settings.bag_randomizer);
-        let current_kind = bag.next();
+        let mut randomizer = settings.randomizer.build(seed);
+        let current_kind = randomizer.next();
         let mut next_queue = Vec::with_capacity(settings.next_count);
         for _ in 0..settings.next_count {
-            next_queue.push(bag.next());
+            next_queue.push(randomizer.next());
         }
+        let now = clock.now();
         Self {
             board: [[EMPTY; BOARD_WIDTH]; BOARD_HEIGHT],
-            current: Piece::new(current_kind),
+            current: Piece::new(current_kind, settings.rotation),
             next_queue,
             hold: None,
             hold_used: false,
-            bag,
+            randomizer,
+            seed,
             score: 0,
             lines: 0,
             level: start_level,
@@ -33,13 +56,14 @@ impl Game {
             last_move: LastMove::None,
             combo: -1,
             back_to_back: false,
+            b2b_chain: 0,
             last_action: None,
-            last_action_time: Instant::now(),
+            last_action_time: now,
             lock_delay: None,
             line_clear_anim: None,
             are_timer: None,
             mode,
-            game_start: Instant::now(),
+            game_start: now,
             elapsed: Duration::ZERO,
             cleared: false,
             marathon_goal: settings.marathon_goal,
@@ -49,22 +73,27 @@ impl Game {
             ghost_enabled: settings.ghost,
             line_clear_anim_enabled: settings.line_clear_anim,
             next_count: settings.next_count,
-            srs_enabled: settings.srs_enabled,
+            rotation_system: settings.rotation,
+            rotate_180_enabled: settings.rotate_180,
             hold_enabled: settings.hold_enabled,
             lock_delay_ms: settings.lock_delay_ms,
             move_reset: settings.move_reset,
             move_reset_count: 0,
             garbage_rise_anim: None,
+            clock,
+            step_clock: None,
+            step_inputs: VecDeque::new(),
+            gravity_timer: now,
         }
     }
 
     pub(super) fn pop_next(&mut self) -> Piece {
         if self.next_queue.is_empty() {
-            return Piece::new(self.bag.next());
+            return Piece::new(self.randomizer.next(), self.rotation_system);
         }
         let kind = self.next_queue.remove(0);
-        self.next_queue.push(self.bag.next());
-        Piece::new(kind)
+        self.next_queue.push(self.randomizer.next());
+        Piece::new(kind, self.rotation_system)
     }
 
     pub(super) fn is_occupied(&self, r: i32, c: i32) -> bool {
@@ -136,7 +165,7 @@ impl Game {
         match self.hold {
             Some(held_kind) => {
                 self.hold = Some(cur_kind);
-                self.current = Piece::new(held_kind);
+                self.current = Piece::new(held_kind, self.rotation_system);
                 if !self.fits(&self.current) {
                     self.game_over = true;
                 }
@@ -174,12 +203,30 @@ impl Game {
         false
     }
 
+    pub fn now(&self) -> Instant {
+        self.clock.now()
+    }
+
+    pub fn since(&self, t: Instant) -> Duration {
+        self.now().saturating_duration_since(t)
+    }
+
     pub fn update_elapsed(&mut self) {
-        self.elapsed = self.game_start.elapsed();
+        self.elapsed = self.since(self.game_start);
     }
 
     pub fn reset_game_start(&mut self) {
-        self.game_start = Instant::now() - self.elapsed;
+        self.game_start = self.now() - self.elapsed;
+    }
+
+    /// Whether Ultra's time limit has run out, as of the last `update_elapsed`.
+    pub fn time_up(&self) -> bool {
+        self.time_remaining().is_some_and(|t| t.is_zero())
+    }
+
+    pub fn lock_delay_expired(&self) -> bool {
+        self.lock_delay
+            .is_some_and(|start| self.since(start) >= self.lock_delay_duration())
     }
 
     pub fn time_remaining(&self) -> Option<Duration> {
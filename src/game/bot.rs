@@ -0,0 +1,424 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::game::garbage::AttackTable;
+use crate::game::piece::*;
+
+use super::scoring::tspin_at;
+use super::{ClearAction, Game, GameInput};
+
+type Board = [[u8; BOARD_WIDTH]; BOARD_HEIGHT];
+
+/// Placements kept at each ply when searching more than one piece ahead.
+const BEAM_WIDTH: usize = 8;
+
+const W_ATTACK: f64 = 6.0;
+const W_BURN: f64 = -4.0;
+const W_HEIGHT: f64 = -0.35;
+const W_HOLES: f64 = -4.0;
+const W_COVERED: f64 = -0.6;
+const W_BUMPINESS: f64 = -0.25;
+const W_DANGER: f64 = -3.0;
+const W_TSLOT: f64 = 2.5;
+const W_B2B: f64 = 1.5;
+const SAFE_HEIGHT: i32 = 10;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum BotDifficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl BotDifficulty {
+    pub const ALL: [BotDifficulty; 3] = [BotDifficulty::Easy, BotDifficulty::Normal, BotDifficulty::Hard];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BotDifficulty::Easy => "Easy",
+            BotDifficulty::Normal => "Norm",
+            BotDifficulty::Hard => "Hard",
+        }
+    }
+
+    pub fn pieces_per_second(self) -> f64 {
+        match self {
+            BotDifficulty::Easy => 0.75,
+            BotDifficulty::Normal => 1.5,
+            BotDifficulty::Hard => 2.5,
+        }
+    }
+
+    /// How many pieces of the queue the search places before judging a board.
+    pub fn search_depth(self) -> usize {
+        match self {
+            BotDifficulty::Easy => 1,
+            BotDifficulty::Normal => 2,
+            BotDifficulty::Hard => 3,
+        }
+    }
+
+    pub fn uses_hold(self) -> bool {
+        self != BotDifficulty::Easy
+    }
+
+    /// Time between two placements.
+    pub fn piece_interval(self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.pieces_per_second())
+    }
+}
+
+/// A CPU player: searches placements for the current piece and returns the
+/// inputs that reach the best one. Attacks are scored on the match's table.
+pub struct Bot {
+    pub difficulty: BotDifficulty,
+    pub table: AttackTable,
+}
+
+#[derive(Clone)]
+struct Placement {
+    piece: Piece,
+    rotated_last: bool,
+    inputs: Vec<GameInput>,
+}
+
+#[derive(Clone)]
+struct Node {
+    board: Board,
+    back_to_back: bool,
+    b2b_chain: u32,
+    combo: i32,
+    gained: f64,
+    score: f64,
+    inputs: Vec<GameInput>,
+}
+
+impl Bot {
+    pub fn new(difficulty: BotDifficulty, table: AttackTable) -> Self {
+        Self { difficulty, table }
+    }
+
+    /// Inputs that place the current piece, ending with a hard drop. Feed them
+    /// to the game in one batch; gravity and lock delay are left to the caller.
+    pub fn plan(&self, game: &Game) -> Vec<GameInput> {
+        let depth = self.difficulty.search_depth().max(1);
+        let root = Node {
+            board: game.board,
+            back_to_back: game.back_to_back,
+            b2b_chain: game.b2b_chain,
+            combo: game.combo,
+            gained: 0.0,
+            score: 0.0,
+            inputs: Vec::new(),
+        };
+
+        let mut queue: Vec<usize> = game.next_queue.iter().take(game.next_count).copied().collect();
+        let mut best = self.search(game, &root, game.current, &queue, depth, &[]);
+
+        if self.difficulty.uses_hold() && game.hold_enabled && !game.hold_used {
+            let held = match game.hold {
+                Some(kind) => Some(kind),
+                None if !queue.is_empty() => Some(queue.remove(0)),
+                None => None,
+            };
+            if let Some(kind) = held {
+                let piece = Piece::new(kind, game.rotation_system);
+                if let Some(alt) = self.search(game, &root, piece, &queue, depth, &[GameInput::Hold]) {
+                    if best.as_ref().is_none_or(|b| alt.score > b.score) {
+                        best = Some(alt);
+                    }
+                }
+            }
+        }
+
+        best.map(|n| n.inputs).unwrap_or_else(|| vec![GameInput::HardDrop])
+    }
+
+    fn search(
+        &self,
+        game: &Game,
+        root: &Node,
+        first: Piece,
+        queue: &[usize],
+        depth: usize,
+        prefix: &[GameInput],
+    ) -> Option<Node> {
+        let mut beam = vec![root.clone()];
+        for ply in 0..depth {
+            let piece = match ply {
+                0 => first,
+                _ => match queue.get(ply - 1) {
+                    Some(&kind) => Piece::new(kind, game.rotation_system),
+                    None => break,
+                },
+            };
+            let mut next: Vec<Node> = Vec::new();
+            for node in &beam {
+                for placement in placements(&node.board, piece, game.rotate_180_enabled) {
+                    let mut child = place(node, &placement, self.table);
+                    if ply == 0 {
+                        child.inputs = prefix.iter().copied().chain(placement.inputs).collect();
+                    }
+                    child.score = child.gained + evaluate(&child.board, child.back_to_back);
+                    next.push(child);
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            next.sort_by(|a, b| b.score.total_cmp(&a.score));
+            next.truncate(BEAM_WIDTH);
+            beam = next;
+        }
+        beam.into_iter().find(|n| !n.inputs.is_empty())
+    }
+}
+
+fn fits(board: &Board, piece: &Piece) -> bool {
+    piece.cells().iter().all(|&(r, c)| {
+        c >= 0
+            && c < BOARD_WIDTH as i32
+            && r < BOARD_HEIGHT as i32
+            && (r < 0 || board[r as usize][c as usize] == EMPTY)
+    })
+}
+
+fn occupied(board: &Board, r: i32, c: i32) -> bool {
+    r < 0
+        || r >= BOARD_HEIGHT as i32
+        || c < 0
+        || c >= BOARD_WIDTH as i32
+        || board[r as usize][c as usize] != EMPTY
+}
+
+/// Mirrors `Game::try_rotate` closely enough to predict where a turn lands.
+fn rotate(board: &Board, piece: &Piece, turn: u8) -> Option<Piece> {
+    if piece.kind == KIND_O {
+        return None;
+    }
+    let to = (piece.rotation + turn) % 4;
+    piece
+        .system
+        .kicks(piece.kind, piece.rotation, to)
+        .iter()
+        .map(|&[dc, dr]| Piece {
+            rotation: to,
+            row: piece.row + dr,
+            col: piece.col + dc,
+            ..*piece
+        })
+        .find(|test| fits(board, test))
+}
+
+fn drop_distance(board: &Board, piece: &Piece) -> i32 {
+    let mut test = *piece;
+    while fits(board, &Piece { row: test.row + 1, ..test }) {
+        test.row += 1;
+    }
+    test.row - piece.row
+}
+
+/// Every resting spot reachable by turning at the top, sliding, and dropping,
+/// plus a final turn at the bottom so T pieces can spin into slots.
+fn placements(board: &Board, start: Piece, allow_180: bool) -> Vec<Placement> {
+    let turns: &[(u8, Option<GameInput>)] = if allow_180 {
+        &[(0, None), (1, Some(GameInput::RotateCw)), (3, Some(GameInput::RotateCcw)), (2, Some(GameInput::Rotate180))]
+    } else {
+        &[(0, None), (1, Some(GameInput::RotateCw)), (3, Some(GameInput::RotateCcw))]
+    };
+
+    let mut out: Vec<Placement> = Vec::new();
+    for &(turn, input) in turns {
+        let rotated = match turn {
+            0 => Some(start),
+            t => rotate(board, &start, t),
+        };
+        let Some(rotated) = rotated else { continue };
+        let base: Vec<GameInput> = input.into_iter().collect();
+
+        for (dc, step) in [(0, None), (-1, Some(GameInput::MoveLeft)), (1, Some(GameInput::MoveRight))] {
+            let mut piece = rotated;
+            let mut inputs = base.clone();
+            loop {
+                let dist = drop_distance(board, &piece);
+                let landed = Piece { row: piece.row + dist, ..piece };
+                let mut hard = inputs.clone();
+                hard.push(GameInput::HardDrop);
+                push_unique(&mut out, Placement { piece: landed, rotated_last: false, inputs: hard });
+
+                if piece.kind == KIND_T {
+                    for (spin_turn, spin) in [(1, GameInput::RotateCw), (3, GameInput::RotateCcw)] {
+                        let Some(spun) = rotate(board, &landed, spin_turn) else { continue };
+                        let fall = drop_distance(board, &spun);
+                        let mut spin_inputs = inputs.clone();
+                        spin_inputs.extend(std::iter::repeat_n(GameInput::SoftDrop, dist as usize));
+                        spin_inputs.push(spin);
+                        spin_inputs.push(GameInput::HardDrop);
+                        push_unique(
+                            &mut out,
+                            Placement {
+                                piece: Piece { row: spun.row + fall, ..spun },
+                                rotated_last: fall == 0,
+                                inputs: spin_inputs,
+                            },
+                        );
+                    }
+                }
+
+                let Some(step) = step else { break };
+                let moved = Piece { col: piece.col + dc, ..piece };
+                if !fits(board, &moved) {
+                    break;
+                }
+                piece = moved;
+                inputs.push(step);
+            }
+        }
+    }
+    out
+}
+
+fn push_unique(out: &mut Vec<Placement>, placement: Placement) {
+    let mut cells = placement.piece.cells();
+    cells.sort();
+    let duplicate = out.iter().any(|o| {
+        let mut other = o.piece.cells();
+        other.sort();
+        other == cells && o.rotated_last == placement.rotated_last
+    });
+    if !duplicate {
+        out.push(placement);
+    }
+}
+
+/// Locks `placement` onto a copy of the node's board and scores the attack
+/// it would send on `table`.
+fn place(node: &Node, placement: &Placement, table: AttackTable) -> Node {
+    let piece = &placement.piece;
+    let (is_tspin, is_mini) = if piece.kind == KIND_T && placement.rotated_last {
+        tspin_at(piece, |r, c| occupied(&node.board, r, c))
+    } else {
+        (false, false)
+    };
+
+    let mut board = node.board;
+    for (r, c) in piece.cells() {
+        if r >= 0 {
+            board[r as usize][c as usize] = (piece.kind + 1) as u8;
+        }
+    }
+    let full: Vec<usize> = (0..BOARD_HEIGHT)
+        .filter(|&r| board[r].iter().all(|&c| c != EMPTY))
+        .collect();
+    let cleared = full.len() as u32;
+
+    let mut child = Node {
+        board,
+        back_to_back: node.back_to_back,
+        b2b_chain: node.b2b_chain,
+        combo: -1,
+        gained: node.gained,
+        score: 0.0,
+        inputs: node.inputs.clone(),
+    };
+    if piece.cells().iter().all(|&(r, _)| r < BUFFER_HEIGHT as i32) {
+        child.gained -= 1000.0;
+    }
+    if cleared == 0 {
+        return child;
+    }
+
+    let mut rest = [[EMPTY; BOARD_WIDTH]; BOARD_HEIGHT];
+    let mut dest = BOARD_HEIGHT;
+    for r in (0..BOARD_HEIGHT).rev() {
+        if !full.contains(&r) {
+            dest -= 1;
+            rest[dest] = board[r];
+        }
+    }
+    let is_all_clear = rest.iter().all(|row| row.iter().all(|&c| c == EMPTY));
+    let difficult = cleared == 4 || is_tspin;
+    let b2b_chain = if difficult && node.back_to_back { node.b2b_chain + 1 } else { 0 };
+
+    let action = ClearAction {
+        label: String::new(),
+        points: 0,
+        cleared_lines: cleared,
+        is_tspin,
+        is_mini,
+        is_back_to_back: difficult && node.back_to_back,
+        combo: node.combo + 1,
+        is_all_clear,
+        garbage_lines: 0,
+        b2b_chain,
+        b2b_broken: if difficult { 0 } else { node.b2b_chain },
+    };
+    let attack = table.attack(&action);
+    child.board = rest;
+    child.b2b_chain = b2b_chain;
+    child.combo = node.combo + 1;
+    child.back_to_back = difficult;
+    child.gained += if attack > 0 {
+        attack as f64 * W_ATTACK
+    } else {
+        cleared as f64 * W_BURN
+    };
+    child
+}
+
+/// Judges a board by its shape: stack height, holes and the blocks piled on
+/// them, surface bumpiness, and open T-spin slots.
+fn evaluate(board: &Board, back_to_back: bool) -> f64 {
+    let mut heights = [0i32; BOARD_WIDTH];
+    let mut holes = 0;
+    let mut covered = 0;
+    for c in 0..BOARD_WIDTH {
+        let top = (0..BOARD_HEIGHT).find(|&r| board[r][c] != EMPTY);
+        if let Some(top) = top {
+            heights[c] = (BOARD_HEIGHT - top) as i32;
+            for (depth, row) in board[top + 1..].iter().enumerate() {
+                if row[c] == EMPTY {
+                    holes += 1;
+                    covered += depth as i32 + 1;
+                }
+            }
+        }
+    }
+    let aggregate: i32 = heights.iter().sum();
+    let bumpiness: i32 = heights.windows(2).map(|w| (w[0] - w[1]).abs()).sum();
+    let max_height = heights.iter().copied().max().unwrap_or(0);
+
+    let mut score = aggregate as f64 * W_HEIGHT
+        + holes as f64 * W_HOLES
+        + covered.min(40) as f64 * W_COVERED
+        + bumpiness as f64 * W_BUMPINESS
+        + (max_height - SAFE_HEIGHT).max(0) as f64 * W_DANGER;
+    if max_height < SAFE_HEIGHT + 2 {
+        score += t_slots(board).min(1) as f64 * W_TSLOT;
+    }
+    if back_to_back {
+        score += W_B2B;
+    }
+    score
+}
+
+/// Counts T-spin double setups: a T-shaped cavity with an overhang on one side
+/// and a nearly full row under its flat part.
+fn t_slots(board: &Board) -> usize {
+    let filled = |r: usize, c: usize| board[r][c] != EMPTY;
+    let mut slots = 0;
+    for r in 1..BOARD_HEIGHT - 1 {
+        for c in 1..BOARD_WIDTH - 1 {
+            let cavity = !filled(r, c - 1) && !filled(r, c) && !filled(r, c + 1) && !filled(r - 1, c);
+            let stem = filled(r + 1, c - 1) && !filled(r + 1, c) && filled(r + 1, c + 1);
+            let floor = r + 2 >= BOARD_HEIGHT || filled(r + 2, c);
+            let overhang = filled(r - 1, c - 1) != filled(r - 1, c + 1);
+            let stem_row_full = (0..BOARD_WIDTH).filter(|&x| x != c).all(|x| filled(r + 1, x));
+            if cavity && stem && floor && overhang && stem_row_full {
+                slots += 1;
+            }
+        }
+    }
+    slots
+}
@@ -0,0 +1,53 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Time source for everything in `Game` that waits: lock delay, ARE, the
+/// line-clear and garbage-rise animations, and the Ultra time limit.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The wall clock, used by interactive play.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when told to, for headless runs and tests.
+pub struct ManualClock {
+    now: Mutex<Instant>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::starting_at(Instant::now())
+    }
+
+    pub fn starting_at(start: Instant) -> Self {
+        Self {
+            now: Mutex::new(start),
+        }
+    }
+
+    pub fn advance(&self, dt: Duration) {
+        let mut now = self.now.lock().unwrap();
+        *now += dt;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+}
@@ -1,18 +1,186 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::game::piece::BOARD_WIDTH;
+use crate::game::rng::SeededRng;
 use crate::game::ClearAction;
 
+/// Chance, in percent, that each line of a messy attack after the first
+/// moves the hole.
+pub const MESSY_CHANGE_PERCENT: usize = 30;
+
+/// How fast the attack multiplier climbs once the margin time has passed:
+/// +0.01 per second, so +0.6 a minute.
+pub const MULTIPLIER_RAMP_PER_SEC: f64 = 0.01;
+
+/// Largest per-lock garbage cap short of no cap at all.
+pub const MAX_GARBAGE_CAP: u32 = 20;
+
+/// Margin times offered in the lobby run from one step up to the maximum.
+pub const MARGIN_STEP_SECS: u32 = 30;
+pub const MAX_MARGIN_SECS: u32 = 300;
+
+/// Where the holes go in the garbage lines an attack sends.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum GarbageStyle {
+    /// One hole per attack, in a fresh column each time.
+    #[default]
+    Clean,
+    /// Like clean, but each further line may move the hole.
+    Messy,
+    /// Every line has its hole somewhere other than the line below.
+    Cheese,
+    /// The same column for the whole game.
+    Column,
+}
+
+impl GarbageStyle {
+    pub const ALL: [GarbageStyle; 4] = [
+        GarbageStyle::Clean,
+        GarbageStyle::Messy,
+        GarbageStyle::Cheese,
+        GarbageStyle::Column,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GarbageStyle::Clean => "Cln",
+            GarbageStyle::Messy => "Mess",
+            GarbageStyle::Cheese => "Chz",
+            GarbageStyle::Column => "Col",
+        }
+    }
+}
+
+/// How garbage is exchanged in a Versus match. The host picks these in the
+/// lobby and both sides play by them.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GarbageRules {
+    /// Line clears cancel pending garbage before anything is sent.
+    pub cancel: bool,
+    pub style: GarbageStyle,
+    pub table: AttackTable,
+    /// How long an incoming attack waits before it can rise.
+    pub delay_ms: u32,
+    /// Most garbage lines that rise on a single lock; `None` for no limit.
+    pub cap: Option<u32>,
+    /// Match time after which attacks are multiplied by a slowly growing
+    /// factor; `None` keeps them as they are.
+    pub margin_secs: Option<u32>,
+    /// Seeds the hole generator. The host deals a fresh one with each
+    /// `StartMatch`, so it is neither saved nor sent with the settings.
+    #[serde(skip)]
+    pub seed: u64,
+}
+
+impl Default for GarbageRules {
+    fn default() -> Self {
+        Self {
+            cancel: true,
+            style: GarbageStyle::Clean,
+            table: AttackTable::Guideline,
+            delay_ms: 0,
+            cap: None,
+            margin_secs: None,
+            seed: 0,
+        }
+    }
+}
+
+impl GarbageRules {
+    /// The attack multiplier `elapsed` into a game.
+    pub fn multiplier(&self, elapsed: Duration) -> f64 {
+        let Some(margin) = self.margin_secs else {
+            return 1.0;
+        };
+        let past = elapsed.saturating_sub(Duration::from_secs(margin as u64));
+        1.0 + past.as_secs_f64() * MULTIPLIER_RAMP_PER_SEC
+    }
+
+    /// `attack` under the multiplier, rounded to whole lines.
+    pub fn scale(&self, attack: u32, elapsed: Duration) -> u32 {
+        (attack as f64 * self.multiplier(elapsed)).round() as u32
+    }
+
+    /// The attack `action` makes under these rules, `elapsed` into a game.
+    pub fn attack(&self, action: &ClearAction, elapsed: Duration) -> u32 {
+        self.scale(self.table.attack(action), elapsed)
+    }
+}
+
+/// Picks the holes for outgoing attacks. Both players of a match seed theirs
+/// alike, so each faces the same run of holes for the same attacks.
+pub struct HoleGenerator {
+    style: GarbageStyle,
+    rng: SeededRng,
+    hole: usize,
+}
+
+impl HoleGenerator {
+    pub fn new(style: GarbageStyle, seed: u64) -> Self {
+        let mut rng = SeededRng::new(seed);
+        let hole = rng.below(BOARD_WIDTH);
+        Self { style, rng, hole }
+    }
+
+    /// Any column but the current hole.
+    fn move_hole(&mut self) {
+        let step = 1 + self.rng.below(BOARD_WIDTH - 1);
+        self.hole = (self.hole + step) % BOARD_WIDTH;
+    }
+
+    /// Splits an attack of `lines` into runs that share a hole, bottom run
+    /// first.
+    pub fn deal(&mut self, lines: u32) -> Vec<GarbageEvent> {
+        let mut runs: Vec<GarbageEvent> = Vec::new();
+        for line in 0..lines {
+            let moves = match self.style {
+                GarbageStyle::Clean => line == 0,
+                GarbageStyle::Messy => line == 0 || self.rng.below(100) < MESSY_CHANGE_PERCENT,
+                GarbageStyle::Cheese => true,
+                GarbageStyle::Column => false,
+            };
+            if moves {
+                self.move_hole();
+            }
+            match runs.last_mut() {
+                Some(run) if run.hole_column == self.hole => run.lines += 1,
+                _ => runs.push(GarbageEvent {
+                    lines: 1,
+                    hole_column: self.hole,
+                }),
+            }
+        }
+        runs
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct GarbageEvent {
     pub lines: u32,
     pub hole_column: usize,
 }
 
+#[derive(Default)]
 pub struct GarbageQueue {
     pub pending: Vec<GarbageEvent>,
+    /// When each pending event may rise, oldest first like `pending`.
+    ready_at: Vec<Instant>,
+    delay: Duration,
 }
 
 impl GarbageQueue {
     pub fn new() -> Self {
+        Self::with_delay(Duration::ZERO)
+    }
+
+    /// A queue whose attacks wait `delay` before they are ready to rise.
+    pub fn with_delay(delay: Duration) -> Self {
         Self {
             pending: Vec::new(),
+            ready_at: Vec::new(),
+            delay,
         }
     }
 
@@ -20,11 +188,29 @@ impl GarbageQueue {
         self.pending.iter().map(|e| e.lines).sum()
     }
 
+    /// Lines whose delay has run out by `now`.
+    pub fn ready_lines(&self, now: Instant) -> u32 {
+        self.pending
+            .iter()
+            .zip(&self.ready_at)
+            .take_while(|(_, at)| **at <= now)
+            .map(|(e, _)| e.lines)
+            .sum()
+    }
+
     pub fn push(&mut self, event: GarbageEvent) {
+        self.push_at(event, Instant::now());
+    }
+
+    /// Queues `event` as arriving at `now`, read from the receiving game's
+    /// clock.
+    pub fn push_at(&mut self, event: GarbageEvent, now: Instant) {
         self.pending.push(event);
+        self.ready_at.push(now + self.delay);
     }
 
     pub fn cancel(&mut self, mut attack: u32) -> u32 {
+        let before = self.pending.len();
         self.pending.retain_mut(|event| {
             if attack == 0 {
                 return true;
@@ -38,14 +224,45 @@ impl GarbageQueue {
                 true
             }
         });
+        // Cancelling eats the oldest events first.
+        self.ready_at.drain(..before - self.pending.len());
         attack
     }
 
-    pub fn drain_all(&mut self) -> Vec<GarbageEvent> {
-        std::mem::take(&mut self.pending)
+    /// What is left of `attack` to send once `rules` have had their say:
+    /// cancelled against pending garbage, or sent in full.
+    pub fn counter(&mut self, attack: u32, rules: GarbageRules) -> u32 {
+        if rules.cancel {
+            self.cancel(attack)
+        } else {
+            attack
+        }
+    }
+
+    /// Takes the garbage that is ready by `now`, oldest first, up to `cap`
+    /// lines. An event cut by the cap leaves its remainder queued.
+    pub fn take_ready(&mut self, now: Instant, cap: Option<u32>) -> Vec<GarbageEvent> {
+        let mut room = cap.unwrap_or(u32::MAX);
+        let mut taken = Vec::new();
+        while room > 0 && self.ready_at.first().is_some_and(|&at| at <= now) {
+            let event = &mut self.pending[0];
+            if event.lines > room {
+                event.lines -= room;
+                taken.push(GarbageEvent {
+                    lines: room,
+                    hole_column: event.hole_column,
+                });
+                break;
+            }
+            room -= event.lines;
+            self.ready_at.remove(0);
+            taken.push(self.pending.remove(0));
+        }
+        taken
     }
 }
 
+/// Bonus lines by combo count on the guideline table.
 fn combo_bonus(combo: i32) -> u32 {
     match combo {
         0..=1 => 0,
@@ -57,16 +274,10 @@ fn combo_bonus(combo: i32) -> u32 {
     }
 }
 
-pub fn calculate_attack(action: &ClearAction) -> u32 {
-    if action.cleared_lines == 0 {
-        return 0;
-    }
-
-    if action.is_all_clear {
-        return 10;
-    }
-
-    let base = if action.is_tspin {
+/// Lines for the clear itself, before back-to-back, combo and all clear
+/// bonuses. Every table but lines-minus-one starts from these.
+fn base_attack(action: &ClearAction) -> u32 {
+    if action.is_tspin {
         if action.is_mini {
             match action.cleared_lines {
                 1 => 0,
@@ -89,10 +300,126 @@ pub fn calculate_attack(action: &ClearAction) -> u32 {
             4 => 4,
             _ => 0,
         }
-    };
+    }
+}
+
+/// The guideline attack for a clear.
+pub fn calculate_attack(action: &ClearAction) -> u32 {
+    if action.cleared_lines == 0 {
+        return 0;
+    }
+
+    if action.is_all_clear {
+        return 10;
+    }
 
     let b2b = if action.is_back_to_back { 1 } else { 0 };
-    let combo = combo_bonus(action.combo);
+    base_attack(action) + b2b + combo_bonus(action.combo)
+}
+
+/// Shortest broken back-to-back chain that releases a surge on the TETR.IO
+/// table.
+pub const TETRIO_SURGE_AT: u32 = 4;
+
+/// How many lines a clear sends. The host picks one in the lobby so both
+/// sides count attack the same way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum AttackTable {
+    /// Guideline lines with +1 back-to-back and a stepped combo bonus.
+    #[default]
+    Guideline,
+    /// Back-to-back bonuses that grow with the chain, a combo multiplier and
+    /// a surge of extra lines when a long chain breaks.
+    Tetrio,
+    /// Guideline lines with Puyo Puyo Tetris's steeper combo table.
+    PuyoTetris,
+    /// One line fewer than were cleared, and nothing else.
+    LinesMinusOne,
+}
 
-    base + b2b + combo
+impl AttackTable {
+    pub const ALL: [AttackTable; 4] = [
+        AttackTable::Guideline,
+        AttackTable::Tetrio,
+        AttackTable::PuyoTetris,
+        AttackTable::LinesMinusOne,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AttackTable::Guideline => "Std",
+            AttackTable::Tetrio => "TIO",
+            AttackTable::PuyoTetris => "PPT",
+            AttackTable::LinesMinusOne => "L-1",
+        }
+    }
+
+    pub fn attack(self, action: &ClearAction) -> u32 {
+        if action.cleared_lines == 0 {
+            return 0;
+        }
+        match self {
+            AttackTable::Guideline => calculate_attack(action),
+            AttackTable::Tetrio => tetrio_attack(action),
+            AttackTable::PuyoTetris => ppt_attack(action),
+            AttackTable::LinesMinusOne => action.cleared_lines - 1,
+        }
+    }
+}
+
+/// Back-to-back bonus by chain length on the TETR.IO table.
+fn tetrio_b2b_bonus(chain: u32) -> u32 {
+    match chain {
+        0 => 0,
+        1..=2 => 1,
+        3..=7 => 2,
+        8..=23 => 3,
+        24..=66 => 4,
+        67..=184 => 5,
+        185..=503 => 6,
+        504..=1369 => 7,
+        _ => 8,
+    }
+}
+
+fn tetrio_attack(action: &ClearAction) -> u32 {
+    let b2b = if action.is_back_to_back { tetrio_b2b_bonus(action.b2b_chain) } else { 0 };
+    let lines = (base_attack(action) + b2b) as f64;
+    let combo = action.combo.max(0) as f64;
+    // Each combo step adds a quarter of the clear; clears worth nothing
+    // still build a little attack from long combos.
+    let mut attack = if lines > 0.0 {
+        (lines * (1.0 + 0.25 * combo)).floor() as u32
+    } else {
+        (1.0 + 1.25 * combo).ln().floor() as u32
+    };
+    if action.is_all_clear {
+        attack += 10;
+    }
+    if action.b2b_broken >= TETRIO_SURGE_AT {
+        attack += action.b2b_broken;
+    }
+    attack
+}
+
+/// Bonus lines by combo count on the Puyo Puyo Tetris table.
+fn ppt_combo_bonus(combo: i32) -> u32 {
+    match combo {
+        ..=0 => 0,
+        1..=2 => 1,
+        3..=4 => 2,
+        5..=6 => 3,
+        7..=9 => 4,
+        _ => 5,
+    }
+}
+
+fn ppt_attack(action: &ClearAction) -> u32 {
+    let b2b = match action.is_back_to_back {
+        true if action.is_tspin && !action.is_mini && action.cleared_lines == 3 => 2,
+        true => 1,
+        false => 0,
+    };
+    let all_clear = if action.is_all_clear { 10 } else { 0 };
+    base_attack(action) + b2b + ppt_combo_bonus(action.combo) + all_clear
 }
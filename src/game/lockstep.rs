@@ -0,0 +1,325 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::game::clock::ManualClock;
+use crate::game::garbage::{GarbageEvent, GarbageQueue, GarbageRules, HoleGenerator};
+use crate::game::rng::SeededRng;
+use crate::game::settings::Settings;
+
+use super::{Game, GameInput, GameMode};
+
+/// How long past its delay garbage may wait before holding it back counts
+/// against a player. Covers the frame a lock lands in and the gap between
+/// when the queue and the game clock are read.
+pub const RISE_SLACK: Duration = Duration::from_millis(250);
+
+/// Something that changed a player's game, in the order it happened.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum LockstepAction {
+    Input(GameInput),
+    /// An attack joined the garbage queue.
+    Incoming(GarbageEvent),
+    /// This many lines from the front of the queue began to rise.
+    Rise(u32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LockstepEvent {
+    /// The game's elapsed time when it happened.
+    pub at: Duration,
+    pub action: LockstepAction,
+}
+
+/// The events one side has been through since its last batch, and a hash of
+/// its game after the last of them.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InputBatch {
+    pub events: Vec<LockstepEvent>,
+    /// Left out while lines are clearing or garbage is rising, when the
+    /// board is part way between two states.
+    pub hash: Option<u64>,
+    /// The sender's game is over and nothing follows.
+    pub last: bool,
+}
+
+/// What checking the other side's game turned up.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Mismatch {
+    /// Their inputs lead to a different game than the one they have.
+    Board,
+    /// An attack their clears do not add up to.
+    Attack,
+    /// Garbage we sent that never reached their queue, or not as sent.
+    Incoming,
+    /// Garbage that rose without a lock, beyond the cap or from nowhere.
+    Garbage,
+    /// Garbage that was due to rise and did not.
+    HeldGarbage,
+    /// Inputs after their board had topped out.
+    PlayedOn,
+    /// A piece input their game could not take then, such as a second drop
+    /// during entry delay or a line clear.
+    OutOfTurn,
+    /// Their inputs stopped before the game was over.
+    Incomplete,
+}
+
+impl Mismatch {
+    /// Whether the two games look to have drifted apart rather than one side
+    /// sending things its game never did.
+    pub fn is_desync(self) -> bool {
+        matches!(self, Mismatch::Board | Mismatch::Incomplete)
+    }
+
+    pub fn title(self) -> &'static str {
+        if self.is_desync() { "DESYNC" } else { "CHEATING" }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Mismatch::Board => "Boards differ",
+            Mismatch::Attack => "Unearned attack",
+            Mismatch::Incoming => "Garbage went missing",
+            Mismatch::Garbage => "Garbage out of turn",
+            Mismatch::HeldGarbage => "Garbage held back",
+            Mismatch::PlayedOn => "Played after top out",
+            Mismatch::OutOfTurn => "Input out of turn",
+            Mismatch::Incomplete => "Inputs stopped",
+        }
+    }
+}
+
+/// The piece seed for game `game` of a verified match. Both players get the
+/// same pieces, so each can rebuild the other's game.
+pub fn piece_seed(match_seed: u64, game: u64) -> u64 {
+    SeededRng::new(match_seed.wrapping_add(game)).next_u64()
+}
+
+impl Game {
+    /// FNV-1a over what the inputs decide: the stack, the falling piece, the
+    /// hold slot and the lines cleared.
+    pub fn state_hash(&self) -> u64 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut eat = |byte: u8| {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        };
+        self.board.iter().flatten().for_each(|&cell| eat(cell));
+        let piece = &self.current;
+        eat(piece.kind as u8);
+        eat(piece.rotation);
+        piece.row.to_le_bytes().into_iter().for_each(&mut eat);
+        piece.col.to_le_bytes().into_iter().for_each(&mut eat);
+        eat(self.hold.map_or(u8::MAX, |kind| kind as u8));
+        self.lines.to_le_bytes().into_iter().for_each(&mut eat);
+        hash
+    }
+}
+
+/// Our own game's events, waiting to go out in the next batch.
+#[derive(Default)]
+pub struct LockstepLog {
+    events: Vec<LockstepEvent>,
+}
+
+impl LockstepLog {
+    pub fn push(&mut self, game: &Game, action: LockstepAction) {
+        self.events.push(LockstepEvent {
+            at: game.elapsed,
+            action,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Everything logged since the last batch, stamped with `game`'s hash.
+    pub fn batch(&mut self, game: &Game, last: bool) -> InputBatch {
+        let settled = !game.is_animating() && !game.is_garbage_animating();
+        InputBatch {
+            events: std::mem::take(&mut self.events),
+            hash: settled.then(|| game.state_hash()),
+            last,
+        }
+    }
+}
+
+/// Plays the other side's events on a copy of their game and holds what
+/// they claim up against it: their board, every attack they send and the
+/// garbage they take.
+pub struct Verifier {
+    game: Game,
+    clock: Arc<ManualClock>,
+    rules: GarbageRules,
+    /// Their garbage queue, fed by the attacks their stream says arrived.
+    queue: GarbageQueue,
+    holes: HoleGenerator,
+    /// Our attacks, not yet seen arriving in their stream.
+    sent: VecDeque<GarbageEvent>,
+    /// Attack their clears earned that has not come in yet.
+    earned: VecDeque<GarbageEvent>,
+    /// Attacks they sent that their stream has not caught up with.
+    claimed: VecDeque<GarbageEvent>,
+    /// After a lock that cleared nothing, the lines that had to rise.
+    rise_due: Option<u32>,
+    finished: bool,
+    failure: Option<Mismatch>,
+}
+
+impl Verifier {
+    /// `settings` are the game settings both sides play with; `seed` and
+    /// `hole_seed` are the ones the other side's game was built from.
+    pub fn new(settings: &Settings, seed: u64, hole_seed: u64) -> Self {
+        let clock = Arc::new(ManualClock::new());
+        Self {
+            game: Game::with_manual_clock(GameMode::Versus, settings, seed, clock.clone()),
+            clock,
+            rules: settings.garbage,
+            queue: GarbageQueue::with_delay(Duration::from_millis(settings.garbage.delay_ms as u64)),
+            holes: HoleGenerator::new(settings.garbage.style, hole_seed),
+            sent: VecDeque::new(),
+            earned: VecDeque::new(),
+            claimed: VecDeque::new(),
+            rise_due: None,
+            finished: false,
+            failure: None,
+        }
+    }
+
+    pub fn failure(&self) -> Option<Mismatch> {
+        self.failure
+    }
+
+    /// Their last batch is in and checked.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// We sent them `attack`.
+    pub fn sent(&mut self, attack: GarbageEvent) {
+        self.sent.push_back(attack);
+    }
+
+    /// They sent us `attack`.
+    pub fn claim(&mut self, attack: GarbageEvent) {
+        self.claimed.push_back(attack);
+        self.settle();
+    }
+
+    pub fn feed(&mut self, batch: &InputBatch) {
+        if self.failure.is_some() || self.finished {
+            return;
+        }
+        for event in &batch.events {
+            if let Err(mismatch) = self.play(*event) {
+                self.failure = Some(mismatch);
+                return;
+            }
+        }
+        if batch.hash.is_some_and(|hash| hash != self.game.state_hash()) {
+            self.failure = Some(Mismatch::Board);
+            return;
+        }
+        self.settle();
+        if batch.last {
+            self.finished = true;
+            if self.rise_due.is_some_and(|lines| lines > 0) {
+                self.failure = Some(Mismatch::HeldGarbage);
+            } else if !self.claimed.is_empty() {
+                self.failure = Some(Mismatch::Attack);
+            }
+        }
+    }
+
+    /// Gives up on a last batch that never came.
+    pub fn give_up(&mut self) {
+        if !self.finished && self.failure.is_none() {
+            self.failure = Some(Mismatch::Incomplete);
+        }
+    }
+
+    /// Pairs claimed attacks with earned ones, oldest first.
+    fn settle(&mut self) {
+        while !self.claimed.is_empty() && !self.earned.is_empty() {
+            if self.claimed.pop_front() != self.earned.pop_front() {
+                self.failure.get_or_insert(Mismatch::Attack);
+                return;
+            }
+        }
+    }
+
+    fn play(&mut self, event: LockstepEvent) -> Result<(), Mismatch> {
+        let now = self.game.game_start + event.at;
+        self.clock.advance(now.saturating_duration_since(self.game.now()));
+        self.game.elapsed = event.at;
+
+        let due = self.rise_due.take();
+        if due.is_some_and(|lines| lines > 0) && !matches!(event.action, LockstepAction::Rise(_)) {
+            return Err(Mismatch::HeldGarbage);
+        }
+
+        match event.action {
+            LockstepAction::Input(input) => {
+                if self.game.game_over {
+                    return Err(Mismatch::PlayedOn);
+                }
+                if !self.game.apply_input(input) {
+                    return Err(Mismatch::OutOfTurn);
+                }
+                if matches!(input, GameInput::HardDrop | GameInput::Lock) {
+                    self.after_lock(now);
+                }
+            }
+            LockstepAction::Incoming(attack) => {
+                if self.sent.pop_front() != Some(attack) {
+                    return Err(Mismatch::Incoming);
+                }
+                self.queue.push_at(attack, now);
+            }
+            // A lock that topped out may still start a rise nobody will see.
+            LockstepAction::Rise(_) if self.game.game_over => {}
+            LockstepAction::Rise(lines) => {
+                let Some(due) = due else {
+                    return Err(Mismatch::Garbage);
+                };
+                let over_cap = self.rules.cap.is_some_and(|cap| lines > cap);
+                if lines < due {
+                    return Err(Mismatch::HeldGarbage);
+                }
+                if over_cap || lines > self.queue.total_pending() {
+                    return Err(Mismatch::Garbage);
+                }
+                // Everything queued so far counts as ready; how long it sat
+                // was checked through `due`.
+                let rising = self.queue.take_ready(now + self.queue_delay(), Some(lines));
+                self.game.begin_garbage_rise(rising);
+                self.game.finish_garbage_rise();
+            }
+        }
+        Ok(())
+    }
+
+    /// The same bookkeeping `outgoing_attack` does on the other side.
+    fn after_lock(&mut self, now: Instant) {
+        let game = &self.game;
+        match &game.last_action {
+            Some(action) if game.combo >= 0 && action.cleared_lines > 0 => {
+                let attack = self.rules.attack(action, game.elapsed);
+                let remaining = self.queue.counter(attack, self.rules);
+                self.earned.extend(self.holes.deal(remaining));
+            }
+            _ if game.game_over => {}
+            _ => {
+                let owed = self.queue.ready_lines(now.checked_sub(RISE_SLACK).unwrap_or(now));
+                self.rise_due = Some(self.rules.cap.map_or(owed, |cap| owed.min(cap)));
+            }
+        }
+    }
+
+    fn queue_delay(&self) -> Duration {
+        Duration::from_millis(self.rules.delay_ms as u64)
+    }
+}
@@ -2,20 +2,37 @@ mod animation;
 mod board;
 mod movement;
 mod scoring;
+pub mod step;
 
+pub mod bot;
+pub mod clock;
 pub mod garbage;
+pub mod lockstep;
 pub mod piece;
+pub mod randomizer;
 pub mod records;
+pub mod replay;
+pub mod rng;
+pub mod rotation;
+pub mod royale;
+pub mod set;
 pub mod settings;
+pub mod stats;
 pub mod types;
 pub use types::*;
 
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests;
 
+use std::collections::VecDeque;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use crate::game::clock::{ManualClock, SharedClock};
 use crate::game::piece::*;
+use crate::game::randomizer::Randomizer;
+use crate::game::rotation::RotationSystem;
 
 pub struct Game {
     pub board: [[u8; BOARD_WIDTH]; BOARD_HEIGHT],
@@ -23,7 +40,8 @@ pub struct Game {
     pub next_queue: Vec<usize>,
     pub hold: Option<usize>,
     pub hold_used: bool,
-    bag: Bag,
+    randomizer: Box<dyn Randomizer>,
+    pub seed: u64,
     pub score: u32,
     pub lines: u32,
     pub level: u32,
@@ -32,6 +50,8 @@ pub struct Game {
     pub last_move: LastMove,
     pub combo: i32,
     pub back_to_back: bool,
+    /// Back-to-back bonuses in a row, 0 until the second difficult clear.
+    pub b2b_chain: u32,
     pub last_action: Option<ClearAction>,
     pub last_action_time: Instant,
     pub lock_delay: Option<Instant>,
@@ -48,10 +68,18 @@ pub struct Game {
     pub ghost_enabled: bool,
     pub line_clear_anim_enabled: bool,
     pub next_count: usize,
-    pub srs_enabled: bool,
+    pub rotation_system: RotationSystem,
+    pub rotate_180_enabled: bool,
     pub hold_enabled: bool,
     pub lock_delay_ms: u32,
     pub move_reset: Option<u32>,
     pub move_reset_count: u32,
     pub garbage_rise_anim: Option<GarbageRiseAnimation>,
+    clock: SharedClock,
+    /// The clock `step` advances; installed on the first call if the game was
+    /// not built with one.
+    step_clock: Option<Arc<ManualClock>>,
+    /// Inputs given to `step` that are waiting for the next piece.
+    step_inputs: VecDeque<GameInput>,
+    gravity_timer: Instant,
 }
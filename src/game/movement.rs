@@ -1,4 +1,4 @@
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use crate::game::piece::*;
 
@@ -9,7 +9,7 @@ impl Game {
         if self.lock_delay.is_some() {
             if self.is_on_ground() {
                 if self.move_reset.is_none_or(|limit| self.move_reset_count < limit) {
-                    self.lock_delay = Some(Instant::now());
+                    self.lock_delay = Some(self.now());
                     self.move_reset_count += 1;
                 }
             } else {
@@ -37,40 +37,40 @@ impl Game {
             return;
         }
 
-        if self.srs_enabled {
-            let from = self.current.rotation;
-            let to = new_rotation;
-            let kick_table = if self.current.kind == 0 {
-                &KICK_I
-            } else {
-                &KICK_JLTSZ
-            };
-            let idx = kick_index(from, to);
-            let kicks = &kick_table[idx];
-
-            for &[dc, dr] in kicks {
-                let mut test = self.current;
-                test.rotation = to;
-                test.col += dc;
-                test.row += dr;
-                if self.fits(&test) {
-                    self.current = test;
-                    self.last_move = super::LastMove::Rotate;
-                    self.refresh_lock_delay();
-                    return;
-                }
-            }
-        } else {
+        let system = self.current.system;
+        let kicks = system.kicks(self.current.kind, self.current.rotation, new_rotation);
+
+        for (i, &[dc, dr]) in kicks.iter().enumerate() {
             let mut test = self.current;
             test.rotation = new_rotation;
+            test.col += dc;
+            test.row += dr;
             if self.fits(&test) {
                 self.current = test;
                 self.last_move = super::LastMove::Rotate;
                 self.refresh_lock_delay();
+                return;
+            }
+            if i == 0
+                && system.center_column_rule(self.current.kind)
+                && self.blocked_in_center_column(&test)
+            {
+                return;
             }
         }
     }
 
+    /// Whether the first blocked cell of `target`, in reading order, lies in
+    /// the centre column of the piece's bounding box.
+    fn blocked_in_center_column(&self, target: &Piece) -> bool {
+        let mut cells = target.cells();
+        cells.sort();
+        cells
+            .iter()
+            .find(|&&(r, c)| self.is_occupied(r, c))
+            .is_some_and(|&(_, c)| c == target.col)
+    }
+
     pub fn rotate_cw(&mut self) {
         let new_rot = (self.current.rotation + 1) % 4;
         self.try_rotate(new_rot);
@@ -81,6 +81,15 @@ impl Game {
         self.try_rotate(new_rot);
     }
 
+    /// Does nothing unless 180° rotation is enabled in the settings.
+    pub fn rotate_180(&mut self) {
+        if !self.rotate_180_enabled {
+            return;
+        }
+        let new_rot = (self.current.rotation + 2) % 4;
+        self.try_rotate(new_rot);
+    }
+
     pub fn hard_drop(&mut self) -> u32 {
         let mut cells_dropped = 0u32;
         while self.move_piece(1, 0) {
@@ -107,14 +116,14 @@ impl Game {
         if g >= 20.0 {
             while self.move_piece(1, 0) {}
             if self.lock_delay.is_none() {
-                self.lock_delay = Some(Instant::now());
+                self.lock_delay = Some(self.now());
             }
         } else if g >= 1.0 {
             let rows = g.floor() as i32;
             for _ in 0..rows {
                 if !self.move_piece(1, 0) {
                     if self.lock_delay.is_none() {
-                        self.lock_delay = Some(Instant::now());
+                        self.lock_delay = Some(self.now());
                     }
                     return;
                 }
@@ -122,7 +131,7 @@ impl Game {
         } else if !self.move_piece(1, 0)
             && self.lock_delay.is_none()
         {
-            self.lock_delay = Some(Instant::now());
+            self.lock_delay = Some(self.now());
         }
     }
 
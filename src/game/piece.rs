@@ -1,6 +1,6 @@
 use crossterm::style::Color;
-use rand::seq::SliceRandom;
-use rand::Rng;
+
+use crate::game::rotation::RotationSystem;
 
 pub const BOARD_WIDTH: usize = 10;
 pub const VISIBLE_HEIGHT: usize = 20;
@@ -12,107 +12,6 @@ pub const MAX_NEXT_COUNT: usize = 6;
 pub const KIND_O: usize = 1;
 pub const KIND_T: usize = 2;
 
-pub const PIECE_STATES: [[[[i32; 2]; 4]; 4]; 7] = [
-    // I
-    [
-        [[0, -1], [0, 0], [0, 1], [0, 2]],
-        [[-1, 1], [0, 1], [1, 1], [2, 1]],
-        [[1, -1], [1, 0], [1, 1], [1, 2]],
-        [[-1, 0], [0, 0], [1, 0], [2, 0]],
-    ],
-    // O
-    [
-        [[0, 0], [0, 1], [1, 0], [1, 1]],
-        [[0, 0], [0, 1], [1, 0], [1, 1]],
-        [[0, 0], [0, 1], [1, 0], [1, 1]],
-        [[0, 0], [0, 1], [1, 0], [1, 1]],
-    ],
-    // T
-    [
-        [[-1, 0], [0, -1], [0, 0], [0, 1]],
-        [[-1, 0], [0, 0], [0, 1], [1, 0]],
-        [[0, -1], [0, 0], [0, 1], [1, 0]],
-        [[-1, 0], [0, -1], [0, 0], [1, 0]],
-    ],
-    // S
-    [
-        [[-1, 0], [-1, 1], [0, -1], [0, 0]],
-        [[-1, 0], [0, 0], [0, 1], [1, 1]],
-        [[0, 0], [0, 1], [1, -1], [1, 0]],
-        [[-1, -1], [0, -1], [0, 0], [1, 0]],
-    ],
-    // Z
-    [
-        [[-1, -1], [-1, 0], [0, 0], [0, 1]],
-        [[-1, 1], [0, 0], [0, 1], [1, 0]],
-        [[0, -1], [0, 0], [1, 0], [1, 1]],
-        [[-1, 0], [0, -1], [0, 0], [1, -1]],
-    ],
-    // L
-    [
-        [[-1, 1], [0, -1], [0, 0], [0, 1]],
-        [[-1, 0], [0, 0], [1, 0], [1, 1]],
-        [[0, -1], [0, 0], [0, 1], [1, -1]],
-        [[-1, -1], [-1, 0], [0, 0], [1, 0]],
-    ],
-    // J
-    [
-        [[-1, -1], [0, -1], [0, 0], [0, 1]],
-        [[-1, 0], [-1, 1], [0, 0], [1, 0]],
-        [[0, -1], [0, 0], [0, 1], [1, 1]],
-        [[-1, 0], [0, 0], [1, -1], [1, 0]],
-    ],
-];
-
-pub const T_FRONT_CORNERS: [[[i32; 2]; 2]; 4] = [
-    [[-1, -1], [-1, 1]],  // 0
-    [[-1, 1], [1, 1]],    // R
-    [[1, -1], [1, 1]],    // 2
-    [[-1, -1], [1, -1]],  // L
-];
-pub const T_BACK_CORNERS: [[[i32; 2]; 2]; 4] = [
-    [[1, -1], [1, 1]],    // 0
-    [[-1, -1], [1, -1]],  // R
-    [[-1, -1], [-1, 1]],  // 2
-    [[-1, 1], [1, 1]],    // L
-];
-
-pub const KICK_JLTSZ: [[[i32; 2]; 5]; 8] = [
-    [[0, 0], [-1, 0], [-1, -1], [0, 2], [-1, 2]],
-    [[0, 0], [1, 0], [1, 1], [0, -2], [1, -2]],
-    [[0, 0], [1, 0], [1, 1], [0, -2], [1, -2]],
-    [[0, 0], [-1, 0], [-1, -1], [0, 2], [-1, 2]],
-    [[0, 0], [1, 0], [1, -1], [0, 2], [1, 2]],
-    [[0, 0], [-1, 0], [-1, 1], [0, -2], [-1, -2]],
-    [[0, 0], [-1, 0], [-1, 1], [0, -2], [-1, -2]],
-    [[0, 0], [1, 0], [1, -1], [0, 2], [1, 2]],
-];
-
-pub const KICK_I: [[[i32; 2]; 5]; 8] = [
-    [[0, 0], [-2, 0], [1, 0], [-2, 1], [1, -2]],
-    [[0, 0], [2, 0], [-1, 0], [2, -1], [-1, 2]],
-    [[0, 0], [-1, 0], [2, 0], [-1, -2], [2, 1]],
-    [[0, 0], [1, 0], [-2, 0], [1, 2], [-2, -1]],
-    [[0, 0], [2, 0], [-1, 0], [2, -1], [-1, 2]],
-    [[0, 0], [-2, 0], [1, 0], [-2, 1], [1, -2]],
-    [[0, 0], [1, 0], [-2, 0], [1, 2], [-2, -1]],
-    [[0, 0], [-1, 0], [2, 0], [-1, -2], [2, 1]],
-];
-
-pub fn kick_index(from: u8, to: u8) -> usize {
-    match (from, to) {
-        (0, 1) => 0,
-        (1, 0) => 1,
-        (1, 2) => 2,
-        (2, 1) => 3,
-        (2, 3) => 4,
-        (3, 2) => 5,
-        (3, 0) => 6,
-        (0, 3) => 7,
-        _ => 0,
-    }
-}
-
 pub const PIECE_COLORS: [Color; 7] = [
     Color::Cyan,
     Color::Yellow,
@@ -129,25 +28,22 @@ pub struct Piece {
     pub rotation: u8,
     pub row: i32,
     pub col: i32,
+    pub system: RotationSystem,
 }
 
 impl Piece {
-    pub fn new(kind: usize) -> Self {
-        let row = if kind == KIND_O {
-            BUFFER_HEIGHT as i32 - 1
-        } else {
-            BUFFER_HEIGHT as i32
-        };
+    pub fn new(kind: usize, system: RotationSystem) -> Self {
         Self {
             kind,
             rotation: 0,
-            row,
-            col: (BOARD_WIDTH as i32) / 2 - 1,
+            row: system.spawn_row(kind),
+            col: system.spawn_col(),
+            system,
         }
     }
 
-    pub fn blocks(&self) -> &[[i32; 2]; 4] {
-        &PIECE_STATES[self.kind][self.rotation as usize]
+    pub fn blocks(&self) -> &'static [[i32; 2]; 4] {
+        self.system.blocks(self.kind, self.rotation)
     }
 
     pub fn cells(&self) -> [(i32, i32); 4] {
@@ -158,26 +54,3 @@ impl Piece {
         out
     }
 }
-
-pub struct Bag {
-    queue: Vec<usize>,
-    use_bag: bool,
-}
-
-impl Bag {
-    pub fn new(use_bag: bool) -> Self {
-        Self { queue: Vec::new(), use_bag }
-    }
-
-    pub fn next(&mut self) -> usize {
-        if !self.use_bag {
-            return rand::thread_rng().gen_range(0..7);
-        }
-        if self.queue.is_empty() {
-            let mut bag = vec![0, 1, 2, 3, 4, 5, 6];
-            bag.shuffle(&mut rand::thread_rng());
-            self.queue = bag;
-        }
-        self.queue.pop().unwrap()
-    }
-}
@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::rng::SeededRng;
+
+const KIND_Z: usize = 4;
+const PIECE_KINDS: usize = 7;
+
+/// Source of the piece sequence. Every implementation draws only from its own
+/// `SeededRng`, so the same seed always yields the same pieces.
+pub trait Randomizer {
+    fn next(&mut self) -> usize;
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum RandomizerKind {
+    #[default]
+    SevenBag,
+    FourteenBag,
+    Random,
+    /// TGM: 4-piece history, up to 6 rolls to avoid a recent piece.
+    Tgm,
+    /// NES: one reroll when the roll repeats the previous piece.
+    Nes,
+}
+
+impl RandomizerKind {
+    pub const ALL: [RandomizerKind; 5] = [
+        RandomizerKind::SevenBag,
+        RandomizerKind::FourteenBag,
+        RandomizerKind::Random,
+        RandomizerKind::Tgm,
+        RandomizerKind::Nes,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RandomizerKind::SevenBag => "7Bag",
+            RandomizerKind::FourteenBag => "14Bg",
+            RandomizerKind::Random => "Rand",
+            RandomizerKind::Tgm => "TGM",
+            RandomizerKind::Nes => "NES",
+        }
+    }
+
+    pub fn build(self, seed: u64) -> Box<dyn Randomizer> {
+        let rng = SeededRng::new(seed);
+        match self {
+            RandomizerKind::SevenBag => Box::new(BagRandomizer::new(1, rng)),
+            RandomizerKind::FourteenBag => Box::new(BagRandomizer::new(2, rng)),
+            RandomizerKind::Random => Box::new(PureRandom { rng }),
+            RandomizerKind::Tgm => Box::new(TgmRandomizer::new(rng)),
+            RandomizerKind::Nes => Box::new(NesRandomizer { prev: None, rng }),
+        }
+    }
+}
+
+/// Shuffles `copies` of each piece together and deals them out.
+pub struct BagRandomizer {
+    copies: usize,
+    queue: Vec<usize>,
+    rng: SeededRng,
+}
+
+impl BagRandomizer {
+    pub fn new(copies: usize, rng: SeededRng) -> Self {
+        Self {
+            copies,
+            queue: Vec::new(),
+            rng,
+        }
+    }
+}
+
+impl Randomizer for BagRandomizer {
+    fn next(&mut self) -> usize {
+        if self.queue.is_empty() {
+            let mut bag: Vec<usize> = (0..self.copies).flat_map(|_| 0..PIECE_KINDS).collect();
+            self.rng.shuffle(&mut bag);
+            self.queue = bag;
+        }
+        self.queue.pop().unwrap()
+    }
+}
+
+pub struct PureRandom {
+    rng: SeededRng,
+}
+
+impl Randomizer for PureRandom {
+    fn next(&mut self) -> usize {
+        self.rng.below(PIECE_KINDS)
+    }
+}
+
+const TGM_ROLLS: usize = 6;
+
+pub struct TgmRandomizer {
+    history: [usize; 4],
+    first: bool,
+    rng: SeededRng,
+}
+
+impl TgmRandomizer {
+    pub fn new(rng: SeededRng) -> Self {
+        Self {
+            history: [KIND_Z; 4],
+            first: true,
+            rng,
+        }
+    }
+}
+
+impl Randomizer for TgmRandomizer {
+    fn next(&mut self) -> usize {
+        let kind = if self.first {
+            // The first piece is never S, Z or O: pick from I, T, L, J.
+            self.first = false;
+            const FIRST: [usize; 4] = [0, 2, 5, 6];
+            FIRST[self.rng.below(FIRST.len())]
+        } else {
+            let mut kind = self.rng.below(PIECE_KINDS);
+            for _ in 1..TGM_ROLLS {
+                if !self.history.contains(&kind) {
+                    break;
+                }
+                kind = self.rng.below(PIECE_KINDS);
+            }
+            kind
+        };
+        self.history.rotate_right(1);
+        self.history[0] = kind;
+        kind
+    }
+}
+
+pub struct NesRandomizer {
+    prev: Option<usize>,
+    rng: SeededRng,
+}
+
+impl Randomizer for NesRandomizer {
+    fn next(&mut self) -> usize {
+        // Roll 8 sides; the extra side or a repeat forces one plain reroll.
+        let mut kind = self.rng.below(PIECE_KINDS + 1);
+        if kind == PIECE_KINDS || Some(kind) == self.prev {
+            kind = self.rng.below(PIECE_KINDS);
+        }
+        self.prev = Some(kind);
+        kind
+    }
+}
@@ -3,6 +3,7 @@ use std::fs;
 use std::path::PathBuf;
 
 use crate::game::GameMode;
+use crate::game::randomizer::RandomizerKind;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct ScoreRecord {
@@ -11,6 +12,9 @@ pub struct ScoreRecord {
     pub level: u32,
     pub time: Option<u64>,
     pub date: String,
+    /// `None` for records saved before the randomizer was kept.
+    #[serde(default)]
+    pub randomizer: Option<RandomizerKind>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Default)]
@@ -22,9 +26,14 @@ pub struct Records {
     pub endless: Vec<ScoreRecord>,
 }
 
-fn records_path() -> PathBuf {
+pub fn data_dir() -> PathBuf {
     let mut path = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
     path.push("rustris");
+    path
+}
+
+fn records_path() -> PathBuf {
+    let mut path = data_dir();
     path.push("records.json");
     path
 }
@@ -48,6 +57,16 @@ impl Records {
         }
     }
 
+    /// The table shown for `mode`. Versus keeps no records and shows Endless.
+    pub fn list(&self, mode: GameMode) -> &[ScoreRecord] {
+        match mode {
+            GameMode::Marathon => &self.marathon,
+            GameMode::Sprint => &self.sprint,
+            GameMode::Ultra => &self.ultra,
+            GameMode::Endless | GameMode::Versus => &self.endless,
+        }
+    }
+
     pub fn add(&mut self, mode: GameMode, record: ScoreRecord) -> Option<usize> {
         let list = match mode {
             GameMode::Marathon => &mut self.marathon,
@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::game::records::data_dir;
+use crate::game::settings::Settings;
+
+use super::{Game, GameInput, GameMode};
+
+const MAX_REPLAYS: usize = 20;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct ReplayEvent {
+    pub at_ms: u64,
+    pub input: GameInput,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Replay {
+    pub seed: u64,
+    pub settings: Settings,
+    pub mode: GameMode,
+    pub date: String,
+    pub score: u32,
+    pub lines: u32,
+    pub level: u32,
+    pub time: u64,
+    pub events: Vec<ReplayEvent>,
+}
+
+#[derive(Default)]
+pub struct ReplayRecorder {
+    events: Vec<ReplayEvent>,
+}
+
+impl ReplayRecorder {
+    pub fn new() -> Self {
+        Self { events: Vec::new() }
+    }
+
+    pub fn record(&mut self, at: Duration, input: GameInput) {
+        self.events.push(ReplayEvent {
+            at_ms: at.as_millis() as u64,
+            input,
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    pub fn finish(&mut self, game: &Game, settings: &Settings, date: String) -> Replay {
+        Replay {
+            seed: game.seed,
+            settings: *settings,
+            mode: game.mode,
+            date,
+            score: game.score,
+            lines: game.lines,
+            level: game.level,
+            time: game.elapsed.as_millis() as u64,
+            events: std::mem::take(&mut self.events),
+        }
+    }
+}
+
+fn replays_dir() -> PathBuf {
+    let mut path = data_dir();
+    path.push("replays");
+    path
+}
+
+impl Replay {
+    /// Saves the replay and prunes the oldest files beyond `MAX_REPLAYS`.
+    pub fn save(&self) {
+        let dir = replays_dir();
+        let _ = fs::create_dir_all(&dir);
+        let name = format!("{}_{:016x}.json", self.date.replace(':', "-"), self.seed);
+        if let Ok(data) = serde_json::to_string(self) {
+            let _ = fs::write(dir.join(name), data);
+        }
+
+        let mut paths = replay_paths();
+        while paths.len() > MAX_REPLAYS {
+            let _ = fs::remove_file(paths.remove(0));
+        }
+    }
+
+    /// Loads every saved replay, newest first.
+    pub fn load_all() -> Vec<Self> {
+        replay_paths()
+            .iter()
+            .rev()
+            .filter_map(|p| fs::read_to_string(p).ok())
+            .filter_map(|data| serde_json::from_str(&data).ok())
+            .collect()
+    }
+}
+
+fn replay_paths() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = match fs::read_dir(replays_dir()) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    paths.sort();
+    paths
+}
+
+impl Game {
+    /// Whether `input` can act on the game as it stands. Piece inputs wait
+    /// out ARE, the line clear and rising garbage, so a piece can never lock
+    /// twice; `AreEnd` and `ClearDone` only end a delay that is running.
+    pub fn accepts(&self, input: GameInput) -> bool {
+        let settling = self.is_animating() || self.is_garbage_animating();
+        match input {
+            GameInput::TimeUp => true,
+            _ if self.game_over => false,
+            GameInput::ClearDone => self.is_animating(),
+            GameInput::AreEnd => self.in_are() && !settling,
+            _ => !self.in_are() && !settling,
+        }
+    }
+
+    /// Applies `input` unless the game does not accept it right now. Returns
+    /// whether it was applied.
+    pub fn apply_input(&mut self, input: GameInput) -> bool {
+        if !self.accepts(input) {
+            return false;
+        }
+        match input {
+            GameInput::MoveLeft => {
+                self.move_piece(0, -1);
+            }
+            GameInput::MoveRight => {
+                self.move_piece(0, 1);
+            }
+            GameInput::SoftDrop => {
+                self.soft_drop();
+            }
+            GameInput::HardDrop => {
+                self.hard_drop();
+            }
+            GameInput::RotateCw => self.rotate_cw(),
+            GameInput::RotateCcw => self.rotate_ccw(),
+            GameInput::Rotate180 => self.rotate_180(),
+            GameInput::Hold => self.hold_piece(),
+            GameInput::Gravity => self.tick(),
+            GameInput::Lock => {
+                self.lock_delay = None;
+                self.lock_and_begin_clear();
+            }
+            GameInput::AreEnd => self.end_are(),
+            GameInput::ClearDone => self.finish_clear(),
+            GameInput::TimeUp => self.game_over = true,
+        }
+        true
+    }
+}
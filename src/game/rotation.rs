@@ -0,0 +1,300 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::piece::{BOARD_WIDTH, BUFFER_HEIGHT, KIND_O, KIND_T};
+
+const KIND_I: usize = 0;
+const KIND_L: usize = 5;
+const KIND_J: usize = 6;
+
+/// Piece shapes, spawn positions and wall kicks. Each system owns its own
+/// state tables; rotation 0 is always the spawn orientation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum RotationSystem {
+    /// Super Rotation System (guideline).
+    #[default]
+    Srs,
+    /// Arika Rotation System (TGM): bottom-aligned states, simple kicks.
+    Ars,
+    /// Nintendo Rotation System (NES): no kicks, two-state I/S/Z.
+    Nrs,
+    /// SRS shapes without any wall kicks.
+    Classic,
+}
+
+impl RotationSystem {
+    pub const ALL: [RotationSystem; 4] = [
+        RotationSystem::Srs,
+        RotationSystem::Ars,
+        RotationSystem::Nrs,
+        RotationSystem::Classic,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RotationSystem::Srs => "SRS",
+            RotationSystem::Ars => "ARS",
+            RotationSystem::Nrs => "NRS",
+            RotationSystem::Classic => "None",
+        }
+    }
+
+    pub fn blocks(self, kind: usize, rotation: u8) -> &'static [[i32; 2]; 4] {
+        let states = match self {
+            RotationSystem::Srs | RotationSystem::Classic => &SRS_STATES,
+            RotationSystem::Ars => &ARS_STATES,
+            RotationSystem::Nrs => &NRS_STATES,
+        };
+        &states[kind][rotation as usize % 4]
+    }
+
+    pub fn spawn_row(self, kind: usize) -> i32 {
+        match self {
+            RotationSystem::Srs | RotationSystem::Classic if kind == KIND_O => {
+                BUFFER_HEIGHT as i32 - 1
+            }
+            _ => BUFFER_HEIGHT as i32,
+        }
+    }
+
+    pub fn spawn_col(self) -> i32 {
+        match self {
+            RotationSystem::Nrs => (BOARD_WIDTH as i32) / 2,
+            _ => (BOARD_WIDTH as i32) / 2 - 1,
+        }
+    }
+
+    /// Offsets (`[dc, dr]`) to try, in order, when rotating `kind` from `from` to `to`.
+    pub fn kicks(self, kind: usize, from: u8, to: u8) -> &'static [[i32; 2]] {
+        let half_turn = (to + 4 - from) % 4 == 2;
+        match self {
+            RotationSystem::Srs => match (kind, half_turn) {
+                (KIND_I, true) => &KICK_I_180[from as usize],
+                (_, true) => &KICK_180[from as usize],
+                (KIND_I, false) => &KICK_I[kick_index(from, to)],
+                _ => &KICK_JLTSZ[kick_index(from, to)],
+            },
+            RotationSystem::Ars if kind != KIND_I => &KICK_ARS,
+            _ => &NO_KICK,
+        }
+    }
+
+    /// ARS refuses to kick an L, J or T whose rotation is blocked first in
+    /// the centre column of its bounding box.
+    pub fn center_column_rule(self, kind: usize) -> bool {
+        self == RotationSystem::Ars && matches!(kind, KIND_T | KIND_L | KIND_J)
+    }
+}
+
+const SRS_STATES: [[[[i32; 2]; 4]; 4]; 7] = [
+    // I
+    [
+        [[0, -1], [0, 0], [0, 1], [0, 2]],
+        [[-1, 1], [0, 1], [1, 1], [2, 1]],
+        [[1, -1], [1, 0], [1, 1], [1, 2]],
+        [[-1, 0], [0, 0], [1, 0], [2, 0]],
+    ],
+    // O
+    [
+        [[0, 0], [0, 1], [1, 0], [1, 1]],
+        [[0, 0], [0, 1], [1, 0], [1, 1]],
+        [[0, 0], [0, 1], [1, 0], [1, 1]],
+        [[0, 0], [0, 1], [1, 0], [1, 1]],
+    ],
+    // T
+    [
+        [[-1, 0], [0, -1], [0, 0], [0, 1]],
+        [[-1, 0], [0, 0], [0, 1], [1, 0]],
+        [[0, -1], [0, 0], [0, 1], [1, 0]],
+        [[-1, 0], [0, -1], [0, 0], [1, 0]],
+    ],
+    // S
+    [
+        [[-1, 0], [-1, 1], [0, -1], [0, 0]],
+        [[-1, 0], [0, 0], [0, 1], [1, 1]],
+        [[0, 0], [0, 1], [1, -1], [1, 0]],
+        [[-1, -1], [0, -1], [0, 0], [1, 0]],
+    ],
+    // Z
+    [
+        [[-1, -1], [-1, 0], [0, 0], [0, 1]],
+        [[-1, 1], [0, 0], [0, 1], [1, 0]],
+        [[0, -1], [0, 0], [1, 0], [1, 1]],
+        [[-1, 0], [0, -1], [0, 0], [1, -1]],
+    ],
+    // L
+    [
+        [[-1, 1], [0, -1], [0, 0], [0, 1]],
+        [[-1, 0], [0, 0], [1, 0], [1, 1]],
+        [[0, -1], [0, 0], [0, 1], [1, -1]],
+        [[-1, -1], [-1, 0], [0, 0], [1, 0]],
+    ],
+    // J
+    [
+        [[-1, -1], [0, -1], [0, 0], [0, 1]],
+        [[-1, 0], [-1, 1], [0, 0], [1, 0]],
+        [[0, -1], [0, 0], [0, 1], [1, 1]],
+        [[-1, 0], [0, 0], [1, -1], [1, 0]],
+    ],
+];
+
+/// TGM states, bottom-aligned in the 3x3 box so pieces do not rise when rotating.
+const ARS_STATES: [[[[i32; 2]; 4]; 4]; 7] = [
+    // I
+    [
+        [[0, -1], [0, 0], [0, 1], [0, 2]],
+        [[-1, 1], [0, 1], [1, 1], [2, 1]],
+        [[0, -1], [0, 0], [0, 1], [0, 2]],
+        [[-1, 1], [0, 1], [1, 1], [2, 1]],
+    ],
+    // O
+    [
+        [[0, 0], [0, 1], [1, 0], [1, 1]],
+        [[0, 0], [0, 1], [1, 0], [1, 1]],
+        [[0, 0], [0, 1], [1, 0], [1, 1]],
+        [[0, 0], [0, 1], [1, 0], [1, 1]],
+    ],
+    // T
+    [
+        [[0, -1], [0, 0], [0, 1], [1, 0]],
+        [[-1, 0], [0, -1], [0, 0], [1, 0]],
+        [[0, 0], [1, -1], [1, 0], [1, 1]],
+        [[-1, 0], [0, 0], [0, 1], [1, 0]],
+    ],
+    // S
+    [
+        [[0, 0], [0, 1], [1, -1], [1, 0]],
+        [[-1, -1], [0, -1], [0, 0], [1, 0]],
+        [[0, 0], [0, 1], [1, -1], [1, 0]],
+        [[-1, -1], [0, -1], [0, 0], [1, 0]],
+    ],
+    // Z
+    [
+        [[0, -1], [0, 0], [1, 0], [1, 1]],
+        [[-1, 1], [0, 0], [0, 1], [1, 0]],
+        [[0, -1], [0, 0], [1, 0], [1, 1]],
+        [[-1, 1], [0, 0], [0, 1], [1, 0]],
+    ],
+    // L
+    [
+        [[0, -1], [0, 0], [0, 1], [1, -1]],
+        [[-1, -1], [-1, 0], [0, 0], [1, 0]],
+        [[0, 1], [1, -1], [1, 0], [1, 1]],
+        [[-1, 0], [0, 0], [1, 0], [1, 1]],
+    ],
+    // J
+    [
+        [[0, -1], [0, 0], [0, 1], [1, 1]],
+        [[-1, 0], [0, 0], [1, -1], [1, 0]],
+        [[0, -1], [1, -1], [1, 0], [1, 1]],
+        [[-1, 0], [-1, 1], [0, 0], [1, 0]],
+    ],
+];
+
+/// NES states: true rotation about the centre block, with T/J/L spawning flat side up.
+const NRS_STATES: [[[[i32; 2]; 4]; 4]; 7] = [
+    // I
+    [
+        [[0, -2], [0, -1], [0, 0], [0, 1]],
+        [[-2, 0], [-1, 0], [0, 0], [1, 0]],
+        [[0, -2], [0, -1], [0, 0], [0, 1]],
+        [[-2, 0], [-1, 0], [0, 0], [1, 0]],
+    ],
+    // O
+    [
+        [[0, -1], [0, 0], [1, -1], [1, 0]],
+        [[0, -1], [0, 0], [1, -1], [1, 0]],
+        [[0, -1], [0, 0], [1, -1], [1, 0]],
+        [[0, -1], [0, 0], [1, -1], [1, 0]],
+    ],
+    // T
+    [
+        [[0, -1], [0, 0], [0, 1], [1, 0]],
+        [[-1, 0], [0, -1], [0, 0], [1, 0]],
+        [[-1, 0], [0, -1], [0, 0], [0, 1]],
+        [[-1, 0], [0, 0], [0, 1], [1, 0]],
+    ],
+    // S
+    [
+        [[0, 0], [0, 1], [1, -1], [1, 0]],
+        [[-1, 0], [0, 0], [0, 1], [1, 1]],
+        [[0, 0], [0, 1], [1, -1], [1, 0]],
+        [[-1, 0], [0, 0], [0, 1], [1, 1]],
+    ],
+    // Z
+    [
+        [[0, -1], [0, 0], [1, 0], [1, 1]],
+        [[-1, 1], [0, 0], [0, 1], [1, 0]],
+        [[0, -1], [0, 0], [1, 0], [1, 1]],
+        [[-1, 1], [0, 0], [0, 1], [1, 0]],
+    ],
+    // L
+    [
+        [[0, -1], [0, 0], [0, 1], [1, -1]],
+        [[-1, -1], [-1, 0], [0, 0], [1, 0]],
+        [[-1, 1], [0, -1], [0, 0], [0, 1]],
+        [[-1, 0], [0, 0], [1, 0], [1, 1]],
+    ],
+    // J
+    [
+        [[0, -1], [0, 0], [0, 1], [1, 1]],
+        [[-1, 0], [0, 0], [1, -1], [1, 0]],
+        [[-1, -1], [0, -1], [0, 0], [0, 1]],
+        [[-1, 0], [-1, 1], [0, 0], [1, 0]],
+    ],
+];
+
+const KICK_JLTSZ: [[[i32; 2]; 5]; 8] = [
+    [[0, 0], [-1, 0], [-1, -1], [0, 2], [-1, 2]],
+    [[0, 0], [1, 0], [1, 1], [0, -2], [1, -2]],
+    [[0, 0], [1, 0], [1, 1], [0, -2], [1, -2]],
+    [[0, 0], [-1, 0], [-1, -1], [0, 2], [-1, 2]],
+    [[0, 0], [1, 0], [1, -1], [0, 2], [1, 2]],
+    [[0, 0], [-1, 0], [-1, 1], [0, -2], [-1, -2]],
+    [[0, 0], [-1, 0], [-1, 1], [0, -2], [-1, -2]],
+    [[0, 0], [1, 0], [1, -1], [0, 2], [1, 2]],
+];
+
+const KICK_I: [[[i32; 2]; 5]; 8] = [
+    [[0, 0], [-2, 0], [1, 0], [-2, 1], [1, -2]],
+    [[0, 0], [2, 0], [-1, 0], [2, -1], [-1, 2]],
+    [[0, 0], [-1, 0], [2, 0], [-1, -2], [2, 1]],
+    [[0, 0], [1, 0], [-2, 0], [1, 2], [-2, -1]],
+    [[0, 0], [2, 0], [-1, 0], [2, -1], [-1, 2]],
+    [[0, 0], [-2, 0], [1, 0], [-2, 1], [1, -2]],
+    [[0, 0], [1, 0], [-2, 0], [1, 2], [-2, -1]],
+    [[0, 0], [-1, 0], [2, 0], [-1, -2], [2, 1]],
+];
+
+/// SRS+ 180° kicks (as in TETR.IO), indexed by the starting rotation.
+const KICK_180: [[[i32; 2]; 6]; 4] = [
+    [[0, 0], [0, -1], [1, -1], [-1, -1], [1, 0], [-1, 0]],
+    [[0, 0], [1, 0], [1, -2], [1, -1], [0, -2], [0, -1]],
+    [[0, 0], [0, 1], [-1, 1], [1, 1], [-1, 0], [1, 0]],
+    [[0, 0], [-1, 0], [-1, -2], [-1, -1], [0, -2], [0, -1]],
+];
+
+const KICK_I_180: [[[i32; 2]; 2]; 4] = [
+    [[0, 0], [0, -1]],
+    [[0, 0], [1, 0]],
+    [[0, 0], [0, 1]],
+    [[0, 0], [-1, 0]],
+];
+
+pub fn kick_index(from: u8, to: u8) -> usize {
+    match (from, to) {
+        (0, 1) => 0,
+        (1, 0) => 1,
+        (1, 2) => 2,
+        (2, 1) => 3,
+        (2, 3) => 4,
+        (3, 2) => 5,
+        (3, 0) => 6,
+        (0, 3) => 7,
+        _ => 0,
+    }
+}
+
+/// ARS tries one cell right, then one cell left.
+const KICK_ARS: [[i32; 2]; 3] = [[0, 0], [1, 0], [-1, 0]];
+
+const NO_KICK: [[i32; 2]; 1] = [[0, 0]];
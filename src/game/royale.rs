@@ -0,0 +1,210 @@
+use serde::{Deserialize, Serialize};
+
+use super::rng::SeededRng;
+
+/// Identifies a player within a room. The host is always player 0.
+pub type PlayerId = u8;
+
+pub const HOST_ID: PlayerId = 0;
+pub const MIN_PLAYERS: usize = 3;
+pub const MAX_PLAYERS: usize = 8;
+
+/// How a player's attacks pick a victim.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TargetStrategy {
+    /// Anyone still alive.
+    #[default]
+    Random,
+    /// Whoever is currently attacking this player.
+    Attackers,
+    /// The player closest to topping out.
+    Kos,
+    /// Whoever has received the least garbage so far.
+    Even,
+}
+
+impl TargetStrategy {
+    pub const ALL: [TargetStrategy; 4] = [
+        TargetStrategy::Random,
+        TargetStrategy::Attackers,
+        TargetStrategy::Kos,
+        TargetStrategy::Even,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TargetStrategy::Random => "Rand",
+            TargetStrategy::Attackers => "Atk",
+            TargetStrategy::Kos => "KOs",
+            TargetStrategy::Even => "Even",
+        }
+    }
+}
+
+/// A player leaving the match: where they placed and who gets the credit.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Ko {
+    pub player: PlayerId,
+    pub place: u8,
+    pub by: Option<PlayerId>,
+}
+
+struct Seat {
+    id: PlayerId,
+    strategy: TargetStrategy,
+    alive: bool,
+    /// Who this player last sent garbage to.
+    target: Option<PlayerId>,
+    /// Who last sent garbage to this player.
+    last_attacker: Option<PlayerId>,
+    received: u32,
+    stack_height: usize,
+    kos: u32,
+}
+
+/// The host's view of a battle royale: who is alive, who is attacking whom,
+/// and the order players were knocked out in.
+pub struct Room {
+    seats: Vec<Seat>,
+    knocked_out: Vec<PlayerId>,
+    rng: SeededRng,
+}
+
+impl Room {
+    pub fn new(players: &[PlayerId], seed: u64) -> Self {
+        let seats = players
+            .iter()
+            .map(|&id| Seat {
+                id,
+                strategy: TargetStrategy::default(),
+                alive: true,
+                target: None,
+                last_attacker: None,
+                received: 0,
+                stack_height: 0,
+                kos: 0,
+            })
+            .collect();
+        Self {
+            seats,
+            knocked_out: Vec::new(),
+            rng: SeededRng::new(seed),
+        }
+    }
+
+    fn seat(&self, id: PlayerId) -> Option<&Seat> {
+        self.seats.iter().find(|s| s.id == id)
+    }
+
+    fn seat_mut(&mut self, id: PlayerId) -> Option<&mut Seat> {
+        self.seats.iter_mut().find(|s| s.id == id)
+    }
+
+    pub fn set_strategy(&mut self, id: PlayerId, strategy: TargetStrategy) {
+        if let Some(seat) = self.seat_mut(id) {
+            seat.strategy = strategy;
+        }
+    }
+
+    /// Rows of the player's visible board that hold blocks, from their latest
+    /// board state. Used by the KOs strategy.
+    pub fn update_stack(&mut self, id: PlayerId, height: usize) {
+        if let Some(seat) = self.seat_mut(id) {
+            seat.stack_height = height;
+        }
+    }
+
+    pub fn is_alive(&self, id: PlayerId) -> bool {
+        self.seat(id).is_some_and(|s| s.alive)
+    }
+
+    pub fn alive_count(&self) -> usize {
+        self.seats.iter().filter(|s| s.alive).count()
+    }
+
+    pub fn kos(&self, id: PlayerId) -> u32 {
+        self.seat(id).map_or(0, |s| s.kos)
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.alive_count() <= 1
+    }
+
+    /// Picks who receives `lines` of garbage from `from`, following the
+    /// sender's strategy. `None` if the sender is out or has nobody left to hit.
+    pub fn route_attack(&mut self, from: PlayerId, lines: u32) -> Option<PlayerId> {
+        let strategy = self.seat(from).filter(|s| s.alive)?.strategy;
+        let candidates: Vec<usize> = (0..self.seats.len())
+            .filter(|&i| self.seats[i].alive && self.seats[i].id != from)
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let preferred: Vec<usize> = match strategy {
+            TargetStrategy::Random => Vec::new(),
+            TargetStrategy::Attackers => candidates
+                .iter()
+                .copied()
+                .filter(|&i| self.seats[i].target == Some(from))
+                .collect(),
+            TargetStrategy::Kos => {
+                let highest = candidates.iter().map(|&i| self.seats[i].stack_height).max().unwrap_or(0);
+                candidates
+                    .iter()
+                    .copied()
+                    .filter(|&i| self.seats[i].stack_height == highest)
+                    .collect()
+            }
+            TargetStrategy::Even => {
+                let least = candidates.iter().map(|&i| self.seats[i].received).min().unwrap_or(0);
+                candidates
+                    .iter()
+                    .copied()
+                    .filter(|&i| self.seats[i].received == least)
+                    .collect()
+            }
+        };
+        let pool = if preferred.is_empty() { &candidates } else { &preferred };
+        let victim = pool[self.rng.below(pool.len())];
+
+        let target = self.seats[victim].id;
+        self.seats[victim].received += lines;
+        self.seats[victim].last_attacker = Some(from);
+        if let Some(sender) = self.seat_mut(from) {
+            sender.target = Some(target);
+        }
+        Some(target)
+    }
+
+    /// Takes a player out of the match. The last player to attack them is
+    /// credited with the KO. `None` if they were already out.
+    pub fn knock_out(&mut self, id: PlayerId) -> Option<Ko> {
+        let place = self.alive_count() as u8;
+        let seat = self.seat_mut(id).filter(|s| s.alive)?;
+        seat.alive = false;
+        let by = seat.last_attacker;
+        self.knocked_out.push(id);
+
+        let by = by.filter(|&attacker| attacker != id);
+        if let Some(attacker) = by.and_then(|a| self.seat_mut(a)) {
+            attacker.kos += 1;
+        }
+        for seat in &mut self.seats {
+            if seat.target == Some(id) {
+                seat.target = None;
+            }
+        }
+        Some(Ko { player: id, place, by })
+    }
+
+    /// Final standings, winner first.
+    pub fn placements(&self) -> Vec<PlayerId> {
+        self.seats
+            .iter()
+            .filter(|s| s.alive)
+            .map(|s| s.id)
+            .chain(self.knocked_out.iter().rev().copied())
+            .collect()
+    }
+}
@@ -1,41 +1,55 @@
-use std::time::Instant;
-
 use crate::game::piece::*;
 
 use super::{ClearAction, Game, GameMode, LineClearAnimation};
 
+/// Three-corner T-spin check for a T `piece` that just rotated into place.
+/// Returns `(is_tspin, is_mini)`.
+pub(super) fn tspin_at(piece: &Piece, is_occupied: impl Fn(i32, i32) -> bool) -> (bool, bool) {
+    // Every rotation system lays the T out differently, so find its
+    // centre and facing from the cells rather than from a state table.
+    let cells = piece.cells();
+    let adjacent = |(ar, ac): (i32, i32), (br, bc): (i32, i32)| (ar - br).abs() + (ac - bc).abs() == 1;
+    let Some(&(cr, cc)) = cells
+        .iter()
+        .find(|&&center| cells.iter().filter(|&&other| adjacent(center, other)).count() == 3)
+    else {
+        return (false, false);
+    };
+    let Some(&(nr, nc)) = cells
+        .iter()
+        .find(|&&(r, c)| (r, c) != (cr, cc) && !cells.contains(&(2 * cr - r, 2 * cc - c)))
+    else {
+        return (false, false);
+    };
+    let (dr, dc) = (nr - cr, nc - cc);
+
+    let front_occupied = [(nr + dc, nc + dr), (nr - dc, nc - dr)]
+        .iter()
+        .filter(|&&(r, c)| is_occupied(r, c))
+        .count();
+    let back_occupied = [(cr - dr + dc, cc - dc + dr), (cr - dr - dc, cc - dc - dr)]
+        .iter()
+        .filter(|&&(r, c)| is_occupied(r, c))
+        .count();
+
+    let total = front_occupied + back_occupied;
+    if total < 3 {
+        return (false, false);
+    }
+
+    if front_occupied == 2 {
+        (true, false)
+    } else {
+        (true, true)
+    }
+}
+
 impl Game {
     fn detect_tspin(&self) -> (bool, bool) {
         if self.current.kind != KIND_T || self.last_move != super::LastMove::Rotate {
             return (false, false);
         }
-
-        let rot = self.current.rotation as usize;
-        let cr = self.current.row;
-        let cc = self.current.col;
-
-        let front = &T_FRONT_CORNERS[rot];
-        let back = &T_BACK_CORNERS[rot];
-
-        let front_occupied = front
-            .iter()
-            .filter(|&&[dr, dc]| self.is_occupied(cr + dr, cc + dc))
-            .count();
-        let back_occupied = back
-            .iter()
-            .filter(|&&[dr, dc]| self.is_occupied(cr + dr, cc + dc))
-            .count();
-
-        let total = front_occupied + back_occupied;
-        if total < 3 {
-            return (false, false);
-        }
-
-        if front_occupied == 2 {
-            (true, false)
-        } else {
-            (true, true)
-        }
+        tspin_at(&self.current, |r, c| self.is_occupied(r, c))
     }
 
     fn lock_current(&mut self) {
@@ -53,6 +67,7 @@ impl Game {
         self.lock_current();
         let full_rows = self.find_full_rows();
         let cleared = full_rows.len() as u32;
+        let garbage_lines = full_rows.iter().filter(|&&r| self.board[r].contains(&GARBAGE_CELL)).count() as u32;
 
         if cleared > 0 {
             self.lines += cleared;
@@ -145,6 +160,9 @@ impl Game {
                 label.push_str(&format!(" Combo x{}", self.combo));
             }
 
+            let b2b_broken = if is_difficult { 0 } else { self.b2b_chain };
+            self.b2b_chain = if is_difficult && self.back_to_back { self.b2b_chain + 1 } else { 0 };
+
             self.last_action = Some(ClearAction {
                 label,
                 points: total + pc_bonus,
@@ -154,8 +172,11 @@ impl Game {
                 is_back_to_back: is_difficult && self.back_to_back,
                 combo: self.combo,
                 is_all_clear,
+                garbage_lines,
+                b2b_chain: self.b2b_chain,
+                b2b_broken,
             });
-            self.last_action_time = Instant::now();
+            self.last_action_time = self.now();
 
             self.back_to_back = is_difficult;
 
@@ -175,10 +196,10 @@ impl Game {
             }
 
             if self.line_clear_anim_enabled {
-                self.line_clear_anim = Some(LineClearAnimation::new(full_rows));
+                self.line_clear_anim = Some(LineClearAnimation::new(full_rows, self.now()));
             } else {
                 self.remove_rows(&full_rows);
-                self.are_timer = Some(Instant::now());
+                self.are_timer = Some(self.now());
             }
             return true;
         } else {
@@ -202,12 +223,15 @@ impl Game {
                     is_back_to_back: false,
                     combo: -1,
                     is_all_clear: false,
+                    garbage_lines: 0,
+                    b2b_chain: 0,
+                    b2b_broken: 0,
                 });
-                self.last_action_time = Instant::now();
+                self.last_action_time = self.now();
             }
         }
 
-        self.are_timer = Some(Instant::now());
+        self.are_timer = Some(self.now());
         false
     }
 }
@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+
+/// Games won by each side in a first-to-N set of Versus games. A set that is
+/// first to 1 is a single game with no set around it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SetScore {
+    pub first_to: u32,
+    pub host: u32,
+    pub guest: u32,
+}
+
+impl SetScore {
+    pub fn new(first_to: u32) -> Self {
+        Self {
+            first_to: first_to.max(1),
+            host: 0,
+            guest: 0,
+        }
+    }
+
+    pub fn is_single(&self) -> bool {
+        self.first_to <= 1
+    }
+
+    pub fn record(&mut self, host_won: bool) {
+        if host_won {
+            self.host += 1;
+        } else {
+            self.guest += 1;
+        }
+    }
+
+    /// Whether the host took the set, once either side has reached `first_to`.
+    pub fn host_won_set(&self) -> Option<bool> {
+        if self.host >= self.first_to {
+            Some(true)
+        } else if self.guest >= self.first_to {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// The game being played, or about to be, counting from 1.
+    pub fn game_number(&self) -> u32 {
+        self.host + self.guest + 1
+    }
+
+    /// Our wins and theirs.
+    pub fn from_side(&self, is_host: bool) -> (u32, u32) {
+        if is_host {
+            (self.host, self.guest)
+        } else {
+            (self.guest, self.host)
+        }
+    }
+}
@@ -1,8 +1,20 @@
 use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
 
+use crate::game::bot::BotDifficulty;
+use crate::game::garbage::{GarbageRules, MARGIN_STEP_SECS, MAX_GARBAGE_CAP, MAX_MARGIN_SECS};
 use crate::game::piece::MAX_NEXT_COUNT;
+use crate::game::randomizer::RandomizerKind;
+use crate::game::records::data_dir;
+use crate::game::rotation::RotationSystem;
+use crate::game::royale::TargetStrategy;
+
+/// The longest set the match setup screen offers.
+pub const MAX_FIRST_TO: u32 = 7;
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings {
     pub level: u32,
     pub marathon_goal: u32,
@@ -12,11 +24,26 @@ pub struct Settings {
     pub ghost: bool,
     pub line_clear_anim: bool,
     pub next_count: usize,
-    pub bag_randomizer: bool,
-    pub srs_enabled: bool,
+    pub randomizer: RandomizerKind,
+    pub rotation: RotationSystem,
+    pub rotate_180: bool,
     pub hold_enabled: bool,
     pub lock_delay_ms: u32,
     pub move_reset: Option<u32>,
+    pub das_ms: u32,
+    pub arr_ms: u32,
+    pub das_release_ms: u32,
+    pub soft_drop_factor: Option<u32>,
+    pub bot_difficulty: BotDifficulty,
+    pub target_strategy: TargetStrategy,
+    pub garbage: GarbageRules,
+    /// Versus games a player must win to take the set; 1 plays single games.
+    pub first_to: u32,
+    /// Each Versus player streams their inputs and the other side replays
+    /// them to check the board, attacks and garbage.
+    pub verify: bool,
+    pub bgm: bool,
+    pub sfx: bool,
 }
 
 impl Default for Settings {
@@ -30,11 +57,101 @@ impl Default for Settings {
             ghost: true,
             line_clear_anim: true,
             next_count: MAX_NEXT_COUNT,
-            bag_randomizer: true,
-            srs_enabled: true,
+            randomizer: RandomizerKind::SevenBag,
+            rotation: RotationSystem::Srs,
+            rotate_180: false,
             hold_enabled: true,
             lock_delay_ms: 500,
             move_reset: Some(15),
+            das_ms: 167,
+            arr_ms: 33,
+            das_release_ms: 100,
+            soft_drop_factor: Some(1),
+            bot_difficulty: BotDifficulty::Normal,
+            target_strategy: TargetStrategy::Random,
+            garbage: GarbageRules::default(),
+            first_to: 1,
+            verify: false,
+            bgm: true,
+            sfx: true,
+        }
+    }
+}
+
+fn settings_path() -> PathBuf {
+    let mut path = data_dir();
+    path.push("settings.json");
+    path
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        let path = settings_path();
+        let mut settings = match fs::read_to_string(&path) {
+            Ok(data) => Self::from_json(&data),
+            Err(_) => Self::default(),
+        };
+        settings.clamp();
+        settings
+    }
+
+    /// Parses a saved settings file, carrying over keys that older versions
+    /// wrote. Falls back to the defaults if the file cannot be read.
+    pub(crate) fn from_json(data: &str) -> Self {
+        let Ok(mut value) = serde_json::from_str::<serde_json::Value>(data) else {
+            return Self::default();
+        };
+        if let Some(fields) = value.as_object_mut() {
+            // `srs_enabled: false` kept SRS shapes but turned the kicks off.
+            if let Some(srs) = fields.remove("srs_enabled") {
+                let rotation = if srs.as_bool() == Some(false) { "Classic" } else { "Srs" };
+                fields.entry("rotation").or_insert(rotation.into());
+            }
+            // `bag_randomizer: false` dealt every piece at random.
+            if let Some(bag) = fields.remove("bag_randomizer") {
+                let randomizer = if bag.as_bool() == Some(false) { "Random" } else { "SevenBag" };
+                fields.entry("randomizer").or_insert(randomizer.into());
+            }
+        }
+        serde_json::from_value(value).unwrap_or_default()
+    }
+
+    /// Pulls every value back inside the range its menu offers, so a hand
+    /// edited or corrupt file cannot set up a game the menus never could.
+    pub(crate) fn clamp(&mut self) {
+        self.level = self.level.clamp(1, 20);
+        self.level_cap = self.level_cap.map(|n| n.clamp(1, 20));
+        self.marathon_goal = self.marathon_goal.clamp(10, 300);
+        self.sprint_goal = self.sprint_goal.clamp(10, 100);
+        self.ultra_time = self.ultra_time.clamp(30, 300);
+        self.next_count = self.next_count.min(MAX_NEXT_COUNT);
+        self.lock_delay_ms = self.lock_delay_ms.min(2000);
+        self.move_reset = self.move_reset.map(|n| n.min(30));
+        self.das_ms = self.das_ms.min(500);
+        self.arr_ms = self.arr_ms.min(100);
+        self.das_release_ms = self.das_release_ms.clamp(30, 500);
+        self.soft_drop_factor = self.soft_drop_factor.map(|n| n.max(1));
+        self.first_to = self.first_to.clamp(1, MAX_FIRST_TO);
+        self.garbage.delay_ms = self.garbage.delay_ms.min(2000);
+        self.garbage.cap = self.garbage.cap.map(|n| n.clamp(1, MAX_GARBAGE_CAP));
+        self.garbage.margin_secs = self.garbage.margin_secs.map(|n| n.clamp(MARGIN_STEP_SECS, MAX_MARGIN_SECS));
+    }
+
+    /// Keeps the Versus-only rules picked on a match setup screen so the
+    /// next match starts from them, leaving the solo settings alone.
+    pub fn keep_match_rules(&mut self, vs: &Settings) {
+        self.garbage = vs.garbage;
+        self.first_to = vs.first_to;
+        self.verify = vs.verify;
+    }
+
+    pub fn save(&self) {
+        let path = settings_path();
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(data) = serde_json::to_string_pretty(self) {
+            let _ = fs::write(&path, data);
         }
     }
 }
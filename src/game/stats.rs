@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::garbage::GarbageRules;
+use crate::game::Game;
+
+/// One player's numbers from a Versus game, kept as the game is played and
+/// swapped with the opponent when it ends.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MatchStats {
+    pub pieces: u32,
+    /// Attack that reached the opponent.
+    pub attack_sent: u32,
+    pub attack_received: u32,
+    /// Attack spent cancelling garbage that was on its way in.
+    pub attack_cancelled: u32,
+    /// Garbage rows among the lines cleared.
+    pub garbage_cleared: u32,
+    pub max_combo: u32,
+    /// Longest run of back-to-back bonuses.
+    pub max_b2b: u32,
+    /// T-spin singles, doubles and triples.
+    pub tspins: [u32; 3],
+    pub tspin_minis: u32,
+    pub duration_ms: u64,
+    /// The back-to-back run still going; only needed while playing.
+    #[serde(skip)]
+    pub b2b_chain: u32,
+}
+
+impl MatchStats {
+    /// Counts a locked piece. `sent` is the attack that went out for it once
+    /// `rules` had scaled it and pending garbage had been countered.
+    pub fn record_lock(&mut self, game: &Game, rules: GarbageRules, sent: u32) {
+        self.pieces += 1;
+        // As in `outgoing_attack`, `last_action` only belongs to this lock
+        // while the combo counter says it cleared lines.
+        let Some(action) = game.last_action.as_ref().filter(|a| game.combo >= 0 && a.cleared_lines > 0) else {
+            return;
+        };
+        let attack = rules.attack(action, game.elapsed);
+        self.attack_sent += sent;
+        self.attack_cancelled += attack.saturating_sub(sent);
+        self.garbage_cleared += action.garbage_lines;
+        self.max_combo = self.max_combo.max(action.combo as u32);
+        self.b2b_chain = if action.is_back_to_back { self.b2b_chain + 1 } else { 0 };
+        self.max_b2b = self.max_b2b.max(self.b2b_chain);
+        if action.is_tspin {
+            if action.is_mini {
+                self.tspin_minis += 1;
+            } else if let Some(count) = self.tspins.get_mut(action.cleared_lines as usize - 1) {
+                *count += 1;
+            }
+        }
+    }
+
+    pub fn finish(&mut self, elapsed: Duration) {
+        self.duration_ms = elapsed.as_millis() as u64;
+    }
+
+    fn seconds(&self) -> f64 {
+        (self.duration_ms as f64 / 1000.0).max(1.0)
+    }
+
+    /// Attack made per minute, whether it was sent or cancelled.
+    pub fn apm(&self) -> f64 {
+        (self.attack_sent + self.attack_cancelled) as f64 * 60.0 / self.seconds()
+    }
+
+    pub fn pps(&self) -> f64 {
+        self.pieces as f64 / self.seconds()
+    }
+
+    /// Attack plus garbage cleared, per second, times 100.
+    pub fn vs_score(&self) -> f64 {
+        (self.attack_sent + self.attack_cancelled + self.garbage_cleared) as f64 * 100.0 / self.seconds()
+    }
+}
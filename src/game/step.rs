@@ -0,0 +1,139 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::game::clock::ManualClock;
+
+use super::{Game, GameInput, LINE_CLEAR_ANIM_DURATION};
+
+/// Longest slice of time `step` advances before re-checking timers, so a large
+/// `dt` behaves like a run of 60 Hz frames.
+pub const SIM_FRAME: Duration = Duration::from_micros(16_667);
+
+impl Game {
+    /// Drive the game without a terminal: apply `inputs` at the current time,
+    /// then advance the simulated clock by `dt`, firing gravity, lock delay,
+    /// line-clear, ARE and time-up as they come due.
+    ///
+    /// Inputs that arrive between pieces (during ARE, a line clear or rising
+    /// garbage) are queued and applied in order as soon as the next piece
+    /// spawns, so a run of hard drops places a run of pieces. Inputs the
+    /// game could never take, such as `AreEnd` outside ARE, are dropped.
+    ///
+    /// Unless the game was built with a manual clock, the first call swaps in
+    /// one frozen at the game's start time. Returns the engine events that
+    /// fired, in order, so a caller can record them alongside the inputs.
+    pub fn step(&mut self, inputs: &[GameInput], dt: Duration) -> Vec<GameInput> {
+        self.step_with_locks(inputs, dt, |_| {})
+    }
+
+    /// `step`, calling `on_lock` with the game as it stands right after each
+    /// piece locks, before its line clear plays out. A long `dt` or a run of
+    /// queued hard drops can lock several pieces in one call.
+    pub fn step_with_locks(
+        &mut self,
+        inputs: &[GameInput],
+        dt: Duration,
+        mut on_lock: impl FnMut(&Game),
+    ) -> Vec<GameInput> {
+        let mut fired = Vec::new();
+        let clock = match &self.step_clock {
+            Some(c) => c.clone(),
+            None => {
+                let c = Arc::new(ManualClock::starting_at(self.game_start));
+                self.clock = c.clone();
+                self.step_clock = Some(c.clone());
+                self.gravity_timer = self.game_start;
+                c
+            }
+        };
+
+        self.step_inputs.extend(inputs);
+        self.apply_step_inputs(&mut on_lock);
+
+        let mut remaining = dt;
+        while !self.game_over {
+            let slice = remaining.min(SIM_FRAME);
+            clock.advance(slice);
+            remaining -= slice;
+            self.run_timers(&mut fired, &mut on_lock);
+            self.apply_step_inputs(&mut on_lock);
+            if remaining.is_zero() {
+                break;
+            }
+        }
+        fired
+    }
+
+    /// Whether inputs given to `step` are still waiting for the next piece.
+    pub fn has_queued_inputs(&self) -> bool {
+        !self.step_inputs.is_empty()
+    }
+
+    /// Applies queued inputs until one has to wait for the next piece.
+    fn apply_step_inputs(&mut self, on_lock: &mut impl FnMut(&Game)) {
+        while let Some(&input) = self.step_inputs.front() {
+            if self.game_over {
+                self.step_inputs.clear();
+                return;
+            }
+            let between_pieces = self.in_are() || self.is_animating() || self.is_garbage_animating();
+            if !self.accepts(input) && between_pieces {
+                return;
+            }
+            self.step_inputs.pop_front();
+            if self.apply_input(input) && matches!(input, GameInput::HardDrop | GameInput::Lock | GameInput::AreEnd) {
+                self.gravity_timer = self.now();
+                if input != GameInput::AreEnd {
+                    on_lock(self);
+                }
+            }
+        }
+    }
+
+    fn run_timers(&mut self, fired: &mut Vec<GameInput>, on_lock: &mut impl FnMut(&Game)) {
+        self.update_elapsed();
+        if self.time_up() {
+            self.apply_input(GameInput::TimeUp);
+            fired.push(GameInput::TimeUp);
+            return;
+        }
+
+        if let Some(anim) = &self.line_clear_anim {
+            if self.since(anim.started_at) >= LINE_CLEAR_ANIM_DURATION {
+                self.finish_clear();
+                fired.push(GameInput::ClearDone);
+                self.gravity_timer = self.now();
+            } else {
+                self.update_animation();
+            }
+            return;
+        }
+
+        if self.is_garbage_animating() {
+            self.update_garbage_animation();
+            return;
+        }
+
+        if self.in_are() {
+            if self.check_are() {
+                fired.push(GameInput::AreEnd);
+                self.gravity_timer = self.now();
+            }
+            return;
+        }
+
+        if self.lock_delay_expired() {
+            self.apply_input(GameInput::Lock);
+            fired.push(GameInput::Lock);
+            self.gravity_timer = self.now();
+            on_lock(self);
+            return;
+        }
+
+        if self.since(self.gravity_timer) >= self.drop_interval() {
+            self.tick();
+            fired.push(GameInput::Gravity);
+            self.gravity_timer = self.now();
+        }
+    }
+}
@@ -1,9 +1,22 @@
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
     use crate::game::*;
+    use crate::game::bot::{Bot, BotDifficulty};
+    use crate::game::clock::ManualClock;
     use crate::game::garbage::*;
+    use crate::game::lockstep::*;
     use crate::game::piece::*;
+    use crate::game::randomizer::RandomizerKind;
+    use crate::game::rng::SeededRng;
+    use crate::game::rotation::*;
+    use crate::game::royale::{Ko, Room, TargetStrategy};
+    use crate::game::set::SetScore;
     use crate::game::settings::Settings;
+    use crate::game::stats::MatchStats;
+    use crate::game::step::SIM_FRAME;
 
     fn test_settings() -> Settings {
         Settings {
@@ -13,20 +26,20 @@ mod tests {
     }
 
     fn make_game() -> Game {
-        Game::new(GameMode::Versus, &test_settings())
+        Game::new(GameMode::Versus, &test_settings(), 0)
     }
 
     #[test]
     fn fits_empty_board() {
         let game = make_game();
-        let piece = Piece::new(0);
+        let piece = Piece::new(0, RotationSystem::Srs);
         assert!(game.fits(&piece));
     }
 
     #[test]
     fn fits_occupied_cell() {
         let mut game = make_game();
-        let p = Piece::new(KIND_T);
+        let p = Piece::new(KIND_T, RotationSystem::Srs);
         let cells = p.cells();
         let (r, c) = cells.iter().find(|&&(r, _)| r >= 0).unwrap();
         game.board[*r as usize][*c as usize] = 1;
@@ -36,7 +49,7 @@ mod tests {
     #[test]
     fn fits_out_of_bounds_left() {
         let game = make_game();
-        let mut piece = Piece::new(0);
+        let mut piece = Piece::new(0, RotationSystem::Srs);
         piece.col = -5;
         assert!(!game.fits(&piece));
     }
@@ -44,7 +57,7 @@ mod tests {
     #[test]
     fn fits_out_of_bounds_right() {
         let game = make_game();
-        let mut piece = Piece::new(0);
+        let mut piece = Piece::new(0, RotationSystem::Srs);
         piece.col = BOARD_WIDTH as i32;
         assert!(!game.fits(&piece));
     }
@@ -52,7 +65,7 @@ mod tests {
     #[test]
     fn fits_out_of_bounds_bottom() {
         let game = make_game();
-        let mut piece = Piece::new(0);
+        let mut piece = Piece::new(0, RotationSystem::Srs);
         piece.row = BOARD_HEIGHT as i32;
         assert!(!game.fits(&piece));
     }
@@ -105,7 +118,7 @@ mod tests {
     fn scoring_single() {
         let mut game = make_game();
         setup_full_rows(&mut game, 1);
-        game.current = Piece::new(0);
+        game.current = Piece::new(0, RotationSystem::Srs);
         game.current.row = (BOARD_HEIGHT as i32) - 2;
         game.current.col = 0;
         game.lock_and_begin_clear();
@@ -116,7 +129,7 @@ mod tests {
     fn scoring_tetris() {
         let mut game = make_game();
         setup_full_rows(&mut game, 4);
-        game.current = Piece::new(0);
+        game.current = Piece::new(0, RotationSystem::Srs);
         game.current.row = (BOARD_HEIGHT as i32) - 5;
         game.lock_and_begin_clear();
         assert!(game.score >= 800);
@@ -128,18 +141,39 @@ mod tests {
         let mut game = make_game();
         game.back_to_back = true;
         setup_full_rows(&mut game, 4);
-        game.current = Piece::new(0);
+        game.current = Piece::new(0, RotationSystem::Srs);
         game.current.row = (BOARD_HEIGHT as i32) - 5;
         game.lock_and_begin_clear();
         assert!(game.score >= 1200);
     }
 
+    #[test]
+    fn scoring_tracks_and_breaks_b2b_chain() {
+        let mut game = make_game();
+        game.back_to_back = true;
+        game.b2b_chain = 3;
+        setup_full_rows(&mut game, 4);
+        game.current = Piece::new(0, RotationSystem::Srs);
+        game.current.row = (BOARD_HEIGHT as i32) - 5;
+        game.lock_and_begin_clear();
+        assert_eq!(game.b2b_chain, 4);
+        assert_eq!(game.last_action.as_ref().unwrap().b2b_chain, 4);
+
+        game.board = [[EMPTY; BOARD_WIDTH]; BOARD_HEIGHT];
+        setup_full_rows(&mut game, 1);
+        game.current = Piece::new(0, RotationSystem::Srs);
+        game.current.row = (BOARD_HEIGHT as i32) - 2;
+        game.lock_and_begin_clear();
+        assert_eq!(game.b2b_chain, 0);
+        assert_eq!(game.last_action.as_ref().unwrap().b2b_broken, 4);
+    }
+
     #[test]
     fn scoring_combo() {
         let mut game = make_game();
         game.combo = 1;
         setup_full_rows(&mut game, 1);
-        game.current = Piece::new(0);
+        game.current = Piece::new(0, RotationSystem::Srs);
         game.current.row = (BOARD_HEIGHT as i32) - 2;
         game.lock_and_begin_clear();
         assert!(game.score >= 200);
@@ -152,7 +186,7 @@ mod tests {
 
         let t_row = (BOARD_HEIGHT as i32) - 1;
         let t_col = 4;
-        game.current = Piece::new(KIND_T);
+        game.current = Piece::new(KIND_T, RotationSystem::Srs);
         game.current.row = t_row;
         game.current.col = t_col;
         game.current.rotation = 0;
@@ -178,7 +212,7 @@ mod tests {
     fn no_clear_resets_combo() {
         let mut game = make_game();
         game.combo = 5;
-        game.current = Piece::new(0);
+        game.current = Piece::new(0, RotationSystem::Srs);
         game.current.row = 0;
         game.lock_and_begin_clear();
         assert_eq!(game.combo, -1);
@@ -198,9 +232,64 @@ mod tests {
         assert_eq!(kick_index(0, 3), 7);
     }
 
+    fn make_game_180() -> Game {
+        let settings = Settings {
+            rotate_180: true,
+            ..test_settings()
+        };
+        Game::new(GameMode::Versus, &settings, 0)
+    }
+
+    #[test]
+    fn rotate_180_disabled_by_default() {
+        let mut game = make_game();
+        game.current = Piece::new(KIND_T, RotationSystem::Srs);
+        game.rotate_180();
+        assert_eq!(game.current.rotation, 0);
+    }
+
+    #[test]
+    fn rotate_180_open_space() {
+        let mut game = make_game_180();
+        game.current = Piece::new(KIND_T, RotationSystem::Srs);
+        game.current.row = 10;
+        game.rotate_180();
+        assert_eq!(game.current.rotation, 2);
+        assert_eq!(game.current.row, 10);
+        assert_eq!(game.current.col, 4);
+        assert!(game.last_move == LastMove::Rotate);
+    }
+
+    #[test]
+    fn rotate_180_kicks_up_off_floor() {
+        let mut game = make_game_180();
+        let floor = (BOARD_HEIGHT as i32) - 1;
+        game.current = Piece::new(KIND_T, RotationSystem::Srs);
+        game.current.row = floor;
+        game.rotate_180();
+        assert_eq!(game.current.rotation, 2);
+        assert_eq!(game.current.row, floor - 1);
+    }
+
+    #[test]
+    fn rotate_180_i_piece_kicks() {
+        let mut game = make_game_180();
+        game.current = Piece::new(0, RotationSystem::Srs);
+        game.current.rotation = 1;
+        game.current.row = 10;
+        game.current.col = 4;
+        // Block the unkicked target so the second SRS+ test (one column right) is used.
+        let target = Piece { rotation: 3, ..game.current };
+        let (r, c) = target.cells()[0];
+        game.board[r as usize][c as usize] = 1;
+        game.rotate_180();
+        assert_eq!(game.current.rotation, 3);
+        assert_eq!(game.current.col, 5);
+    }
+
     #[test]
     fn piece_new_i_spawn() {
-        let p = Piece::new(0);
+        let p = Piece::new(0, RotationSystem::Srs);
         assert_eq!(p.kind, 0);
         assert_eq!(p.rotation, 0);
         assert_eq!(p.row, (BOARD_HEIGHT - VISIBLE_HEIGHT) as i32);
@@ -209,21 +298,131 @@ mod tests {
 
     #[test]
     fn piece_new_o_spawn() {
-        let p = Piece::new(KIND_O);
+        let p = Piece::new(KIND_O, RotationSystem::Srs);
         assert_eq!(p.row, (BOARD_HEIGHT - VISIBLE_HEIGHT) as i32 - 1);
         assert_eq!(p.col, (BOARD_WIDTH as i32) / 2 - 1);
     }
 
     #[test]
     fn piece_new_t_spawn() {
-        let p = Piece::new(KIND_T);
+        let p = Piece::new(KIND_T, RotationSystem::Srs);
         assert_eq!(p.row, (BOARD_HEIGHT - VISIBLE_HEIGHT) as i32);
         assert_eq!(p.col, (BOARD_WIDTH as i32) / 2 - 1);
     }
 
+    fn make_game_with(rotation: RotationSystem) -> Game {
+        let settings = Settings {
+            rotation,
+            ..test_settings()
+        };
+        Game::new(GameMode::Versus, &settings, 0)
+    }
+
+    #[test]
+    fn rotation_states_are_connected_tetrominoes() {
+        for system in RotationSystem::ALL {
+            for kind in 0..7 {
+                for rot in 0..4 {
+                    let blocks = system.blocks(kind, rot);
+                    for (i, a) in blocks.iter().enumerate() {
+                        assert!(!blocks[i + 1..].contains(a), "{:?} {} {}", system, kind, rot);
+                        let touches = blocks
+                            .iter()
+                            .any(|b| (a[0] - b[0]).abs() + (a[1] - b[1]).abs() == 1);
+                        assert!(touches, "{:?} {} {}", system, kind, rot);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn spawned_pieces_are_visible_and_fit() {
+        for system in RotationSystem::ALL {
+            let game = make_game_with(system);
+            for kind in 0..7 {
+                let p = Piece::new(kind, system);
+                assert!(game.fits(&p), "{:?} {}", system, kind);
+                let bottom = p.cells().iter().map(|&(r, _)| r).max().unwrap();
+                assert!(bottom >= BUFFER_HEIGHT as i32, "{:?} {}", system, kind);
+            }
+        }
+    }
+
+    #[test]
+    fn classic_rotation_never_kicks() {
+        let mut game = make_game_with(RotationSystem::Classic);
+        game.current = Piece::new(KIND_T, RotationSystem::Classic);
+        game.current.rotation = 1;
+        game.current.row = 10;
+        game.current.col = 0;
+        game.rotate_cw();
+        assert_eq!(game.current.rotation, 1);
+    }
+
+    #[test]
+    fn ars_kicks_off_wall() {
+        let mut game = make_game_with(RotationSystem::Ars);
+        game.current = Piece::new(KIND_T, RotationSystem::Ars);
+        game.current.rotation = 3;
+        game.current.row = 10;
+        game.current.col = 0;
+        game.rotate_cw();
+        assert_eq!(game.current.rotation, 0);
+        assert_eq!(game.current.col, 1);
+    }
+
+    #[test]
+    fn ars_center_column_blocks_kick() {
+        let mut game = make_game_with(RotationSystem::Ars);
+        game.current = Piece::new(KIND_T, RotationSystem::Ars);
+        game.current.row = 10;
+        game.current.col = 4;
+        // The rotated T needs the cell above its centre; a kick would clear it,
+        // but ARS refuses because the blocker is in the centre column.
+        game.board[9][4] = 1;
+        game.rotate_cw();
+        assert_eq!(game.current.rotation, 0);
+        assert_eq!(game.current.col, 4);
+    }
+
+    #[test]
+    fn nrs_has_no_kicks() {
+        let mut game = make_game_with(RotationSystem::Nrs);
+        game.current = Piece::new(0, RotationSystem::Nrs);
+        game.current.rotation = 1;
+        game.current.row = 10;
+        game.current.col = 0;
+        game.rotate_cw();
+        assert_eq!(game.current.rotation, 1);
+    }
+
+    #[test]
+    fn tspin_detected_in_ars_orientation() {
+        let mut game = make_game_with(RotationSystem::Ars);
+        let floor = BOARD_HEIGHT as i32 - 1;
+        // ARS rotation 2 points up with the flat side on the floor.
+        game.current = Piece::new(KIND_T, RotationSystem::Ars);
+        game.current.rotation = 2;
+        game.current.row = floor - 1;
+        game.current.col = 4;
+        game.last_move = LastMove::Rotate;
+        game.board[(floor - 1) as usize][3] = 1;
+        game.board[(floor - 1) as usize][5] = 1;
+        game.board[(floor - 2) as usize][3] = 1;
+        for c in 0..BOARD_WIDTH {
+            if !(3..=5).contains(&c) {
+                game.board[floor as usize][c] = 1;
+            }
+        }
+        game.lock_and_begin_clear();
+        let action = game.last_action.as_ref().expect("line clear");
+        assert!(action.is_tspin);
+    }
+
     #[test]
     fn bag_7bag_completeness() {
-        let mut bag = Bag::new(true);
+        let mut bag = RandomizerKind::SevenBag.build(1);
         let mut counts = [0u32; 7];
         for _ in 0..7 {
             let kind = bag.next();
@@ -237,7 +436,7 @@ mod tests {
 
     #[test]
     fn bag_7bag_two_cycles() {
-        let mut bag = Bag::new(true);
+        let mut bag = RandomizerKind::SevenBag.build(1);
         let mut counts = [0u32; 7];
         for _ in 0..14 {
             let kind = bag.next();
@@ -248,15 +447,110 @@ mod tests {
         }
     }
 
+    #[test]
+    fn bag_14bag_completeness() {
+        let mut bag = RandomizerKind::FourteenBag.build(1);
+        let mut counts = [0u32; 7];
+        for _ in 0..14 {
+            counts[bag.next()] += 1;
+        }
+        for count in counts {
+            assert_eq!(count, 2);
+        }
+    }
+
     #[test]
     fn bag_random_mode() {
-        let mut bag = Bag::new(false);
+        let mut bag = RandomizerKind::Random.build(1);
         for _ in 0..100 {
             let kind = bag.next();
             assert!(kind < 7);
         }
     }
 
+    #[test]
+    fn tgm_first_piece_not_s_z_o() {
+        for seed in 0..50 {
+            let first = RandomizerKind::Tgm.build(seed).next();
+            assert!(![KIND_O, 3, 4].contains(&first), "seed {} gave {}", seed, first);
+        }
+    }
+
+    #[test]
+    fn tgm_avoids_recent_pieces() {
+        // With 6 rolls against a 4-piece history, repeats of the previous piece are rare.
+        let mut r = RandomizerKind::Tgm.build(9);
+        let seq: Vec<usize> = (0..1000).map(|_| r.next()).collect();
+        let repeats = seq.windows(2).filter(|w| w[0] == w[1]).count();
+        assert!(repeats < 20, "{} repeats", repeats);
+    }
+
+    #[test]
+    fn nes_rerolls_repeats() {
+        let mut r = RandomizerKind::Nes.build(3);
+        let seq: Vec<usize> = (0..1000).map(|_| r.next()).collect();
+        assert!(seq.iter().all(|&k| k < 7));
+        // A plain random source repeats about 1 in 7; NES cuts that to about 1 in 28.
+        let repeats = seq.windows(2).filter(|w| w[0] == w[1]).count();
+        assert!(repeats < 80, "{} repeats", repeats);
+    }
+
+    #[test]
+    fn bag_same_seed_same_sequence() {
+        for kind in RandomizerKind::ALL {
+            let mut a = kind.build(42);
+            let mut b = kind.build(42);
+            for _ in 0..70 {
+                assert_eq!(a.next(), b.next());
+            }
+        }
+    }
+
+    #[test]
+    fn bag_different_seed_different_sequence() {
+        let mut a = RandomizerKind::SevenBag.build(1);
+        let mut b = RandomizerKind::SevenBag.build(2);
+        let seq_a: Vec<usize> = (0..28).map(|_| a.next()).collect();
+        let seq_b: Vec<usize> = (0..28).map(|_| b.next()).collect();
+        assert_ne!(seq_a, seq_b);
+    }
+
+    #[test]
+    fn game_same_seed_same_pieces() {
+        let settings = test_settings();
+        let mut a = Game::new(GameMode::Marathon, &settings, 7);
+        let mut b = Game::new(GameMode::Marathon, &settings, 7);
+        assert_eq!(a.current.kind, b.current.kind);
+        assert_eq!(a.next_queue, b.next_queue);
+        for _ in 0..20 {
+            a.hard_drop();
+            a.are_timer = None;
+            a.spawn_next();
+            b.hard_drop();
+            b.are_timer = None;
+            b.spawn_next();
+            assert_eq!(a.current.kind, b.current.kind);
+        }
+    }
+
+    #[test]
+    fn rng_known_sequence() {
+        // Reference values for SplitMix64 seeded with 0.
+        let mut rng = SeededRng::new(0);
+        assert_eq!(rng.next_u64(), 0xE220_A839_7B1D_CDAF);
+        assert_eq!(rng.next_u64(), 0x6E78_9E6A_A1B9_65F4);
+    }
+
+    #[test]
+    fn rng_below_in_range() {
+        let mut rng = SeededRng::new(123);
+        for n in 1..20 {
+            for _ in 0..50 {
+                assert!(rng.below(n) < n);
+            }
+        }
+    }
+
     // --- Garbage queue tests ---
 
     fn make_action(
@@ -276,6 +570,9 @@ mod tests {
             is_back_to_back,
             combo,
             is_all_clear,
+            garbage_lines: 0,
+            b2b_chain: if is_back_to_back { 1 } else { 0 },
+            b2b_broken: 0,
         }
     }
 
@@ -328,6 +625,60 @@ mod tests {
         assert_eq!(q.total_pending(), 3);
     }
 
+    #[test]
+    fn delayed_garbage_becomes_ready_in_order() {
+        let start = Instant::now();
+        let mut q = GarbageQueue::with_delay(Duration::from_millis(500));
+        q.push_at(GarbageEvent { lines: 2, hole_column: 0 }, start);
+        q.push_at(GarbageEvent { lines: 3, hole_column: 1 }, start + Duration::from_millis(300));
+        assert_eq!(q.ready_lines(start), 0);
+        assert!(q.take_ready(start, None).is_empty());
+        assert_eq!(q.ready_lines(start + Duration::from_millis(500)), 2);
+        assert_eq!(q.ready_lines(start + Duration::from_millis(800)), 5);
+
+        let taken = q.take_ready(start + Duration::from_millis(600), None);
+        assert_eq!(taken.len(), 1);
+        assert_eq!(q.total_pending(), 3);
+    }
+
+    #[test]
+    fn garbage_cap_splits_an_event() {
+        let start = Instant::now();
+        let mut q = GarbageQueue::new();
+        q.push_at(GarbageEvent { lines: 3, hole_column: 4 }, start);
+        q.push_at(GarbageEvent { lines: 2, hole_column: 5 }, start);
+        let taken = q.take_ready(start, Some(4));
+        assert_eq!(taken.iter().map(|e| (e.lines, e.hole_column)).collect::<Vec<_>>(), vec![(3, 4), (1, 5)]);
+        assert_eq!(q.total_pending(), 1);
+        assert_eq!(q.pending[0].hole_column, 5);
+        assert_eq!(q.ready_lines(start), 1);
+    }
+
+    #[test]
+    fn cancel_clears_the_oldest_delayed_garbage() {
+        let start = Instant::now();
+        let mut q = GarbageQueue::with_delay(Duration::from_secs(1));
+        q.push_at(GarbageEvent { lines: 2, hole_column: 0 }, start);
+        q.push_at(GarbageEvent { lines: 2, hole_column: 1 }, start + Duration::from_secs(5));
+        assert_eq!(q.cancel(2), 0);
+        assert_eq!(q.ready_lines(start + Duration::from_secs(2)), 0);
+        assert_eq!(q.ready_lines(start + Duration::from_secs(6)), 2);
+    }
+
+    #[test]
+    fn multiplier_ramps_after_the_margin() {
+        let rules = GarbageRules {
+            margin_secs: Some(60),
+            ..GarbageRules::default()
+        };
+        assert_eq!(rules.multiplier(Duration::from_secs(30)), 1.0);
+        assert_eq!(rules.scale(4, Duration::from_secs(60)), 4);
+        assert_eq!(rules.multiplier(Duration::from_secs(160)), 2.0);
+        assert_eq!(rules.scale(4, Duration::from_secs(160)), 8);
+        assert_eq!(rules.scale(1, Duration::from_secs(120)), 2);
+        assert_eq!(GarbageRules::default().scale(4, Duration::from_secs(600)), 4);
+    }
+
     #[test]
     fn attack_single() {
         let a = make_action(1, false, false, false, 0, false);
@@ -405,4 +756,839 @@ mod tests {
         let a = make_action(0, false, false, false, 0, false);
         assert_eq!(calculate_attack(&a), 0);
     }
+
+    // --- Attack table tests ---
+
+    #[test]
+    fn guideline_table_matches_calculate_attack() {
+        for a in [
+            make_action(4, false, false, true, 3, false),
+            make_action(2, true, false, false, 0, false),
+            make_action(1, false, false, false, 0, true),
+        ] {
+            assert_eq!(AttackTable::Guideline.attack(&a), calculate_attack(&a));
+        }
+    }
+
+    #[test]
+    fn tetrio_b2b_bonus_grows_with_the_chain() {
+        let mut a = make_action(4, false, false, true, 0, false);
+        assert_eq!(AttackTable::Tetrio.attack(&a), 5);
+        a.b2b_chain = 3;
+        assert_eq!(AttackTable::Tetrio.attack(&a), 6);
+        a.b2b_chain = 8;
+        assert_eq!(AttackTable::Tetrio.attack(&a), 7);
+    }
+
+    #[test]
+    fn tetrio_combo_multiplies_the_clear() {
+        assert_eq!(AttackTable::Tetrio.attack(&make_action(2, true, false, false, 4, false)), 8);
+        assert_eq!(AttackTable::Tetrio.attack(&make_action(2, false, false, false, 2, false)), 1);
+        // Singles are worth nothing on their own but long combos still send.
+        assert_eq!(AttackTable::Tetrio.attack(&make_action(1, false, false, false, 1, false)), 0);
+        assert_eq!(AttackTable::Tetrio.attack(&make_action(1, false, false, false, 6, false)), 2);
+    }
+
+    #[test]
+    fn tetrio_surge_releases_on_a_long_broken_chain() {
+        let mut a = make_action(1, false, false, false, 0, false);
+        a.b2b_broken = TETRIO_SURGE_AT - 1;
+        assert_eq!(AttackTable::Tetrio.attack(&a), 0);
+        a.b2b_broken = 6;
+        assert_eq!(AttackTable::Tetrio.attack(&a), 6);
+    }
+
+    #[test]
+    fn ppt_table_has_steeper_combos() {
+        assert_eq!(AttackTable::PuyoTetris.attack(&make_action(1, false, false, false, 1, false)), 1);
+        assert_eq!(AttackTable::PuyoTetris.attack(&make_action(2, false, false, false, 4, false)), 3);
+        assert_eq!(AttackTable::PuyoTetris.attack(&make_action(1, false, false, false, 12, false)), 5);
+        assert_eq!(AttackTable::PuyoTetris.attack(&make_action(1, false, false, false, 0, true)), 10);
+    }
+
+    #[test]
+    fn ppt_b2b_tspin_triple_gets_two() {
+        assert_eq!(AttackTable::PuyoTetris.attack(&make_action(3, true, false, true, 0, false)), 8);
+        assert_eq!(AttackTable::PuyoTetris.attack(&make_action(4, false, false, true, 0, false)), 5);
+    }
+
+    #[test]
+    fn lines_minus_one_ignores_bonuses() {
+        assert_eq!(AttackTable::LinesMinusOne.attack(&make_action(1, false, false, false, 5, false)), 0);
+        assert_eq!(AttackTable::LinesMinusOne.attack(&make_action(4, false, false, true, 3, true)), 3);
+        assert_eq!(AttackTable::LinesMinusOne.attack(&make_action(0, true, false, false, 0, false)), 0);
+    }
+
+    fn runs(holes: &mut HoleGenerator, lines: u32) -> Vec<(u32, usize)> {
+        holes.deal(lines).iter().map(|e| (e.lines, e.hole_column)).collect()
+    }
+
+    #[test]
+    fn clean_holes_share_one_column_per_attack() {
+        let mut holes = HoleGenerator::new(GarbageStyle::Clean, 7);
+        let first = runs(&mut holes, 4);
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].0, 4);
+        let second = runs(&mut holes, 2);
+        assert_eq!(second.len(), 1);
+        assert_ne!(second[0].1, first[0].1);
+        assert!(runs(&mut holes, 0).is_empty());
+    }
+
+    #[test]
+    fn column_holes_never_move() {
+        let mut holes = HoleGenerator::new(GarbageStyle::Column, 7);
+        let column = runs(&mut holes, 3)[0].1;
+        for lines in [1, 4, 2] {
+            assert_eq!(runs(&mut holes, lines), vec![(lines, column)]);
+        }
+    }
+
+    #[test]
+    fn cheese_holes_move_every_line() {
+        let mut holes = HoleGenerator::new(GarbageStyle::Cheese, 7);
+        let dealt = runs(&mut holes, 8);
+        assert_eq!(dealt.len(), 8);
+        assert!(dealt.windows(2).all(|w| w[0].1 != w[1].1));
+        assert!(dealt.iter().all(|&(lines, hole)| lines == 1 && hole < BOARD_WIDTH));
+    }
+
+    #[test]
+    fn messy_holes_are_reproducible_from_the_seed() {
+        let mut a = HoleGenerator::new(GarbageStyle::Messy, 99);
+        let mut b = HoleGenerator::new(GarbageStyle::Messy, 99);
+        for lines in [4, 10, 1, 6] {
+            let dealt = runs(&mut a, lines);
+            assert_eq!(dealt.iter().map(|r| r.0).sum::<u32>(), lines);
+            assert_eq!(dealt, runs(&mut b, lines));
+        }
+    }
+
+    // --- Match stats tests ---
+
+    fn lock_with(stats: &mut MatchStats, action: Option<ClearAction>, sent: u32) {
+        let mut game = make_game();
+        game.combo = action.as_ref().map_or(-1, |a| a.combo);
+        game.last_action = action;
+        stats.record_lock(&game, GarbageRules::default(), sent);
+    }
+
+    #[test]
+    fn stats_split_attack_into_sent_and_cancelled() {
+        let mut stats = MatchStats::default();
+        lock_with(&mut stats, Some(make_action(4, false, false, false, 0, false)), 1);
+        assert_eq!(stats.pieces, 1);
+        assert_eq!((stats.attack_sent, stats.attack_cancelled), (1, 3));
+    }
+
+    #[test]
+    fn stats_ignore_a_stale_clear() {
+        let mut stats = MatchStats::default();
+        let mut game = make_game();
+        game.last_action = Some(make_action(4, false, false, false, 0, false));
+        game.combo = -1;
+        stats.record_lock(&game, GarbageRules::default(), 0);
+        assert_eq!(stats.pieces, 1);
+        assert_eq!(stats.attack_cancelled, 0);
+    }
+
+    #[test]
+    fn stats_track_longest_b2b_chain_and_combo() {
+        let mut stats = MatchStats::default();
+        lock_with(&mut stats, Some(make_action(4, false, false, false, 0, false)), 4);
+        lock_with(&mut stats, Some(make_action(4, false, false, true, 1, false)), 5);
+        lock_with(&mut stats, Some(make_action(2, true, false, true, 2, false)), 6);
+        lock_with(&mut stats, Some(make_action(1, false, false, false, 3, false)), 1);
+        lock_with(&mut stats, Some(make_action(4, false, false, true, 0, false)), 5);
+        assert_eq!(stats.max_b2b, 2);
+        assert_eq!(stats.max_combo, 3);
+    }
+
+    #[test]
+    fn stats_count_tspins_by_lines() {
+        let mut stats = MatchStats::default();
+        lock_with(&mut stats, Some(make_action(2, true, false, false, 0, false)), 4);
+        lock_with(&mut stats, Some(make_action(3, true, false, false, 0, false)), 6);
+        lock_with(&mut stats, Some(make_action(1, true, true, false, 0, false)), 0);
+        lock_with(&mut stats, None, 0);
+        assert_eq!(stats.tspins, [0, 1, 1]);
+        assert_eq!(stats.tspin_minis, 1);
+    }
+
+    #[test]
+    fn stats_rates_over_duration() {
+        let mut stats = MatchStats {
+            pieces: 120,
+            attack_sent: 20,
+            attack_cancelled: 10,
+            garbage_cleared: 6,
+            ..MatchStats::default()
+        };
+        stats.finish(Duration::from_secs(60));
+        assert_eq!(stats.pps(), 2.0);
+        assert_eq!(stats.apm(), 30.0);
+        assert_eq!(stats.vs_score(), 60.0);
+    }
+
+    #[test]
+    fn lock_counts_cleared_garbage_rows() {
+        let mut game = make_game();
+        game.receive_garbage(1, BOARD_WIDTH - 1);
+        game.board[BOARD_HEIGHT - 2][..BOARD_WIDTH - 1].fill(1);
+        game.board[BOARD_HEIGHT - 3][0] = 1;
+        game.current = Piece::new(0, game.rotation_system);
+        game.rotate_cw();
+        while game.move_piece(0, 1) {}
+        game.hard_drop();
+        assert_eq!(game.lines, 2);
+        assert_eq!(game.last_action.as_ref().unwrap().garbage_lines, 1);
+    }
+
+    // --- Replay tests ---
+
+    #[test]
+    fn replay_reproduces_run() {
+        use crate::game::replay::{Replay, ReplayRecorder};
+        use std::time::Duration;
+
+        let settings = Settings::default();
+        let script = [
+            GameInput::MoveLeft,
+            GameInput::RotateCw,
+            GameInput::HardDrop,
+            GameInput::AreEnd,
+            GameInput::Hold,
+            GameInput::MoveRight,
+            GameInput::MoveRight,
+            GameInput::Gravity,
+            GameInput::Lock,
+            GameInput::AreEnd,
+            GameInput::RotateCcw,
+            GameInput::SoftDrop,
+            GameInput::HardDrop,
+            GameInput::AreEnd,
+        ];
+
+        let mut original = Game::new(GameMode::Marathon, &settings, 99);
+        let mut recorder = ReplayRecorder::new();
+        for (i, &input) in script.iter().enumerate() {
+            original.apply_input(input);
+            recorder.record(Duration::from_millis(i as u64 * 10), input);
+        }
+        let replay = recorder.finish(&original, &settings, String::new());
+        let json = serde_json::to_string(&replay).unwrap();
+        let replay: Replay = serde_json::from_str(&json).unwrap();
+
+        let mut playback = Game::new(replay.mode, &replay.settings, replay.seed);
+        for ev in &replay.events {
+            playback.apply_input(ev.input);
+        }
+        assert_eq!(playback.board, original.board);
+        assert_eq!(playback.score, original.score);
+        assert_eq!(playback.hold, original.hold);
+        assert_eq!(playback.current.kind, original.current.kind);
+        assert_eq!(playback.next_queue, original.next_queue);
+    }
+
+    #[test]
+    fn apply_input_cannot_lock_a_piece_twice() {
+        let settings = Settings::default();
+        let mut game = Game::new(GameMode::Marathon, &settings, 7);
+        setup_full_rows(&mut game, 1);
+        game.current = Piece::new(0, RotationSystem::Srs);
+        game.current.row = (BOARD_HEIGHT as i32) - 2;
+        game.current.col = 0;
+        assert!(game.apply_input(GameInput::HardDrop));
+        assert!(game.is_animating());
+        let (board, score) = (game.board, game.score);
+
+        assert!(!game.apply_input(GameInput::HardDrop));
+        assert!(!game.apply_input(GameInput::Lock));
+        assert!(!game.apply_input(GameInput::AreEnd));
+        assert_eq!((game.board, game.score, game.lines), (board, score, 1));
+
+        assert!(game.apply_input(GameInput::ClearDone));
+        assert!(game.in_are());
+        assert!(!game.apply_input(GameInput::HardDrop));
+        assert!(!game.apply_input(GameInput::ClearDone));
+        assert_eq!(game.lines, 1);
+        assert!(game.apply_input(GameInput::AreEnd));
+        assert!(game.apply_input(GameInput::MoveLeft));
+    }
+
+    // --- Lockstep tests ---
+
+    /// One side of a verified game as the match loop drives it: inputs land on
+    /// the game and in the log, and locks settle garbage the way
+    /// `outgoing_attack` does.
+    struct LockstepSide {
+        game: Game,
+        log: LockstepLog,
+        queue: GarbageQueue,
+        holes: HoleGenerator,
+        start: Instant,
+    }
+
+    impl LockstepSide {
+        fn new(seed: u64) -> Self {
+            Self {
+                game: Game::new(GameMode::Versus, &test_settings(), seed),
+                log: LockstepLog::default(),
+                queue: GarbageQueue::new(),
+                holes: HoleGenerator::new(GarbageStyle::Clean, 5),
+                start: Instant::now(),
+            }
+        }
+
+        /// Applies `input` and returns the attack runs a lock sent.
+        fn input(&mut self, input: GameInput) -> Vec<GarbageEvent> {
+            self.game.elapsed += Duration::from_millis(100);
+            self.game.apply_input(input);
+            self.log.push(&self.game, LockstepAction::Input(input));
+            if !matches!(input, GameInput::HardDrop | GameInput::Lock) {
+                return Vec::new();
+            }
+            let rules = GarbageRules::default();
+            match &self.game.last_action {
+                Some(action) if self.game.combo >= 0 && action.cleared_lines > 0 => {
+                    let attack = rules.attack(action, self.game.elapsed);
+                    let remaining = self.queue.counter(attack, rules);
+                    self.holes.deal(remaining)
+                }
+                _ => {
+                    let rising = self.queue.take_ready(self.start + self.game.elapsed, None);
+                    if !rising.is_empty() {
+                        self.log.push(&self.game, LockstepAction::Rise(rising.iter().map(|e| e.lines).sum()));
+                        self.game.begin_garbage_rise(rising);
+                        self.game.finish_garbage_rise();
+                    }
+                    Vec::new()
+                }
+            }
+        }
+
+        fn receive(&mut self, attack: GarbageEvent) {
+            self.queue.push_at(attack, self.start + self.game.elapsed);
+            self.log.push(&self.game, LockstepAction::Incoming(attack));
+        }
+    }
+
+    /// A seed whose second piece is an I.
+    fn seed_with_i_second() -> u64 {
+        (0..).find(|&seed| make_game_seeded(seed).next_queue[0] == 0).unwrap()
+    }
+
+    fn make_game_seeded(seed: u64) -> Game {
+        Game::new(GameMode::Versus, &test_settings(), seed)
+    }
+
+    /// Takes four lines of garbage under the first piece, then clears them with
+    /// a vertical I. Returns the side and the verifier watching it, which has
+    /// already been told about the garbage.
+    fn garbage_then_tetris() -> (LockstepSide, Verifier, Vec<GarbageEvent>) {
+        let seed = seed_with_i_second();
+        let mut side = LockstepSide::new(seed);
+        let mut verifier = Verifier::new(&test_settings(), seed, 5);
+
+        // Where the I will stand once turned upright.
+        let mut probe = make_game_seeded(seed);
+        probe.apply_input(GameInput::HardDrop);
+        probe.apply_input(GameInput::AreEnd);
+        probe.apply_input(GameInput::RotateCw);
+        let hole = probe.current.cells()[0].1 as usize;
+        assert!(hole >= 3);
+
+        let garbage = GarbageEvent { lines: 4, hole_column: hole };
+        verifier.sent(garbage);
+        side.receive(garbage);
+        for _ in 0..5 {
+            side.input(GameInput::MoveLeft);
+        }
+        side.input(GameInput::HardDrop);
+        side.input(GameInput::AreEnd);
+        side.input(GameInput::RotateCw);
+        let sent = side.input(GameInput::HardDrop);
+        assert_eq!(side.game.lines, 4);
+        (side, verifier, sent)
+    }
+
+    #[test]
+    fn verifier_accepts_an_honest_game() {
+        let (mut side, mut verifier, sent) = garbage_then_tetris();
+        assert_eq!(sent.iter().map(|e| e.lines).sum::<u32>(), 4);
+        for attack in sent {
+            verifier.claim(attack);
+        }
+        verifier.feed(&side.log.batch(&side.game, true));
+        assert!(verifier.is_finished());
+        assert_eq!(verifier.failure(), None);
+    }
+
+    #[test]
+    fn verifier_catches_an_unearned_attack() {
+        let (mut side, mut verifier, sent) = garbage_then_tetris();
+        for attack in sent {
+            verifier.claim(attack);
+        }
+        verifier.claim(GarbageEvent { lines: 2, hole_column: 0 });
+        verifier.feed(&side.log.batch(&side.game, true));
+        assert_eq!(verifier.failure(), Some(Mismatch::Attack));
+    }
+
+    #[test]
+    fn verifier_catches_a_bigger_attack_than_earned() {
+        let (mut side, mut verifier, sent) = garbage_then_tetris();
+        verifier.feed(&side.log.batch(&side.game, false));
+        verifier.claim(GarbageEvent {
+            lines: sent[0].lines + 1,
+            ..sent[0]
+        });
+        assert_eq!(verifier.failure(), Some(Mismatch::Attack));
+    }
+
+    #[test]
+    fn verifier_catches_a_piece_dropped_twice() {
+        let (mut side, mut verifier, sent) = garbage_then_tetris();
+        for attack in sent {
+            verifier.claim(attack);
+        }
+        // Dropped again during entry delay, so the cleared rows would count twice.
+        side.log.push(&side.game, LockstepAction::Input(GameInput::HardDrop));
+        verifier.feed(&side.log.batch(&side.game, false));
+        assert_eq!(verifier.failure(), Some(Mismatch::OutOfTurn));
+        assert!(!Mismatch::OutOfTurn.is_desync());
+    }
+
+    #[test]
+    fn verifier_catches_held_garbage() {
+        let seed = 3;
+        let mut side = LockstepSide::new(seed);
+        let mut verifier = Verifier::new(&test_settings(), seed, 5);
+        let garbage = GarbageEvent { lines: 2, hole_column: 0 };
+        verifier.sent(garbage);
+        side.receive(garbage);
+        side.game.elapsed += RISE_SLACK;
+        // A lock that clears nothing, with the garbage left out of the log.
+        side.game.apply_input(GameInput::HardDrop);
+        side.log.push(&side.game, LockstepAction::Input(GameInput::HardDrop));
+        side.input(GameInput::AreEnd);
+        verifier.feed(&side.log.batch(&side.game, false));
+        assert_eq!(verifier.failure(), Some(Mismatch::HeldGarbage));
+    }
+
+    #[test]
+    fn verifier_catches_garbage_that_never_arrived() {
+        let seed = 3;
+        let mut side = LockstepSide::new(seed);
+        let mut verifier = Verifier::new(&test_settings(), seed, 5);
+        verifier.sent(GarbageEvent { lines: 4, hole_column: 2 });
+        side.receive(GarbageEvent { lines: 1, hole_column: 2 });
+        verifier.feed(&side.log.batch(&side.game, false));
+        assert_eq!(verifier.failure(), Some(Mismatch::Incoming));
+    }
+
+    #[test]
+    fn verifier_catches_a_board_its_inputs_do_not_make() {
+        let seed = 3;
+        let mut side = LockstepSide::new(seed);
+        let mut verifier = Verifier::new(&test_settings(), seed, 5);
+        side.input(GameInput::MoveLeft);
+        // Moved without logging it.
+        side.game.apply_input(GameInput::MoveLeft);
+        verifier.feed(&side.log.batch(&side.game, false));
+        assert_eq!(verifier.failure(), Some(Mismatch::Board));
+    }
+
+    #[test]
+    fn verifier_flags_a_stream_that_stops() {
+        let mut verifier = Verifier::new(&test_settings(), 3, 5);
+        verifier.feed(&InputBatch {
+            events: Vec::new(),
+            hash: None,
+            last: false,
+        });
+        verifier.give_up();
+        assert_eq!(verifier.failure(), Some(Mismatch::Incomplete));
+        assert!(verifier.failure().unwrap().is_desync());
+    }
+
+    #[test]
+    fn verified_games_share_their_pieces() {
+        assert_eq!(piece_seed(42, 0), piece_seed(42, 0));
+        assert_ne!(piece_seed(42, 0), piece_seed(42, 1));
+    }
+
+    // --- Settings tests ---
+
+    #[test]
+    fn settings_tolerate_missing_and_unknown_fields() {
+        let s: Settings = serde_json::from_str(r#"{"level": 5, "no_such_field": true}"#).unwrap();
+        assert_eq!(s.level, 5);
+        assert_eq!(s.next_count, Settings::default().next_count);
+        assert!(s.bgm && s.sfx);
+    }
+
+    #[test]
+    fn settings_carry_over_the_old_srs_flag() {
+        let s = Settings::from_json(r#"{"level": 3, "srs_enabled": false}"#);
+        assert_eq!((s.level, s.rotation), (3, RotationSystem::Classic));
+        let s = Settings::from_json(r#"{"srs_enabled": true}"#);
+        assert_eq!(s.rotation, RotationSystem::Srs);
+        let s = Settings::from_json(r#"{"rotation": "Ars"}"#);
+        assert_eq!(s.rotation, RotationSystem::Ars);
+    }
+
+    #[test]
+    fn settings_carry_over_the_old_bag_flag() {
+        let s = Settings::from_json(r#"{"bag_randomizer": false}"#);
+        assert_eq!(s.randomizer, RandomizerKind::Random);
+        let s = Settings::from_json(r#"{"bag_randomizer": true}"#);
+        assert_eq!(s.randomizer, RandomizerKind::SevenBag);
+    }
+
+    #[test]
+    fn settings_clamp_pulls_values_into_menu_ranges() {
+        let mut s: Settings =
+            serde_json::from_str(r#"{"lock_delay_ms": 90000, "move_reset": 99, "ultra_time": 5}"#).unwrap();
+        s.clamp();
+        assert_eq!((s.lock_delay_ms, s.move_reset, s.ultra_time), (2000, Some(30), 30));
+
+        let mut s: Settings =
+            serde_json::from_str(r#"{"das_ms": 9000, "arr_ms": 700, "das_release_ms": 0}"#).unwrap();
+        s.clamp();
+        assert_eq!((s.das_ms, s.arr_ms, s.das_release_ms), (500, 100, 30));
+
+        let mut s: Settings =
+            serde_json::from_str(r#"{"garbage": {"delay_ms": 60000, "cap": 0, "margin_secs": 5}}"#).unwrap();
+        s.clamp();
+        assert_eq!(s.garbage.delay_ms, 2000);
+        assert_eq!(s.garbage.cap, Some(1));
+        assert_eq!(s.garbage.margin_secs, Some(MARGIN_STEP_SECS));
+    }
+
+    #[test]
+    fn score_record_randomizer_defaults_for_old_entries() {
+        let r: records::ScoreRecord = serde_json::from_str(
+            r#"{"score": 100, "lines": 4, "level": 1, "time": null, "date": "2024-01-01T00:00:00Z"}"#,
+        )
+        .unwrap();
+        assert_eq!(r.randomizer, None);
+    }
+
+    // --- Headless step tests ---
+
+    #[test]
+    fn step_gravity_moves_piece() {
+        let mut game = make_game();
+        let row = game.current.row;
+        let fired = game.step(&[], Duration::from_millis(1050));
+        assert_eq!(game.current.row, row + 1);
+        assert_eq!(fired, vec![GameInput::Gravity]);
+    }
+
+    #[test]
+    fn step_hard_drop_spawns_next_after_are() {
+        let mut game = make_game();
+        let next = game.next_queue[0];
+        let fired = game.step(&[GameInput::HardDrop], ARE_DELAY + SIM_FRAME);
+        assert!(fired.contains(&GameInput::AreEnd));
+        assert_eq!(game.current.kind, next);
+        assert!(!game.in_are());
+    }
+
+    #[test]
+    fn step_queues_inputs_until_the_next_piece() {
+        let mut game = make_game();
+        game.step(&[GameInput::HardDrop], SIM_FRAME);
+        game.step(&[GameInput::HardDrop], SIM_FRAME);
+        game.step(&[GameInput::HardDrop], SIM_FRAME);
+        assert!(game.has_queued_inputs());
+        let filled = |game: &Game| game.board.iter().flatten().filter(|&&c| c != EMPTY).count();
+        assert_eq!(filled(&game), 4);
+
+        game.step(&[], ARE_DELAY * 3 + SIM_FRAME * 3);
+        assert!(!game.has_queued_inputs());
+        assert_eq!(filled(&game), 12);
+    }
+
+    #[test]
+    fn step_with_locks_sees_every_queued_drop() {
+        let mut game = make_game();
+        let mut locks = 0;
+        game.step_with_locks(&[GameInput::HardDrop; 3], SIM_FRAME, |_| locks += 1);
+        assert_eq!(locks, 1);
+        // The two queued drops lock in the same call once their pieces spawn.
+        game.step_with_locks(&[], ARE_DELAY * 2 + SIM_FRAME * 2, |_| locks += 1);
+        assert_eq!(locks, 3);
+    }
+
+    #[test]
+    fn step_drops_inputs_the_game_cannot_take() {
+        let mut game = make_game();
+        let kind = game.current.kind;
+        game.step(&[GameInput::AreEnd, GameInput::ClearDone], SIM_FRAME);
+        assert!(!game.has_queued_inputs());
+        assert_eq!(game.current.kind, kind);
+    }
+
+    #[test]
+    fn step_lock_delay_locks_resting_piece() {
+        let mut game = make_game();
+        let drops = vec![GameInput::SoftDrop; BOARD_HEIGHT];
+        game.step(&drops, Duration::ZERO);
+        assert!(game.board.iter().all(|row| row.iter().all(|&c| c == EMPTY)));
+        let fired = game.step(&[], game.lock_delay_duration() + Duration::from_millis(1100));
+        assert!(fired.contains(&GameInput::Lock));
+        assert!(game.board.iter().any(|row| row.iter().any(|&c| c != EMPTY)));
+    }
+
+    #[test]
+    fn step_ultra_times_out() {
+        let settings = Settings {
+            ultra_time: 30,
+            ..test_settings()
+        };
+        let mut game = Game::new(GameMode::Ultra, &settings, 0);
+        game.step(&[], Duration::from_secs(29));
+        assert!(!game.game_over);
+        let fired = game.step(&[], Duration::from_secs(2));
+        assert!(game.game_over);
+        assert_eq!(fired.last(), Some(&GameInput::TimeUp));
+    }
+
+    #[test]
+    fn step_same_seed_same_result() {
+        let script = [
+            GameInput::MoveLeft,
+            GameInput::RotateCw,
+            GameInput::HardDrop,
+            GameInput::MoveRight,
+            GameInput::HardDrop,
+            GameInput::Hold,
+            GameInput::HardDrop,
+        ];
+        let run = || {
+            let mut game = Game::new(GameMode::Marathon, &test_settings(), 99);
+            for &input in &script {
+                game.step(&[input], Duration::from_millis(250));
+            }
+            (game.board, game.score)
+        };
+        assert_eq!(run(), run());
+    }
+
+    // --- Manual clock tests ---
+
+    fn manual_game(mode: GameMode, settings: &Settings) -> (Game, Arc<ManualClock>) {
+        let clock = Arc::new(ManualClock::new());
+        let game = Game::with_manual_clock(mode, settings, 0, clock.clone());
+        (game, clock)
+    }
+
+    #[test]
+    fn clock_lock_delay_expires_exactly() {
+        let (mut game, clock) = manual_game(GameMode::Versus, &test_settings());
+        game.lock_delay = Some(game.now());
+        clock.advance(game.lock_delay_duration() - Duration::from_millis(1));
+        assert!(!game.lock_delay_expired());
+        clock.advance(Duration::from_millis(1));
+        assert!(game.lock_delay_expired());
+    }
+
+    #[test]
+    fn clock_are_ends_after_delay() {
+        let (mut game, clock) = manual_game(GameMode::Versus, &test_settings());
+        let next = game.next_queue[0];
+        game.are_timer = Some(game.now());
+        clock.advance(ARE_DELAY - Duration::from_millis(1));
+        assert!(!game.check_are());
+        assert!(game.in_are());
+        clock.advance(Duration::from_millis(1));
+        assert!(game.check_are());
+        assert_eq!(game.current.kind, next);
+    }
+
+    #[test]
+    fn clock_ultra_time_up() {
+        let settings = Settings {
+            ultra_time: 60,
+            ..test_settings()
+        };
+        let (mut game, clock) = manual_game(GameMode::Ultra, &settings);
+        clock.advance(Duration::from_secs(59));
+        game.update_elapsed();
+        assert!(!game.time_up());
+        assert_eq!(game.time_remaining(), Some(Duration::from_secs(1)));
+        clock.advance(Duration::from_secs(1));
+        game.update_elapsed();
+        assert!(game.time_up());
+    }
+
+    #[test]
+    fn clock_garbage_rises_one_line_per_interval() {
+        let (mut game, clock) = manual_game(GameMode::Versus, &test_settings());
+        game.begin_garbage_rise(vec![GarbageEvent { lines: 3, hole_column: 0 }]);
+        let garbage_rows = |game: &Game| {
+            game.board
+                .iter()
+                .filter(|row| row.contains(&GARBAGE_CELL))
+                .count()
+        };
+
+        clock.advance(Duration::from_millis(39));
+        game.update_garbage_animation();
+        assert_eq!(garbage_rows(&game), 0);
+
+        clock.advance(Duration::from_millis(1));
+        game.update_garbage_animation();
+        assert_eq!(garbage_rows(&game), 1);
+        assert_eq!(game.garbage_anim_remaining(), 2);
+
+        clock.advance(Duration::from_millis(80));
+        game.update_garbage_animation();
+        assert_eq!(garbage_rows(&game), 3);
+    }
+
+
+    // --- Bot tests ---
+
+    #[test]
+    fn bot_takes_open_tetris() {
+        let mut game = make_game();
+        for r in BOARD_HEIGHT - 4..BOARD_HEIGHT {
+            for c in 0..BOARD_WIDTH - 1 {
+                game.board[r][c] = GARBAGE_CELL;
+            }
+        }
+        game.current = Piece::new(0, game.rotation_system);
+        let plan = Bot::new(BotDifficulty::Easy, AttackTable::Guideline).plan(&game);
+        assert_eq!(plan.last(), Some(&GameInput::HardDrop));
+        game.step(&plan, Duration::ZERO);
+        assert_eq!(game.lines, 4);
+    }
+
+    /// How many lines the easy bot clears with an I piece over a one-row
+    /// single, while holding a long back-to-back chain.
+    fn lines_after_single_offer(table: AttackTable) -> u32 {
+        let mut game = make_game();
+        for c in 0..BOARD_WIDTH - 1 {
+            game.board[BOARD_HEIGHT - 1][c] = GARBAGE_CELL;
+        }
+        game.current = Piece::new(0, game.rotation_system);
+        game.back_to_back = true;
+        game.b2b_chain = 10;
+        let plan = Bot::new(BotDifficulty::Easy, table).plan(&game);
+        game.step(&plan, Duration::ZERO);
+        game.lines
+    }
+
+    #[test]
+    fn bot_scores_clears_on_the_match_table() {
+        // A single is only a burn on the guideline table, but breaking a
+        // long chain surges on TETR.IO's.
+        assert_eq!(lines_after_single_offer(AttackTable::Guideline), 0);
+        assert_eq!(lines_after_single_offer(AttackTable::Tetrio), 1);
+    }
+
+    #[test]
+    fn bot_plan_is_deterministic() {
+        let game = Game::new(GameMode::Versus, &test_settings(), 5);
+        let bot = Bot::new(BotDifficulty::Hard, AttackTable::Guideline);
+        assert_eq!(bot.plan(&game), bot.plan(&game));
+    }
+
+    #[test]
+    fn bot_survives_and_clears_lines() {
+        let mut game = Game::new(GameMode::Versus, &test_settings(), 1);
+        let bot = Bot::new(BotDifficulty::Normal, AttackTable::Guideline);
+        for _ in 0..100 {
+            let plan = bot.plan(&game);
+            game.step(&plan, ARE_DELAY + SIM_FRAME);
+        }
+        assert!(!game.game_over);
+        assert!(game.lines >= 30, "only {} lines", game.lines);
+    }
+
+    // --- Set score tests ---
+
+    #[test]
+    fn set_goes_to_first_to_reach_target() {
+        let mut set = SetScore::new(3);
+        for host_won in [true, false, true, false, false] {
+            assert_eq!(set.host_won_set(), None);
+            set.record(host_won);
+        }
+        assert_eq!(set.host_won_set(), Some(false));
+        assert_eq!(set.from_side(true), (2, 3));
+        assert_eq!(set.from_side(false), (3, 2));
+        assert_eq!(set.game_number(), 6);
+    }
+
+    #[test]
+    fn single_game_set() {
+        let mut set = SetScore::new(0);
+        assert!(set.is_single());
+        set.record(true);
+        assert_eq!(set.host_won_set(), Some(true));
+    }
+
+    // --- Battle royale tests ---
+
+    #[test]
+    fn room_never_targets_self_or_knocked_out() {
+        let mut room = Room::new(&[0, 1, 2, 3], 7);
+        room.knock_out(2);
+        for _ in 0..50 {
+            let target = room.route_attack(0, 1).unwrap();
+            assert!(target == 1 || target == 3);
+        }
+        assert_eq!(room.route_attack(2, 1), None);
+    }
+
+    #[test]
+    fn room_attackers_strategy_hits_back() {
+        let mut room = Room::new(&[0, 1, 2, 3], 7);
+        room.set_strategy(0, TargetStrategy::Attackers);
+        room.set_strategy(3, TargetStrategy::Kos);
+        room.update_stack(0, 15);
+        assert_eq!(room.route_attack(3, 2), Some(0));
+        for _ in 0..10 {
+            assert_eq!(room.route_attack(0, 1), Some(3));
+        }
+    }
+
+    #[test]
+    fn room_kos_strategy_picks_highest_stack() {
+        let mut room = Room::new(&[0, 1, 2], 7);
+        room.set_strategy(0, TargetStrategy::Kos);
+        room.update_stack(1, 4);
+        room.update_stack(2, 12);
+        assert_eq!(room.route_attack(0, 1), Some(2));
+    }
+
+    #[test]
+    fn room_even_strategy_spreads_garbage() {
+        let mut room = Room::new(&[0, 1, 2, 3], 7);
+        room.set_strategy(0, TargetStrategy::Even);
+        let mut hit: Vec<u8> = (0..3).map(|_| room.route_attack(0, 1).unwrap()).collect();
+        hit.sort();
+        assert_eq!(hit, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn room_placements_follow_knock_outs() {
+        let mut room = Room::new(&[0, 1, 2, 3], 7);
+        room.set_strategy(1, TargetStrategy::Kos);
+        room.update_stack(2, 18);
+        assert_eq!(room.route_attack(1, 4), Some(2));
+
+        assert_eq!(room.knock_out(2), Some(Ko { player: 2, place: 4, by: Some(1) }));
+        assert_eq!(room.knock_out(2), None);
+        assert_eq!(room.kos(1), 1);
+        assert_eq!(room.knock_out(0), Some(Ko { player: 0, place: 3, by: None }));
+        assert!(!room.is_over());
+        room.knock_out(3);
+        assert!(room.is_over());
+        assert_eq!(room.placements(), vec![1, 3, 0, 2]);
+    }
 }
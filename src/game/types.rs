@@ -1,6 +1,7 @@
+use serde::{Deserialize, Serialize};
 use std::time::{Duration, Instant};
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum GameMode {
     Marathon,
     Sprint,
@@ -49,10 +50,10 @@ pub struct LineClearAnimation {
 }
 
 impl LineClearAnimation {
-    pub fn new(rows: Vec<usize>) -> Self {
+    pub fn new(rows: Vec<usize>, started_at: Instant) -> Self {
         Self {
             rows,
-            started_at: Instant::now(),
+            started_at,
             phase: 0,
         }
     }
@@ -65,6 +66,27 @@ pub fn format_option_or_inf(val: Option<u32>) -> String {
     }
 }
 
+/// A single mutation of the engine, either from a key press or from a timer.
+///
+/// Feeding the same sequence of inputs to a `Game` built from the same seed
+/// and settings always reproduces the same run.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GameInput {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateCw,
+    RotateCcw,
+    Rotate180,
+    Hold,
+    Gravity,
+    Lock,
+    AreEnd,
+    ClearDone,
+    TimeUp,
+}
+
 #[derive(Clone, Copy, PartialEq)]
 pub enum LastMove {
     None,
@@ -88,4 +110,10 @@ pub struct ClearAction {
     pub is_back_to_back: bool,
     pub combo: i32,
     pub is_all_clear: bool,
+    /// How many of the cleared lines were garbage.
+    pub garbage_lines: u32,
+    /// Back-to-back bonuses in a row counting this clear; 0 when it had none.
+    pub b2b_chain: u32,
+    /// The back-to-back chain this clear broke, if it broke one.
+    pub b2b_broken: u32,
 }
@@ -0,0 +1,6 @@
+//! The Rustris game engine and its LAN protocol. Neither touches the
+//! terminal or audio, so the game binary, `rustris-sim` and tests can all
+//! drive them.
+
+pub mod game;
+pub mod net;
@@ -1,10 +1,9 @@
 mod audio;
-mod game;
-mod net;
 mod render;
 mod ui;
 
 use crossterm::{cursor, execute, terminal};
+use rustris::{game, net};
 use std::io;
 
 fn main() -> io::Result<()> {
@@ -5,10 +5,15 @@ use std::time::Duration;
 use super::transport::Connection;
 
 pub fn connect(addr: &str) -> io::Result<Connection> {
+    connect_within(addr, Duration::from_secs(5))
+}
+
+/// Like `connect`, giving up after `timeout`.
+pub fn connect_within(addr: &str, timeout: Duration) -> io::Result<Connection> {
     let sock_addr = addr
         .to_socket_addrs()?
         .next()
         .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "could not resolve address"))?;
-    let stream = TcpStream::connect_timeout(&sock_addr, Duration::from_secs(5))?;
+    let stream = TcpStream::connect_timeout(&sock_addr, timeout)?;
     Connection::new(stream)
 }
@@ -0,0 +1,29 @@
+use std::io;
+
+use super::protocol::NetMessage;
+
+/// How a message is turned into the bytes of one frame. Every connection
+/// starts out on JSON; both sides move to binary once their `Hello`s show
+/// they can.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Codec {
+    #[default]
+    Json,
+    Binary,
+}
+
+impl Codec {
+    pub fn encode(self, msg: &NetMessage) -> io::Result<Vec<u8>> {
+        match self {
+            Codec::Json => serde_json::to_vec(msg).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Codec::Binary => bincode::serialize(msg).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        }
+    }
+
+    pub fn decode(self, bytes: &[u8]) -> io::Result<NetMessage> {
+        match self {
+            Codec::Json => serde_json::from_slice(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Codec::Binary => bincode::deserialize(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::VecDeque;
+
+use crate::game::rng::random_seed;
+
+use super::protocol::{BoardCells, BoardSnapshot, BoardUpdate};
+
+/// Boards each side keeps for deltas to refer back to, about two seconds'
+/// worth at the board sync rate.
+const HISTORY: usize = 32;
+/// Past this many unacknowledged updates the sender stops building on its
+/// acknowledged board, which the receiver may soon forget.
+const MAX_UNACKED: usize = HISTORY / 2;
+
+/// `board` as (cell, run length) runs.
+pub fn runs(board: &[u8]) -> Vec<(u8, u8)> {
+    let mut runs: Vec<(u8, u8)> = Vec::new();
+    for &cell in board {
+        match runs.last_mut() {
+            Some((c, n)) if *c == cell && *n < u8::MAX => *n += 1,
+            _ => runs.push((cell, 1)),
+        }
+    }
+    runs
+}
+
+pub fn expand(runs: &[(u8, u8)]) -> Vec<u8> {
+    runs.iter()
+        .flat_map(|&(cell, n)| std::iter::repeat_n(cell, n as usize))
+        .collect()
+}
+
+/// (index, cell) for every cell of `new` that differs from `old`.
+fn changes(old: &[u8], new: &[u8]) -> Vec<(u8, u8)> {
+    new.iter()
+        .enumerate()
+        .filter(|&(i, &cell)| old.get(i) != Some(&cell))
+        .map(|(i, &cell)| (i as u8, cell))
+        .collect()
+}
+
+/// Turns outgoing snapshots into updates, as deltas against the newest board
+/// the peer has acknowledged when that is smaller than the run encoding.
+pub struct BoardEncoder {
+    next_seq: u32,
+    unacked: VecDeque<(u32, Vec<u8>)>,
+    acked: Option<(u32, Vec<u8>)>,
+}
+
+impl Default for BoardEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BoardEncoder {
+    /// Sequence numbers start somewhere random so a stray ack from an
+    /// earlier game cannot match.
+    pub fn new() -> Self {
+        Self {
+            next_seq: random_seed() as u32,
+            unacked: VecDeque::new(),
+            acked: None,
+        }
+    }
+
+    pub fn encode(&mut self, snap: &BoardSnapshot) -> BoardUpdate {
+        let seq = self.next_seq;
+        self.next_seq = self.next_seq.wrapping_add(1);
+
+        let full = runs(&snap.board);
+        let cells = match &self.acked {
+            Some((base, board)) if self.unacked.len() < MAX_UNACKED => {
+                let changed = changes(board, &snap.board);
+                if changed.len() < full.len() {
+                    BoardCells::Changed { base: *base, cells: changed }
+                } else {
+                    BoardCells::Runs(full)
+                }
+            }
+            _ => BoardCells::Runs(full),
+        };
+
+        self.unacked.push_back((seq, snap.board.clone()));
+        if self.unacked.len() > HISTORY {
+            self.unacked.pop_front();
+        }
+
+        BoardUpdate {
+            seq,
+            cells,
+            current_cells: snap.current_cells.clone(),
+            current_kind: snap.current_kind,
+            score: snap.score,
+            lines: snap.lines,
+            pending_garbage: snap.pending_garbage,
+        }
+    }
+
+    pub fn ack(&mut self, seq: u32) {
+        if let Some(pos) = self.unacked.iter().position(|&(s, _)| s == seq) {
+            self.acked = self.unacked.drain(..=pos).next_back();
+        }
+    }
+}
+
+/// Rebuilds snapshots from incoming updates.
+#[derive(Default)]
+pub struct BoardDecoder {
+    history: VecDeque<(u32, Vec<u8>)>,
+}
+
+impl BoardDecoder {
+    /// `None` if the update builds on a board this side does not have.
+    pub fn decode(&mut self, update: &BoardUpdate) -> Option<BoardSnapshot> {
+        let board = match &update.cells {
+            BoardCells::Runs(runs) => expand(runs),
+            BoardCells::Changed { base, cells } => {
+                let (_, base_board) = self.history.iter().find(|(s, _)| s == base)?;
+                let mut board = base_board.clone();
+                for &(i, cell) in cells {
+                    if let Some(c) = board.get_mut(i as usize) {
+                        *c = cell;
+                    }
+                }
+                board
+            }
+        };
+
+        self.history.push_back((update.seq, board.clone()));
+        if self.history.len() > HISTORY {
+            self.history.pop_front();
+        }
+
+        Some(BoardSnapshot {
+            board,
+            current_cells: update.current_cells.clone(),
+            current_kind: update.current_kind,
+            score: update.score,
+            lines: update.lines,
+            pending_garbage: update.pending_garbage,
+        })
+    }
+}
@@ -0,0 +1,161 @@
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+use crate::game::settings::Settings;
+
+use super::protocol::PROTOCOL_VERSION;
+
+/// UDP port hosts announce themselves on, one below the default game port.
+pub const DISCOVERY_PORT: u16 = 21710;
+
+const BEACON_MAGIC: &[u8; 4] = b"RTRS";
+const BEACON_INTERVAL: Duration = Duration::from_secs(1);
+/// A host not heard from for this long is dropped from the list.
+const HOST_EXPIRY: Duration = Duration::from_secs(3);
+const MAX_BEACON_LEN: usize = 2048;
+const MAX_NAME_LEN: usize = 16;
+
+/// What a hosting lobby announces to the LAN.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Beacon {
+    pub name: String,
+    pub port: u16,
+    pub version: u8,
+    pub settings: Settings,
+    /// A battle royale room rather than a 1v1 game.
+    pub room: bool,
+}
+
+impl Beacon {
+    pub fn new(port: u16, settings: Settings, room: bool) -> Self {
+        Self {
+            name: host_name(),
+            port,
+            version: PROTOCOL_VERSION,
+            settings,
+            room,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = BEACON_MAGIC.to_vec();
+        bytes.extend(serde_json::to_vec(self).unwrap_or_default());
+        bytes
+    }
+
+    /// `None` for anything that is not a beacon.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let json = bytes.strip_prefix(BEACON_MAGIC)?;
+        serde_json::from_slice(json).ok()
+    }
+}
+
+/// This machine's name, for other players to recognise it by.
+pub fn host_name() -> String {
+    let name = std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "rustris".to_string());
+    name.chars().take(MAX_NAME_LEN).collect()
+}
+
+/// Sends a host's beacon to the LAN broadcast address about once a second.
+pub struct BeaconSender {
+    socket: UdpSocket,
+    payload: Vec<u8>,
+    last_sent: Option<Instant>,
+}
+
+impl BeaconSender {
+    pub fn new(beacon: &Beacon) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_broadcast(true)?;
+        Ok(Self {
+            socket,
+            payload: beacon.encode(),
+            last_sent: None,
+        })
+    }
+
+    /// Sends the beacon if it is due. Failures are ignored; the next tick
+    /// tries again.
+    pub fn tick(&mut self) {
+        if self.last_sent.is_some_and(|t| t.elapsed() < BEACON_INTERVAL) {
+            return;
+        }
+        let _ = self.socket.send_to(&self.payload, (Ipv4Addr::BROADCAST, DISCOVERY_PORT));
+        self.last_sent = Some(Instant::now());
+    }
+}
+
+/// A host heard on the LAN.
+#[derive(Clone, Debug)]
+pub struct FoundHost {
+    pub addr: SocketAddr,
+    pub beacon: Beacon,
+    last_seen: Instant,
+}
+
+impl FoundHost {
+    pub fn compatible(&self) -> bool {
+        self.beacon.version == PROTOCOL_VERSION
+    }
+}
+
+/// Listens for beacons and keeps a list of the hosts currently announcing.
+pub struct HostFinder {
+    socket: UdpSocket,
+    hosts: Vec<FoundHost>,
+}
+
+impl HostFinder {
+    /// Fails if another program on this machine already holds the port.
+    pub fn new() -> io::Result<Self> {
+        Self::bind(DISCOVERY_PORT)
+    }
+
+    pub fn bind(port: u16) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port))?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            hosts: Vec::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Reads every beacon that has arrived and forgets hosts that went quiet.
+    pub fn poll(&mut self) {
+        let mut buf = [0u8; MAX_BEACON_LEN];
+        while let Ok((len, from)) = self.socket.recv_from(&mut buf) {
+            if let Some(beacon) = Beacon::decode(&buf[..len]) {
+                self.heard(SocketAddr::new(from.ip(), beacon.port), beacon);
+            }
+        }
+        self.hosts.retain(|h| h.last_seen.elapsed() < HOST_EXPIRY);
+    }
+
+    fn heard(&mut self, addr: SocketAddr, beacon: Beacon) {
+        let last_seen = Instant::now();
+        match self.hosts.iter_mut().find(|h| h.addr == addr) {
+            Some(host) => {
+                host.beacon = beacon;
+                host.last_seen = last_seen;
+            }
+            None => self.hosts.push(FoundHost { addr, beacon, last_seen }),
+        }
+    }
+
+    /// Hosts heard recently, in the order they were first heard.
+    pub fn hosts(&self) -> &[FoundHost] {
+        &self.hosts
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use super::protocol::NetMessage;
+use super::transport::Transport;
+
+pub const PING_INTERVAL: Duration = Duration::from_secs(1);
+/// A peer silent for this long is treated as a dropped link.
+pub const PEER_TIMEOUT: Duration = Duration::from_secs(5);
+/// Weight of the newest sample in the smoothed round-trip time, as in TCP's
+/// SRTT.
+const RTT_GAIN: f64 = 1.0 / 8.0;
+
+/// Pings the peer on an interval, answers its pings and keeps a smoothed
+/// round-trip time. Any message from the peer counts as a sign of life.
+pub struct Heartbeat {
+    next_seq: u32,
+    /// Pings still waiting for their pongs, oldest first, and when each
+    /// went out. A slow pong still times its own ping.
+    in_flight: VecDeque<(u32, Instant)>,
+    last_ping: Option<Instant>,
+    last_heard: Instant,
+    rtt: Option<Duration>,
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Heartbeat {
+    pub fn new() -> Self {
+        Self {
+            next_seq: 0,
+            in_flight: VecDeque::new(),
+            last_ping: None,
+            last_heard: Instant::now(),
+            rtt: None,
+        }
+    }
+
+    /// Sends a ping if one is due.
+    pub fn tick(&mut self, conn: &mut dyn Transport) {
+        if self.last_ping.is_some_and(|t| t.elapsed() < PING_INTERVAL) {
+            return;
+        }
+        let now = Instant::now();
+        // Pings this old are lost; the peer has timed out by now anyway.
+        self.in_flight.retain(|&(_, sent_at)| now - sent_at < PEER_TIMEOUT);
+        if conn.send(&NetMessage::Ping(self.next_seq)).is_ok() {
+            self.in_flight.push_back((self.next_seq, now));
+        }
+        self.next_seq = self.next_seq.wrapping_add(1);
+        self.last_ping = Some(now);
+    }
+
+    /// Notes a message from the peer, answering it if it is a ping. True if
+    /// the message was keepalive traffic and needs no further handling.
+    pub fn heard(&mut self, conn: &mut dyn Transport, msg: &NetMessage) -> bool {
+        self.last_heard = Instant::now();
+        match *msg {
+            NetMessage::Ping(seq) => {
+                let _ = conn.send(&NetMessage::Pong(seq));
+                true
+            }
+            NetMessage::Pong(seq) => {
+                // Pongs come back in order, so pings sent before this one
+                // will not be answered.
+                if let Some(i) = self.in_flight.iter().position(|&(sent_seq, _)| sent_seq == seq) {
+                    let sent_at = self.in_flight[i].1;
+                    self.in_flight.drain(..=i);
+                    self.sample(sent_at.elapsed());
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn sample(&mut self, rtt: Duration) {
+        self.rtt = Some(match self.rtt {
+            Some(smoothed) => smoothed.mul_f64(1.0 - RTT_GAIN) + rtt.mul_f64(RTT_GAIN),
+            None => rtt,
+        });
+    }
+
+    /// The smoothed round-trip time, once a pong has come back.
+    pub fn rtt(&self) -> Option<Duration> {
+        self.rtt
+    }
+
+    pub fn timed_out(&self) -> bool {
+        self.last_heard.elapsed() >= PEER_TIMEOUT
+    }
+
+    /// Starts the silence timer over, e.g. after the link was resumed. The
+    /// round-trip time is kept.
+    pub fn restart(&mut self) {
+        self.in_flight.clear();
+        self.last_ping = None;
+        self.last_heard = Instant::now();
+    }
+}
@@ -1,6 +1,16 @@
 pub mod protocol;
+pub mod codec;
+pub mod delta;
 pub mod transport;
 pub mod host;
 pub mod client;
+pub mod discovery;
+pub mod room;
+pub mod spectate;
+pub mod session;
+pub mod keepalive;
 
 pub use protocol::*;
+
+#[cfg(test)]
+mod tests;
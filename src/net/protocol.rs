@@ -1,10 +1,21 @@
 use serde::{Deserialize, Serialize};
+use std::io;
 
-use crate::game::piece::{BOARD_WIDTH, BUFFER_HEIGHT, VISIBLE_HEIGHT};
+use crate::game::piece::{BOARD_WIDTH, BUFFER_HEIGHT, EMPTY, VISIBLE_HEIGHT};
+use crate::game::lockstep::{InputBatch, Mismatch};
+use crate::game::royale::{Ko, PlayerId, TargetStrategy};
+use crate::game::set::SetScore;
 use crate::game::settings::Settings;
+use crate::game::stats::MatchStats;
 use crate::game::Game;
 
-pub const PROTOCOL_VERSION: u8 = 1;
+use super::codec::Codec;
+
+pub const PROTOCOL_VERSION: u8 = 13;
+
+/// Which player a spectator message is about.
+pub const HOST_SLOT: u8 = 0;
+pub const GUEST_SLOT: u8 = 1;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct GarbageAttack {
@@ -52,6 +63,37 @@ impl BoardSnapshot {
             pending_garbage,
         }
     }
+
+    /// Rows from the highest locked block down to the floor.
+    pub fn stack_height(&self) -> usize {
+        self.board
+            .chunks(BOARD_WIDTH)
+            .position(|row| row.iter().any(|&c| c != EMPTY))
+            .map_or(0, |top| VISIBLE_HEIGHT - top)
+    }
+}
+
+/// The locked cells of a `BoardUpdate`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum BoardCells {
+    /// The whole board as (cell, run length) runs, row by row.
+    Runs(Vec<(u8, u8)>),
+    /// (index, cell) for every cell that differs from board `base`, an
+    /// earlier update the receiver has acknowledged.
+    Changed { base: u32, cells: Vec<(u8, u8)> },
+}
+
+/// A `BoardSnapshot` as sent during a 1v1 match: the same fields, with the
+/// board compressed against what the receiver already has.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BoardUpdate {
+    pub seq: u32,
+    pub cells: BoardCells,
+    pub current_cells: Vec<(i32, i32)>,
+    pub current_kind: usize,
+    pub score: u32,
+    pub lines: u32,
+    pub pending_garbage: u32,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -62,8 +104,15 @@ pub enum MatchOutcome {
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum NetMessage {
-    Hello { version: u8 },
-    LobbySettings(Settings),
+    Hello {
+        version: u8,
+        /// The sender can switch to `Codec::Binary` after the handshake.
+        #[serde(default)]
+        binary: bool,
+    },
+    /// The match settings and the match setup screen's revision of them;
+    /// 0 when sent outside that screen.
+    LobbySettings(u32, Settings),
     Ready,
     Countdown(u8),
     GameStart,
@@ -74,4 +123,80 @@ pub enum NetMessage {
     RematchRequest,
     RematchAccept,
     Disconnect,
+    /// Sent instead of `Ready` by a connection that only wants to watch.
+    Spectate,
+    SpectatorBoard(u8, BoardSnapshot),
+    SpectatorGarbage(u8, GarbageAttack),
+    /// The slot of the player who won.
+    SpectatorResult(u8),
+    /// Sent instead of `Ready` to take a seat in a battle royale room.
+    JoinRoom,
+    /// The id the host gave the new player.
+    RoomJoined(PlayerId),
+    /// Everyone seated in the room, host first.
+    RoomPlayers(Vec<PlayerId>),
+    SetTargeting(TargetStrategy),
+    RoomBoard(PlayerId, BoardSnapshot),
+    /// A lock's garbage, run by run, sent to the room host to be aimed.
+    RoomAttack(Vec<GarbageAttack>),
+    /// Garbage for the receiver, run by run, and who sent it.
+    RoomGarbage(PlayerId, Vec<GarbageAttack>),
+    RoomKo(Ko),
+    /// Final standings, winner first.
+    RoomResult(Vec<PlayerId>),
+    /// A player's Ready toggle on the match setup screen, for the settings
+    /// revision they had seen.
+    ReadyState(bool, u32),
+    /// Sent by the host once both players are ready, with the session
+    /// token a dropped guest reconnects with and the garbage hole seed.
+    StartMatch(u64, u64),
+    /// Sent instead of `Ready` by a guest dialling back in to a match that
+    /// dropped, with the token from `StartMatch`.
+    Resume(u64),
+    /// The host took the guest back; the match carries on.
+    Resumed,
+    /// Sent by both sides after a resume: how many of the other side's
+    /// attacks, deaths and input batches this game have arrived, so the
+    /// rest can be sent again.
+    Resync(u32),
+    /// Keepalive during a match; answered with a `Pong` of the same number.
+    Ping(u32),
+    Pong(u32),
+    BoardUpdate(BoardUpdate),
+    /// The receiver has board `seq` and deltas may build on it.
+    BoardAck(u32),
+    /// The sender's numbers for the game that just ended.
+    MatchStats(MatchStats),
+    /// The host's count of the set after each game; the guest takes it as is.
+    SetScore(SetScore),
+    /// The sender's game events since its last batch, in a verified match.
+    Inputs(InputBatch),
+    /// The sender's check of the receiver's game failed; the game is void.
+    Mismatch(Mismatch),
+}
+
+/// What this build greets a peer with.
+pub fn hello() -> NetMessage {
+    NetMessage::Hello {
+        version: PROTOCOL_VERSION,
+        binary: true,
+    }
+}
+
+/// Checks the peer's `Hello` and picks the codec both sides speak once the
+/// `Hello`s have crossed.
+pub fn negotiate(msg: NetMessage) -> io::Result<Codec> {
+    match msg {
+        NetMessage::Hello { version, binary } if version == PROTOCOL_VERSION => {
+            Ok(if binary { Codec::Binary } else { Codec::Json })
+        }
+        NetMessage::Hello { version, .. } => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("protocol version mismatch: local={}, remote={}", PROTOCOL_VERSION, version),
+        )),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "expected Hello",
+        )),
+    }
 }
@@ -0,0 +1,318 @@
+use std::io;
+use std::mem;
+
+use crate::game::royale::{Ko, PlayerId, Room, TargetStrategy, HOST_ID, MAX_PLAYERS};
+
+use super::protocol::{BoardSnapshot, GarbageAttack, NetMessage};
+use super::spectate::{Arrival, SpectatorHub};
+use super::transport::Transport;
+
+/// Something the local player needs to know about during a room match.
+#[derive(Debug)]
+pub enum RoomEvent {
+    Board(PlayerId, BoardSnapshot),
+    /// Garbage aimed at the local player, run by run, and who sent it.
+    Garbage(PlayerId, Vec<GarbageAttack>),
+    Ko(Ko),
+    /// Final standings, winner first.
+    Finished(Vec<PlayerId>),
+    HostLeft,
+}
+
+/// The local player's side of a room match, whether hosting it or not.
+pub trait RoomLink {
+    fn me(&self) -> PlayerId;
+    fn send_board(&mut self, snap: BoardSnapshot);
+    /// Sends the runs of one lock's garbage, which all hit the same target.
+    fn send_attack(&mut self, runs: Vec<GarbageAttack>);
+    fn report_death(&mut self);
+    fn poll(&mut self) -> Vec<RoomEvent>;
+}
+
+struct Guest {
+    id: PlayerId,
+    conn: Box<dyn Transport>,
+    strategy: TargetStrategy,
+}
+
+/// Seats players as they join the host's port. Everyone seated is told the
+/// room's roster whenever it changes.
+pub struct RoomLobby {
+    hub: SpectatorHub,
+    guests: Vec<Guest>,
+    next_id: PlayerId,
+}
+
+impl RoomLobby {
+    pub fn new(hub: SpectatorHub) -> Self {
+        Self {
+            hub,
+            guests: Vec::new(),
+            next_id: HOST_ID + 1,
+        }
+    }
+
+    pub fn player_count(&self) -> usize {
+        self.guests.len() + 1
+    }
+
+    pub fn roster(&self) -> Vec<PlayerId> {
+        std::iter::once(HOST_ID).chain(self.guests.iter().map(|g| g.id)).collect()
+    }
+
+    /// Admits a connection directly, skipping the listener.
+    pub fn admit(&mut self, conn: Box<dyn Transport>) {
+        self.hub.admit(conn);
+    }
+
+    /// Seats new players, drops the ones that left and picks up targeting
+    /// changes. Returns the error of a peer that failed the version check.
+    pub fn poll(&mut self) -> Option<io::Error> {
+        let mut changed = false;
+        let rejected = match self.hub.poll() {
+            Some(Arrival::RoomPlayer(mut conn)) => {
+                if self.player_count() < MAX_PLAYERS && conn.send(&NetMessage::RoomJoined(self.next_id)).is_ok() {
+                    self.guests.push(Guest {
+                        id: self.next_id,
+                        conn,
+                        strategy: TargetStrategy::default(),
+                    });
+                    self.next_id += 1;
+                    changed = true;
+                } else {
+                    let _ = conn.send(&NetMessage::Disconnect);
+                }
+                None
+            }
+            Some(Arrival::Player(mut conn) | Arrival::Resume(_, mut conn)) => {
+                let _ = conn.send(&NetMessage::Disconnect);
+                None
+            }
+            Some(Arrival::Rejected(e)) => Some(e),
+            None => None,
+        };
+
+        let before = self.guests.len();
+        self.guests.retain_mut(|guest| loop {
+            match guest.conn.try_recv() {
+                Ok(Some(NetMessage::SetTargeting(strategy))) => guest.strategy = strategy,
+                Ok(Some(NetMessage::Disconnect)) => return false,
+                Ok(Some(_)) => {}
+                Ok(None) => return true,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return true,
+                Err(_) => return false,
+            }
+        });
+        if changed || self.guests.len() != before {
+            self.broadcast(&NetMessage::RoomPlayers(self.roster()));
+        }
+        rejected
+    }
+
+    pub fn broadcast(&mut self, msg: &NetMessage) {
+        for guest in &mut self.guests {
+            let _ = guest.conn.send(msg);
+        }
+    }
+
+    /// Closes the doors and seats everyone in a new match.
+    pub fn start(self, host_strategy: TargetStrategy, seed: u64) -> RoomHost {
+        let mut room = Room::new(&self.roster(), seed);
+        room.set_strategy(HOST_ID, host_strategy);
+        for guest in &self.guests {
+            room.set_strategy(guest.id, guest.strategy);
+        }
+        let RoomLobby { guests, .. } = self;
+        RoomHost {
+            room,
+            guests,
+            events: Vec::new(),
+        }
+    }
+}
+
+/// The host's end of a room match. Guests report to it; it decides who each
+/// attack hits and who placed where, and relays boards to everyone.
+pub struct RoomHost {
+    room: Room,
+    guests: Vec<Guest>,
+    events: Vec<RoomEvent>,
+}
+
+impl RoomHost {
+    pub fn broadcast(&mut self, msg: &NetMessage) {
+        for guest in &mut self.guests {
+            let _ = guest.conn.send(msg);
+        }
+    }
+
+    fn send_to(&mut self, id: PlayerId, msg: &NetMessage) {
+        if let Some(guest) = self.guests.iter_mut().find(|g| g.id == id) {
+            let _ = guest.conn.send(msg);
+        }
+    }
+
+    fn deliver(&mut self, from: PlayerId, runs: Vec<GarbageAttack>) {
+        let lines = runs.iter().map(|run| run.lines).sum();
+        match self.room.route_attack(from, lines) {
+            Some(HOST_ID) => self.events.push(RoomEvent::Garbage(from, runs)),
+            Some(target) => self.send_to(target, &NetMessage::RoomGarbage(from, runs)),
+            None => {}
+        }
+    }
+
+    fn relay_board(&mut self, from: PlayerId, snap: BoardSnapshot) {
+        self.room.update_stack(from, snap.stack_height());
+        let msg = NetMessage::RoomBoard(from, snap);
+        for guest in self.guests.iter_mut().filter(|g| g.id != from) {
+            let _ = guest.conn.send(&msg);
+        }
+    }
+
+    fn knock_out(&mut self, id: PlayerId) {
+        let Some(ko) = self.room.knock_out(id) else {
+            return;
+        };
+        self.broadcast(&NetMessage::RoomKo(ko));
+        self.events.push(RoomEvent::Ko(ko));
+        if self.room.is_over() {
+            let placements = self.room.placements();
+            self.broadcast(&NetMessage::RoomResult(placements.clone()));
+            self.events.push(RoomEvent::Finished(placements));
+        }
+    }
+
+    fn handle(&mut self, from: PlayerId, msg: NetMessage) {
+        match msg {
+            NetMessage::BoardState(snap) => {
+                self.relay_board(from, snap.clone());
+                self.events.push(RoomEvent::Board(from, snap));
+            }
+            NetMessage::RoomAttack(runs) => self.deliver(from, runs),
+            NetMessage::PlayerDead => self.knock_out(from),
+            NetMessage::SetTargeting(strategy) => self.room.set_strategy(from, strategy),
+            _ => {}
+        }
+    }
+}
+
+impl RoomLink for RoomHost {
+    fn me(&self) -> PlayerId {
+        HOST_ID
+    }
+
+    fn send_board(&mut self, snap: BoardSnapshot) {
+        self.relay_board(HOST_ID, snap);
+    }
+
+    fn send_attack(&mut self, runs: Vec<GarbageAttack>) {
+        self.deliver(HOST_ID, runs);
+    }
+
+    fn report_death(&mut self) {
+        self.knock_out(HOST_ID);
+    }
+
+    fn poll(&mut self) -> Vec<RoomEvent> {
+        let mut i = 0;
+        while i < self.guests.len() {
+            let id = self.guests[i].id;
+            let mut left = false;
+            let mut inbox = Vec::new();
+            loop {
+                match self.guests[i].conn.try_recv() {
+                    Ok(Some(NetMessage::Disconnect)) => {
+                        left = true;
+                        break;
+                    }
+                    Ok(Some(msg)) => inbox.push(msg),
+                    Ok(None) => break,
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(_) => {
+                        left = true;
+                        break;
+                    }
+                }
+            }
+            for msg in inbox {
+                self.handle(id, msg);
+            }
+            if left {
+                self.guests.retain(|g| g.id != id);
+                self.knock_out(id);
+            } else {
+                i += 1;
+            }
+        }
+        mem::take(&mut self.events)
+    }
+}
+
+impl Drop for RoomHost {
+    fn drop(&mut self) {
+        self.broadcast(&NetMessage::Disconnect);
+    }
+}
+
+/// A seated player's end of a room match: reports to the host and takes its
+/// word on everything else.
+pub struct RoomGuest {
+    me: PlayerId,
+    conn: Box<dyn Transport>,
+}
+
+impl RoomGuest {
+    pub fn new(me: PlayerId, conn: Box<dyn Transport>) -> Self {
+        Self { me, conn }
+    }
+}
+
+impl RoomLink for RoomGuest {
+    fn me(&self) -> PlayerId {
+        self.me
+    }
+
+    fn send_board(&mut self, snap: BoardSnapshot) {
+        let _ = self.conn.send(&NetMessage::BoardState(snap));
+    }
+
+    fn send_attack(&mut self, runs: Vec<GarbageAttack>) {
+        let _ = self.conn.send(&NetMessage::RoomAttack(runs));
+    }
+
+    fn report_death(&mut self) {
+        let _ = self.conn.send(&NetMessage::PlayerDead);
+    }
+
+    fn poll(&mut self) -> Vec<RoomEvent> {
+        let mut events = Vec::new();
+        loop {
+            match self.conn.try_recv() {
+                Ok(Some(msg)) => match msg {
+                    NetMessage::RoomBoard(id, snap) => events.push(RoomEvent::Board(id, snap)),
+                    NetMessage::RoomGarbage(from, runs) => events.push(RoomEvent::Garbage(from, runs)),
+                    NetMessage::RoomKo(ko) => events.push(RoomEvent::Ko(ko)),
+                    NetMessage::RoomResult(placements) => events.push(RoomEvent::Finished(placements)),
+                    NetMessage::Disconnect => {
+                        events.push(RoomEvent::HostLeft);
+                        break;
+                    }
+                    _ => {}
+                },
+                Ok(None) => break,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(_) => {
+                    events.push(RoomEvent::HostLeft);
+                    break;
+                }
+            }
+        }
+        events
+    }
+}
+
+impl Drop for RoomGuest {
+    fn drop(&mut self) {
+        let _ = self.conn.send(&NetMessage::Disconnect);
+    }
+}
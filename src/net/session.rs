@@ -0,0 +1,231 @@
+use std::io;
+use std::time::Duration;
+
+use super::client;
+use super::codec::Codec;
+use super::protocol::{hello, negotiate, NetMessage};
+use super::transport::{Connection, Transport};
+
+/// How long both sides wait for a dropped match to come back before the
+/// opponent is counted out.
+pub const RESUME_GRACE: Duration = Duration::from_secs(15);
+
+/// How long one dial back to the host may hold up the reconnecting screen.
+const REDIAL_TIMEOUT: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Copy)]
+enum Stage {
+    Greeting,
+    Settings,
+    Resuming,
+}
+
+/// A guest dialling back in to a match that dropped. The handshake moves
+/// one message at a time, so the reconnecting screen keeps drawing.
+pub struct ResumeAttempt {
+    conn: Connection,
+    token: u64,
+    stage: Stage,
+}
+
+impl ResumeAttempt {
+    /// Dials the host again to ask for the match with session `token`.
+    pub fn start(addr: &str, token: u64) -> io::Result<Self> {
+        Ok(Self {
+            conn: client::connect_within(addr, REDIAL_TIMEOUT)?,
+            token,
+            stage: Stage::Greeting,
+        })
+    }
+
+    /// Moves the handshake along without waiting. True once the host has
+    /// taken the match back.
+    pub fn poll(&mut self) -> io::Result<bool> {
+        while let Some(msg) = self.conn.try_recv()? {
+            match (self.stage, msg) {
+                (Stage::Greeting, msg) => {
+                    let codec = negotiate(msg)?;
+                    self.conn.send(&hello())?;
+                    self.conn.set_codec(codec);
+                    self.stage = Stage::Settings;
+                }
+                (Stage::Settings, NetMessage::LobbySettings(..)) => {
+                    self.conn.send(&NetMessage::Resume(self.token))?;
+                    self.stage = Stage::Resuming;
+                }
+                (Stage::Settings, _) => {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "expected LobbySettings"));
+                }
+                (Stage::Resuming, NetMessage::Resumed) => return Ok(true),
+                (Stage::Resuming, _) => {
+                    return Err(io::Error::new(io::ErrorKind::ConnectionRefused, "host refused to resume"));
+                }
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// The guest's link to the host. Keeps the host's address and the session
+/// token from `StartMatch` so that a dropped match can be dialled back into.
+pub struct GuestLink {
+    addr: String,
+    conn: Connection,
+    session: Option<u64>,
+    attempt: Option<ResumeAttempt>,
+}
+
+impl GuestLink {
+    pub fn new(addr: &str, conn: Connection) -> Self {
+        Self {
+            addr: addr.to_string(),
+            conn,
+            session: None,
+            attempt: None,
+        }
+    }
+
+    fn note(&mut self, msg: &NetMessage) {
+        if let NetMessage::StartMatch(token, _) = msg {
+            self.session = Some(*token);
+        }
+    }
+}
+
+impl Transport for GuestLink {
+    fn send(&mut self, msg: &NetMessage) -> io::Result<()> {
+        self.conn.send(msg)
+    }
+
+    fn try_recv(&mut self) -> io::Result<Option<NetMessage>> {
+        let msg = self.conn.try_recv()?;
+        if let Some(m) = &msg {
+            self.note(m);
+        }
+        Ok(msg)
+    }
+
+    fn recv_blocking(&mut self) -> io::Result<NetMessage> {
+        let msg = self.conn.recv_blocking()?;
+        self.note(&msg);
+        Ok(msg)
+    }
+
+    fn set_codec(&mut self, codec: Codec) {
+        self.conn.set_codec(codec);
+    }
+
+    /// Dials the host if no attempt is under way, otherwise moves the one
+    /// in progress along. A failed attempt is dropped and the next call
+    /// dials again.
+    fn reconnect(&mut self) -> bool {
+        let Some(token) = self.session else {
+            return false;
+        };
+        if self.attempt.is_none() {
+            self.attempt = ResumeAttempt::start(&self.addr, token).ok();
+        }
+        match self.attempt.as_mut().map(ResumeAttempt::poll) {
+            Some(Ok(true)) => {
+                if let Some(attempt) = self.attempt.take() {
+                    self.conn = attempt.conn;
+                }
+                true
+            }
+            Some(Ok(false)) => false,
+            _ => {
+                self.attempt = None;
+                false
+            }
+        }
+    }
+}
+
+/// Wraps the link for one game so that the events deciding it (attacks,
+/// deaths and verified input batches) survive a resume. Each side counts
+/// the events it has taken in and, once the link is back, tells the other
+/// side that count with `Resync`; the other side then sends the rest again.
+/// Events sent before that `Resync` arrives are held so they keep their
+/// order. A link that resumed without being asked reports `Resumed`, which
+/// starts the same exchange.
+pub struct ResumableLink<'a> {
+    conn: &'a mut dyn Transport,
+    sent: Vec<NetMessage>,
+    heard: u32,
+    awaiting_resync: bool,
+}
+
+impl<'a> ResumableLink<'a> {
+    pub fn new(conn: &'a mut dyn Transport) -> Self {
+        Self {
+            conn,
+            sent: Vec::new(),
+            heard: 0,
+            awaiting_resync: false,
+        }
+    }
+
+    /// Holds new events and tells the other side what has arrived so far.
+    fn resync(&mut self) {
+        self.awaiting_resync = true;
+        let _ = self.conn.send(&NetMessage::Resync(self.heard));
+    }
+}
+
+fn is_match_event(msg: &NetMessage) -> bool {
+    matches!(msg, NetMessage::GarbageAttack(_) | NetMessage::PlayerDead | NetMessage::Inputs(_))
+}
+
+impl Transport for ResumableLink<'_> {
+    fn send(&mut self, msg: &NetMessage) -> io::Result<()> {
+        if !is_match_event(msg) {
+            return self.conn.send(msg);
+        }
+        self.sent.push(msg.clone());
+        if self.awaiting_resync {
+            return Ok(());
+        }
+        self.conn.send(msg)
+    }
+
+    fn try_recv(&mut self) -> io::Result<Option<NetMessage>> {
+        loop {
+            match self.conn.try_recv()? {
+                Some(NetMessage::Resumed) => self.resync(),
+                Some(NetMessage::Resync(heard)) => {
+                    self.awaiting_resync = false;
+                    for msg in self.sent.iter().skip(heard as usize) {
+                        self.conn.send(msg)?;
+                    }
+                }
+                Some(msg) => {
+                    if is_match_event(&msg) {
+                        self.heard += 1;
+                    }
+                    return Ok(Some(msg));
+                }
+                None => return Ok(None),
+            }
+        }
+    }
+
+    fn recv_blocking(&mut self) -> io::Result<NetMessage> {
+        let msg = self.conn.recv_blocking()?;
+        if is_match_event(&msg) {
+            self.heard += 1;
+        }
+        Ok(msg)
+    }
+
+    fn set_codec(&mut self, codec: Codec) {
+        self.conn.set_codec(codec);
+    }
+
+    fn reconnect(&mut self) -> bool {
+        if !self.conn.reconnect() {
+            return false;
+        }
+        self.resync();
+        true
+    }
+}
@@ -0,0 +1,286 @@
+use std::io;
+use std::mem;
+use std::net::TcpListener;
+
+use crate::game::settings::Settings;
+
+use super::codec::Codec;
+use super::delta::BoardDecoder;
+use super::protocol::{hello, negotiate, MatchOutcome, NetMessage, GUEST_SLOT, HOST_SLOT};
+use super::transport::Transport;
+
+/// A connection the hub has greeted but that has not yet said whether it
+/// wants to play or watch.
+struct Pending {
+    conn: Box<dyn Transport>,
+    greeted: bool,
+}
+
+enum Step {
+    Waiting,
+    Gone,
+    Player,
+    RoomPlayer,
+    Spectator,
+    Resume(u64),
+    Rejected(io::Error),
+}
+
+/// What `SpectatorHub::poll` turned up.
+pub enum Arrival {
+    /// A peer finished the handshake and sent `Ready`.
+    Player(Box<dyn Transport>),
+    /// A peer finished the handshake and sent `JoinRoom`.
+    RoomPlayer(Box<dyn Transport>),
+    /// A dropped guest dialled back in with a session token.
+    Resume(u64, Box<dyn Transport>),
+    /// A peer failed the version check and was dropped.
+    Rejected(io::Error),
+}
+
+/// Accepts every connection on the host's port and runs the lobby handshake
+/// with each. Peers that answer `Spectate` are kept as read-only watchers:
+/// anything they send is read and thrown away.
+pub struct SpectatorHub {
+    listener: Option<TcpListener>,
+    settings: Settings,
+    pending: Vec<Pending>,
+    spectators: Vec<Box<dyn Transport>>,
+}
+
+impl SpectatorHub {
+    /// Without a listener, connections only arrive through `admit`.
+    pub fn new(listener: Option<TcpListener>, settings: Settings) -> Self {
+        Self {
+            listener,
+            settings,
+            pending: Vec::new(),
+            spectators: Vec::new(),
+        }
+    }
+
+    /// Starts the handshake with a new connection.
+    pub fn admit(&mut self, mut conn: Box<dyn Transport>) {
+        if conn.send(&hello()).is_ok() {
+            self.pending.push(Pending { conn, greeted: false });
+        }
+    }
+
+    pub fn spectator_count(&self) -> usize {
+        self.spectators.len()
+    }
+
+    /// Accepts new connections, moves handshakes along and drops watchers
+    /// that have left. Never blocks.
+    pub fn poll(&mut self) -> Option<Arrival> {
+        if let Some(listener) = &self.listener {
+            let mut accepted = Vec::new();
+            while let Ok(Some(conn)) = super::host::try_accept(listener) {
+                accepted.push(conn);
+            }
+            for conn in accepted {
+                self.admit(Box::new(conn));
+            }
+        }
+
+        self.spectators.retain_mut(|s| still_watching(s.as_mut()));
+
+        let mut i = 0;
+        while i < self.pending.len() {
+            match self.advance(i) {
+                Step::Waiting => i += 1,
+                Step::Gone => {
+                    self.pending.swap_remove(i);
+                }
+                Step::Spectator => {
+                    let pending = self.pending.swap_remove(i);
+                    self.spectators.push(pending.conn);
+                }
+                Step::Player => {
+                    return Some(Arrival::Player(self.pending.swap_remove(i).conn));
+                }
+                Step::RoomPlayer => {
+                    return Some(Arrival::RoomPlayer(self.pending.swap_remove(i).conn));
+                }
+                Step::Resume(token) => {
+                    return Some(Arrival::Resume(token, self.pending.swap_remove(i).conn));
+                }
+                Step::Rejected(e) => {
+                    self.pending.swap_remove(i);
+                    return Some(Arrival::Rejected(e));
+                }
+            }
+        }
+        None
+    }
+
+    fn advance(&mut self, i: usize) -> Step {
+        let pending = &mut self.pending[i];
+        loop {
+            let msg = match pending.conn.try_recv() {
+                Ok(Some(msg)) => msg,
+                Ok(None) => return Step::Waiting,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Step::Waiting,
+                Err(_) => return Step::Gone,
+            };
+            if !pending.greeted {
+                match negotiate(msg) {
+                    Ok(codec) => pending.conn.set_codec(codec),
+                    Err(e) => return Step::Rejected(e),
+                }
+                if pending.conn.send(&NetMessage::LobbySettings(0, self.settings)).is_err() {
+                    return Step::Gone;
+                }
+                pending.greeted = true;
+                continue;
+            }
+            match msg {
+                NetMessage::Ready => return Step::Player,
+                NetMessage::JoinRoom => return Step::RoomPlayer,
+                NetMessage::Spectate => return Step::Spectator,
+                NetMessage::Resume(token) => return Step::Resume(token),
+                NetMessage::Disconnect => return Step::Gone,
+                _ => {}
+            }
+        }
+    }
+
+    /// Sends to every watcher, dropping any that can no longer be reached.
+    pub fn broadcast(&mut self, msg: &NetMessage) {
+        self.spectators.retain_mut(|s| s.send(msg).is_ok());
+    }
+}
+
+impl Drop for SpectatorHub {
+    fn drop(&mut self) {
+        self.broadcast(&NetMessage::Disconnect);
+    }
+}
+
+/// Reads and discards whatever a watcher sent. False once it has gone.
+fn still_watching(conn: &mut dyn Transport) -> bool {
+    loop {
+        match conn.try_recv() {
+            Ok(Some(NetMessage::Disconnect)) => return false,
+            Ok(Some(_)) => {}
+            Ok(None) => return true,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => return true,
+            Err(_) => return false,
+        }
+    }
+}
+
+/// What watchers are told about `msg`, sent or received by the player in
+/// `slot`.
+fn spectator_view(msg: &NetMessage, slot: u8) -> Option<NetMessage> {
+    let other = if slot == HOST_SLOT { GUEST_SLOT } else { HOST_SLOT };
+    match msg {
+        NetMessage::BoardState(snap) => Some(NetMessage::SpectatorBoard(slot, snap.clone())),
+        NetMessage::GarbageAttack(attack) => Some(NetMessage::SpectatorGarbage(slot, attack.clone())),
+        NetMessage::Countdown(_) | NetMessage::GameStart => Some(msg.clone()),
+        NetMessage::MatchResult(MatchOutcome::Win) => Some(NetMessage::SpectatorResult(slot)),
+        NetMessage::MatchResult(MatchOutcome::Lose) => Some(NetMessage::SpectatorResult(other)),
+        _ => None,
+    }
+}
+
+/// The host's link to its opponent, copying the match to every watcher on
+/// the way through. A second player who turns up mid-match is turned away,
+/// unless it is the opponent coming back with the session token.
+pub struct Broadcast {
+    player: Box<dyn Transport>,
+    hub: SpectatorHub,
+    /// Taken from the `StartMatch` the host sends.
+    session: Option<u64>,
+    /// The opponent came back and has not been reported yet.
+    resumed: bool,
+    /// Each player's board updates, rebuilt into full boards for watchers.
+    boards: [BoardDecoder; 2],
+}
+
+impl Broadcast {
+    pub fn new(player: Box<dyn Transport>, hub: SpectatorHub) -> Self {
+        Self {
+            player,
+            hub,
+            session: None,
+            resumed: false,
+            boards: Default::default(),
+        }
+    }
+
+    /// Hands the watchers back, e.g. when the opponent leaves before the
+    /// match starts.
+    pub fn into_hub(self) -> SpectatorHub {
+        self.hub
+    }
+
+    fn tend_hub(&mut self) {
+        match self.hub.poll() {
+            Some(Arrival::Resume(token, mut conn)) if self.session == Some(token) => {
+                if conn.send(&NetMessage::Resumed).is_ok() {
+                    self.player = conn;
+                    self.resumed = true;
+                }
+            }
+            Some(Arrival::Player(mut late) | Arrival::RoomPlayer(mut late) | Arrival::Resume(_, mut late)) => {
+                let _ = late.send(&NetMessage::Disconnect);
+            }
+            Some(Arrival::Rejected(_)) | None => {}
+        }
+    }
+
+    fn mirror(&mut self, msg: &NetMessage, slot: u8) {
+        let view = match msg {
+            NetMessage::BoardUpdate(update) => self.boards[slot as usize]
+                .decode(update)
+                .map(|snap| NetMessage::SpectatorBoard(slot, snap)),
+            _ => spectator_view(msg, slot),
+        };
+        if let Some(view) = view {
+            self.hub.broadcast(&view);
+        }
+    }
+}
+
+impl Transport for Broadcast {
+    fn send(&mut self, msg: &NetMessage) -> io::Result<()> {
+        if let NetMessage::StartMatch(token, _) = msg {
+            self.session = Some(*token);
+        }
+        self.mirror(msg, HOST_SLOT);
+        self.player.send(msg)
+    }
+
+    /// A guest who dialled back in before the old link was seen to fail is
+    /// taken back here too, and reported as `Resumed`.
+    fn try_recv(&mut self) -> io::Result<Option<NetMessage>> {
+        let msg = self.player.try_recv()?;
+        match &msg {
+            Some(m) => self.mirror(m, GUEST_SLOT),
+            None => {
+                self.tend_hub();
+                if mem::take(&mut self.resumed) {
+                    return Ok(Some(NetMessage::Resumed));
+                }
+            }
+        }
+        Ok(msg)
+    }
+
+    fn recv_blocking(&mut self) -> io::Result<NetMessage> {
+        let msg = self.player.recv_blocking()?;
+        self.mirror(&msg, GUEST_SLOT);
+        Ok(msg)
+    }
+
+    fn set_codec(&mut self, codec: Codec) {
+        self.player.set_codec(codec);
+    }
+
+    /// Takes the opponent back if they have dialled in again. Never blocks.
+    fn reconnect(&mut self) -> bool {
+        self.tend_hub();
+        mem::take(&mut self.resumed)
+    }
+}
@@ -0,0 +1,632 @@
+use std::cell::Cell;
+use std::io;
+use std::net::UdpSocket;
+use std::rc::Rc;
+
+use crate::game::piece::{BOARD_WIDTH, EMPTY, VISIBLE_HEIGHT};
+use crate::game::settings::Settings;
+use crate::game::royale::{Ko, TargetStrategy, HOST_ID};
+use crate::net::codec::Codec;
+use crate::net::delta::{self, BoardDecoder, BoardEncoder};
+use crate::net::discovery::{Beacon, HostFinder};
+use crate::net::keepalive::{Heartbeat, PING_INTERVAL};
+use crate::net::room::{RoomEvent, RoomGuest, RoomHost, RoomLink, RoomLobby};
+use crate::net::session::{self, ResumableLink};
+use crate::net::spectate::{Arrival, Broadcast, SpectatorHub};
+use crate::net::transport::{channel_pair, ChannelTransport, Transport};
+use crate::net::{
+    hello, BoardCells, BoardSnapshot, GarbageAttack, MatchOutcome, NetMessage, GUEST_SLOT, HOST_SLOT, PROTOCOL_VERSION,
+};
+
+#[test]
+fn channel_pair_delivers_in_order() {
+    let (mut a, mut b) = channel_pair();
+    assert!(b.try_recv().unwrap().is_none());
+    a.send(&NetMessage::Ready).unwrap();
+    a.send(&NetMessage::Countdown(3)).unwrap();
+    assert!(matches!(b.try_recv().unwrap(), Some(NetMessage::Ready)));
+    assert!(matches!(b.recv_blocking().unwrap(), NetMessage::Countdown(3)));
+    b.send(&NetMessage::GameStart).unwrap();
+    assert!(matches!(a.try_recv().unwrap(), Some(NetMessage::GameStart)));
+}
+
+#[test]
+fn channel_pair_reports_dropped_peer() {
+    let (mut a, b) = channel_pair();
+    drop(b);
+    assert_eq!(a.try_recv().unwrap_err().kind(), io::ErrorKind::ConnectionAborted);
+    assert_eq!(a.send(&NetMessage::Ready).unwrap_err().kind(), io::ErrorKind::ConnectionAborted);
+}
+
+// --- Spectator tests ---
+
+/// Runs the client half of the lobby handshake against `hub`, answering the
+/// settings with `reply`. Returns the client end and what the hub made of it.
+fn join(hub: &mut SpectatorHub, reply: NetMessage) -> (ChannelTransport, Option<Arrival>) {
+    let (host_end, mut client) = channel_pair();
+    hub.admit(Box::new(host_end));
+    assert!(matches!(client.try_recv().unwrap(), Some(NetMessage::Hello { .. })));
+    client.send(&hello()).unwrap();
+    client.set_codec(Codec::Binary);
+    assert!(hub.poll().is_none());
+    assert!(matches!(client.try_recv().unwrap(), Some(NetMessage::LobbySettings(..))));
+    client.send(&reply).unwrap();
+    let arrival = hub.poll();
+    (client, arrival)
+}
+
+fn empty_snapshot(score: u32) -> BoardSnapshot {
+    BoardSnapshot {
+        board: Vec::new(),
+        current_cells: Vec::new(),
+        current_kind: 0,
+        score,
+        lines: 0,
+        pending_garbage: 0,
+    }
+}
+
+fn drain(conn: &mut dyn Transport) -> Vec<NetMessage> {
+    let mut msgs = Vec::new();
+    while let Ok(Some(msg)) = conn.try_recv() {
+        msgs.push(msg);
+    }
+    msgs
+}
+
+#[test]
+fn hub_sorts_players_from_spectators() {
+    let mut hub = SpectatorHub::new(None, Settings::default());
+    let (_watcher, arrival) = join(&mut hub, NetMessage::Spectate);
+    assert!(arrival.is_none());
+    assert_eq!(hub.spectator_count(), 1);
+    let (_player, arrival) = join(&mut hub, NetMessage::Ready);
+    assert!(matches!(arrival, Some(Arrival::Player(_))));
+    assert_eq!(hub.spectator_count(), 1);
+}
+
+#[test]
+fn hub_rejects_version_mismatch() {
+    let mut hub = SpectatorHub::new(None, Settings::default());
+    let (host_end, mut client) = channel_pair();
+    hub.admit(Box::new(host_end));
+    client.send(&NetMessage::Hello { version: PROTOCOL_VERSION + 1, binary: true }).unwrap();
+    assert!(matches!(hub.poll(), Some(Arrival::Rejected(_))));
+}
+
+#[test]
+fn spectator_sees_both_players() {
+    let mut hub = SpectatorHub::new(None, Settings::default());
+    let (mut watcher, _) = join(&mut hub, NetMessage::Spectate);
+    let (mut guest, arrival) = join(&mut hub, NetMessage::Ready);
+    let Some(Arrival::Player(player)) = arrival else {
+        panic!("expected a player");
+    };
+    let mut host = Broadcast::new(player, hub);
+
+    host.send(&NetMessage::Countdown(3)).unwrap();
+    host.send(&NetMessage::BoardState(empty_snapshot(100))).unwrap();
+    guest.send(&NetMessage::BoardState(empty_snapshot(200))).unwrap();
+    guest.send(&NetMessage::GarbageAttack(GarbageAttack { lines: 4, hole_column: 2 })).unwrap();
+    while host.try_recv().unwrap().is_some() {}
+    host.send(&NetMessage::MatchResult(MatchOutcome::Lose)).unwrap();
+
+    let seen = drain(&mut watcher);
+    assert!(matches!(seen[0], NetMessage::Countdown(3)));
+    assert!(matches!(&seen[1], NetMessage::SpectatorBoard(HOST_SLOT, s) if s.score == 100));
+    assert!(matches!(&seen[2], NetMessage::SpectatorBoard(GUEST_SLOT, s) if s.score == 200));
+    assert!(matches!(&seen[3], NetMessage::SpectatorGarbage(GUEST_SLOT, a) if a.lines == 4));
+    assert!(matches!(seen[4], NetMessage::SpectatorResult(GUEST_SLOT)));
+    assert_eq!(seen.len(), 5);
+
+    drop(host);
+    assert!(matches!(watcher.try_recv().unwrap(), Some(NetMessage::Disconnect)));
+}
+
+#[test]
+fn spectator_cannot_reach_player() {
+    let mut hub = SpectatorHub::new(None, Settings::default());
+    let (mut watcher, _) = join(&mut hub, NetMessage::Spectate);
+    let (mut guest, arrival) = join(&mut hub, NetMessage::Ready);
+    let Some(Arrival::Player(player)) = arrival else {
+        panic!("expected a player");
+    };
+    let mut host = Broadcast::new(player, hub);
+
+    watcher.send(&NetMessage::GarbageAttack(GarbageAttack { lines: 10, hole_column: 0 })).unwrap();
+    watcher.send(&NetMessage::PlayerDead).unwrap();
+    assert!(host.try_recv().unwrap().is_none());
+    assert!(guest.try_recv().unwrap().is_none());
+}
+
+#[test]
+fn late_player_is_turned_away() {
+    let mut hub = SpectatorHub::new(None, Settings::default());
+    let (_guest, arrival) = join(&mut hub, NetMessage::Ready);
+    let Some(Arrival::Player(player)) = arrival else {
+        panic!("expected a player");
+    };
+    let (late_end, mut late) = channel_pair();
+    hub.admit(Box::new(late_end));
+    let mut host = Broadcast::new(player, hub);
+
+    assert!(matches!(late.try_recv().unwrap(), Some(NetMessage::Hello { .. })));
+    late.send(&hello()).unwrap();
+    late.set_codec(Codec::Binary);
+    assert!(host.try_recv().unwrap().is_none());
+    assert!(matches!(late.try_recv().unwrap(), Some(NetMessage::LobbySettings(..))));
+    late.send(&NetMessage::Ready).unwrap();
+    assert!(host.try_recv().unwrap().is_none());
+    assert!(matches!(late.try_recv().unwrap(), Some(NetMessage::Disconnect)));
+}
+
+// --- Resume tests ---
+
+/// A host link whose guest has been told session `token`, and a second,
+/// not yet greeted connection waiting in its hub.
+fn started_match(token: u64) -> (Broadcast, ChannelTransport, ChannelTransport) {
+    let mut hub = SpectatorHub::new(None, Settings::default());
+    let (guest, arrival) = join(&mut hub, NetMessage::Ready);
+    let Some(Arrival::Player(player)) = arrival else {
+        panic!("expected a player");
+    };
+    let (returning_end, returning) = channel_pair();
+    hub.admit(Box::new(returning_end));
+    let mut host = Broadcast::new(player, hub);
+    host.send(&NetMessage::StartMatch(token, 0)).unwrap();
+    (host, guest, returning)
+}
+
+/// Runs the returning guest's half of the handshake, asking for `token`.
+fn ask_to_resume(host: &mut Broadcast, returning: &mut ChannelTransport, token: u64) -> bool {
+    assert!(matches!(returning.try_recv().unwrap(), Some(NetMessage::Hello { .. })));
+    returning.send(&hello()).unwrap();
+    returning.set_codec(Codec::Binary);
+    assert!(!host.reconnect());
+    assert!(matches!(returning.try_recv().unwrap(), Some(NetMessage::LobbySettings(..))));
+    returning.send(&NetMessage::Resume(token)).unwrap();
+    host.reconnect()
+}
+
+#[test]
+fn dropped_guest_resumes_with_token() {
+    let (mut host, guest, mut returning) = started_match(42);
+    drop(guest);
+    assert!(host.try_recv().is_err());
+
+    assert!(ask_to_resume(&mut host, &mut returning, 42));
+    assert!(matches!(returning.try_recv().unwrap(), Some(NetMessage::Resumed)));
+    host.send(&NetMessage::Countdown(1)).unwrap();
+    assert!(matches!(returning.try_recv().unwrap(), Some(NetMessage::Countdown(1))));
+    assert!(!host.reconnect());
+}
+
+#[test]
+fn resume_with_wrong_token_is_turned_away() {
+    let (mut host, _guest, mut returning) = started_match(42);
+    assert!(!ask_to_resume(&mut host, &mut returning, 7));
+    assert!(matches!(returning.try_recv().unwrap(), Some(NetMessage::Disconnect)));
+}
+
+#[test]
+fn guest_link_resumes_over_tcp() {
+    let listener = crate::net::host::listen_nonblocking(0).unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let mut hub = SpectatorHub::new(Some(listener), Settings::default());
+    let (_guest, arrival) = join(&mut hub, NetMessage::Ready);
+    let Some(Arrival::Player(player)) = arrival else {
+        panic!("expected a player");
+    };
+    let mut host = Broadcast::new(player, hub);
+    host.send(&NetMessage::StartMatch(42, 0)).unwrap();
+
+    let mut attempt = session::ResumeAttempt::start(&format!("127.0.0.1:{}", port), 42).unwrap();
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    let (mut host_back, mut guest_back) = (false, false);
+    while !(host_back && guest_back) {
+        assert!(std::time::Instant::now() < deadline, "the match never came back");
+        host_back |= host.reconnect();
+        guest_back |= attempt.poll().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
+
+/// A link that loses whatever is sent while `down` is set, and always
+/// comes back when asked.
+struct Flaky {
+    inner: ChannelTransport,
+    down: Rc<Cell<bool>>,
+}
+
+impl Transport for Flaky {
+    fn send(&mut self, msg: &NetMessage) -> io::Result<()> {
+        if self.down.get() {
+            return Ok(());
+        }
+        self.inner.send(msg)
+    }
+
+    fn try_recv(&mut self) -> io::Result<Option<NetMessage>> {
+        self.inner.try_recv()
+    }
+
+    fn recv_blocking(&mut self) -> io::Result<NetMessage> {
+        self.inner.recv_blocking()
+    }
+
+    fn set_codec(&mut self, codec: Codec) {
+        self.inner.set_codec(codec);
+    }
+
+    fn reconnect(&mut self) -> bool {
+        true
+    }
+}
+
+fn attack_lines(msg: Option<NetMessage>) -> Option<u32> {
+    match msg {
+        Some(NetMessage::GarbageAttack(ga)) => Some(ga.lines),
+        _ => None,
+    }
+}
+
+#[test]
+fn events_lost_on_a_dropped_link_are_sent_again_in_order() {
+    let (a, b) = channel_pair();
+    let down = Rc::new(Cell::new(false));
+    let mut our_end = Flaky { inner: a, down: down.clone() };
+    let mut their_end = Flaky { inner: b, down: down.clone() };
+    let mut ours = ResumableLink::new(&mut our_end);
+    let mut theirs = ResumableLink::new(&mut their_end);
+    let attack = |lines| NetMessage::GarbageAttack(GarbageAttack { lines, hole_column: 0 });
+
+    ours.send(&attack(1)).unwrap();
+    assert_eq!(attack_lines(theirs.try_recv().unwrap()), Some(1));
+    down.set(true);
+    ours.send(&attack(2)).unwrap();
+    ours.send(&NetMessage::PlayerDead).unwrap();
+    down.set(false);
+
+    assert!(ours.reconnect());
+    assert!(theirs.reconnect());
+    // Held until the other side says what it already has.
+    ours.send(&attack(3)).unwrap();
+    assert!(theirs.try_recv().unwrap().is_none());
+    assert!(ours.try_recv().unwrap().is_none());
+
+    assert_eq!(attack_lines(theirs.try_recv().unwrap()), Some(2));
+    assert!(matches!(theirs.try_recv().unwrap(), Some(NetMessage::PlayerDead)));
+    assert_eq!(attack_lines(theirs.try_recv().unwrap()), Some(3));
+    assert!(theirs.try_recv().unwrap().is_none());
+}
+
+#[test]
+fn guest_back_before_the_host_noticed_still_gets_its_events_through() {
+    let (mut broadcast, _old_link, mut returning) = started_match(42);
+    let mut host = ResumableLink::new(&mut broadcast);
+
+    // The old link is still open, so the host only ever reads.
+    assert!(matches!(returning.try_recv().unwrap(), Some(NetMessage::Hello { .. })));
+    returning.send(&hello()).unwrap();
+    returning.set_codec(Codec::Binary);
+    assert!(host.try_recv().unwrap().is_none());
+    assert!(matches!(returning.try_recv().unwrap(), Some(NetMessage::LobbySettings(..))));
+    returning.send(&NetMessage::Resume(42)).unwrap();
+    assert!(host.try_recv().unwrap().is_none());
+    assert!(matches!(returning.try_recv().unwrap(), Some(NetMessage::Resumed)));
+
+    let mut guest_end = Flaky { inner: returning, down: Rc::new(Cell::new(false)) };
+    let mut guest = ResumableLink::new(&mut guest_end);
+    assert!(guest.reconnect());
+    guest.send(&NetMessage::GarbageAttack(GarbageAttack { lines: 2, hole_column: 0 })).unwrap();
+    assert!(guest.try_recv().unwrap().is_none());
+    assert_eq!(attack_lines(host.try_recv().unwrap()), Some(2));
+}
+
+// --- Keepalive tests ---
+
+#[test]
+fn heartbeat_pings_and_times_the_pong() {
+    let (mut a, mut b) = channel_pair();
+    let (mut ha, mut hb) = (Heartbeat::new(), Heartbeat::new());
+
+    ha.tick(&mut a);
+    ha.tick(&mut a);
+    let ping = b.try_recv().unwrap().unwrap();
+    assert!(matches!(ping, NetMessage::Ping(0)));
+    assert!(b.try_recv().unwrap().is_none(), "second tick came too soon");
+
+    assert!(hb.heard(&mut b, &ping));
+    let pong = a.try_recv().unwrap().unwrap();
+    assert!(matches!(pong, NetMessage::Pong(0)));
+    assert!(ha.rtt().is_none());
+    assert!(ha.heard(&mut a, &pong));
+    assert!(ha.rtt().is_some());
+    assert!(hb.rtt().is_none());
+}
+
+#[test]
+fn heartbeat_times_a_pong_slower_than_the_ping_interval() {
+    let (mut a, _b) = channel_pair();
+    let mut hb = Heartbeat::new();
+    hb.tick(&mut a);
+    std::thread::sleep(PING_INTERVAL);
+    hb.tick(&mut a);
+    // The pong to the first ping turns up after the second ping went out.
+    assert!(hb.heard(&mut a, &NetMessage::Pong(0)));
+    assert!(hb.rtt().is_some_and(|rtt| rtt >= PING_INTERVAL));
+}
+
+#[test]
+fn heartbeat_ignores_stale_pongs_and_passes_other_messages() {
+    let (mut a, _b) = channel_pair();
+    let mut hb = Heartbeat::new();
+    hb.tick(&mut a);
+    assert!(hb.heard(&mut a, &NetMessage::Pong(7)));
+    assert!(hb.rtt().is_none());
+    assert!(!hb.heard(&mut a, &NetMessage::PlayerDead));
+    assert!(!hb.timed_out());
+}
+
+// --- Codec and board delta tests ---
+
+fn board_with(cells: &[(usize, u8)]) -> BoardSnapshot {
+    let mut snap = empty_snapshot(0);
+    snap.board = vec![EMPTY; BOARD_WIDTH * VISIBLE_HEIGHT];
+    for &(i, cell) in cells {
+        snap.board[i] = cell;
+    }
+    snap
+}
+
+#[test]
+fn binary_codec_round_trips_and_is_smaller() {
+    let msg = NetMessage::BoardState(board_with(&[(195, 3), (199, 4)]));
+    let json = Codec::Json.encode(&msg).unwrap();
+    let binary = Codec::Binary.encode(&msg).unwrap();
+    assert!(binary.len() < json.len());
+    let NetMessage::BoardState(back) = Codec::Binary.decode(&binary).unwrap() else {
+        panic!("wrong message");
+    };
+    assert_eq!(back.board[199], 4);
+    assert!(Codec::Json.decode(&binary).is_err());
+}
+
+#[test]
+fn hub_handshake_switches_to_binary() {
+    let mut hub = SpectatorHub::new(None, Settings::default());
+    let (host_end, mut client) = channel_pair();
+    hub.admit(Box::new(host_end));
+    client.try_recv().unwrap();
+    client.send(&hello()).unwrap();
+    hub.poll();
+    // Still on JSON, the client cannot read the settings.
+    assert!(client.try_recv().is_err());
+}
+
+#[test]
+fn run_encoding_round_trips() {
+    let board = board_with(&[(0, 1), (1, 1), (150, 2)]).board;
+    let runs = delta::runs(&board);
+    assert_eq!(runs[0], (1, 2));
+    assert_eq!(delta::expand(&runs), board);
+}
+
+#[test]
+fn updates_build_on_acknowledged_boards() {
+    let mut encoder = BoardEncoder::new();
+    let mut decoder = BoardDecoder::default();
+
+    let first = encoder.encode(&board_with(&[(190, 1)]));
+    assert!(matches!(first.cells, BoardCells::Runs(_)));
+    assert!(decoder.decode(&first).is_some());
+    encoder.ack(first.seq);
+
+    let next = board_with(&[(190, 1), (191, 2)]);
+    let update = encoder.encode(&next);
+    assert!(matches!(&update.cells, BoardCells::Changed { base, cells } if *base == first.seq && cells.len() == 1));
+    assert_eq!(decoder.decode(&update).unwrap().board, next.board);
+}
+
+#[test]
+fn delta_on_unknown_base_is_dropped() {
+    let mut encoder = BoardEncoder::new();
+    let first = encoder.encode(&board_with(&[]));
+    encoder.ack(first.seq);
+    let update = encoder.encode(&board_with(&[(5, 1)]));
+    assert!(BoardDecoder::default().decode(&update).is_none());
+}
+
+#[test]
+fn encoder_falls_back_to_runs_without_acks() {
+    let mut encoder = BoardEncoder::new();
+    let first = encoder.encode(&board_with(&[]));
+    encoder.ack(first.seq);
+    let kinds: Vec<bool> = (0..20)
+        .map(|i| matches!(encoder.encode(&board_with(&[(i, 1)])).cells, BoardCells::Changed { .. }))
+        .collect();
+    assert!(kinds[0]);
+    assert!(!kinds[19]);
+}
+
+#[test]
+fn watchers_get_full_boards_from_updates() {
+    let mut hub = SpectatorHub::new(None, Settings::default());
+    let (mut watcher, _) = join(&mut hub, NetMessage::Spectate);
+    let (mut guest, arrival) = join(&mut hub, NetMessage::Ready);
+    let Some(Arrival::Player(player)) = arrival else {
+        panic!("expected a player");
+    };
+    let mut host = Broadcast::new(player, hub);
+    let mut encoder = BoardEncoder::new();
+
+    let first = encoder.encode(&board_with(&[(10, 5)]));
+    encoder.ack(first.seq);
+    guest.send(&NetMessage::BoardUpdate(first)).unwrap();
+    guest.send(&NetMessage::BoardUpdate(encoder.encode(&board_with(&[(10, 5), (11, 6)])))).unwrap();
+    while host.try_recv().unwrap().is_some() {}
+
+    let seen = drain(&mut watcher);
+    assert_eq!(seen.len(), 2);
+    assert!(matches!(&seen[1], NetMessage::SpectatorBoard(GUEST_SLOT, s) if s.board[11] == 6 && s.board[10] == 5));
+}
+
+// --- Battle royale room tests ---
+
+/// Seats a guest in `lobby` and returns its end, with the id it was given.
+fn seat(lobby: &mut RoomLobby) -> (ChannelTransport, u8) {
+    let (host_end, mut client) = channel_pair();
+    lobby.admit(Box::new(host_end));
+    client.recv_blocking().unwrap();
+    client.send(&hello()).unwrap();
+    client.set_codec(Codec::Binary);
+    assert!(lobby.poll().is_none());
+    client.recv_blocking().unwrap();
+    client.send(&NetMessage::JoinRoom).unwrap();
+    assert!(lobby.poll().is_none());
+    let NetMessage::RoomJoined(id) = client.recv_blocking().unwrap() else {
+        panic!("expected RoomJoined");
+    };
+    (client, id)
+}
+
+fn room_of_three() -> (RoomHost, ChannelTransport, ChannelTransport) {
+    let mut lobby = RoomLobby::new(SpectatorHub::new(None, Settings::default()));
+    let (mut a, _) = seat(&mut lobby);
+    let (mut b, _) = seat(&mut lobby);
+    a.send(&NetMessage::SetTargeting(TargetStrategy::Kos)).unwrap();
+    lobby.poll();
+    drain(&mut a);
+    drain(&mut b);
+    (lobby.start(TargetStrategy::Random, 1), a, b)
+}
+
+#[test]
+fn room_lobby_seats_players() {
+    let mut lobby = RoomLobby::new(SpectatorHub::new(None, Settings::default()));
+    let (mut a, id_a) = seat(&mut lobby);
+    let (_b, id_b) = seat(&mut lobby);
+    assert_eq!((id_a, id_b), (1, 2));
+    assert_eq!(lobby.roster(), vec![HOST_ID, 1, 2]);
+    let rosters: Vec<_> = drain(&mut a)
+        .into_iter()
+        .filter_map(|m| match m {
+            NetMessage::RoomPlayers(r) => Some(r),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(rosters.last(), Some(&vec![0, 1, 2]));
+}
+
+#[test]
+fn room_lobby_turns_away_one_on_one_players() {
+    let mut lobby = RoomLobby::new(SpectatorHub::new(None, Settings::default()));
+    let (mut client, arrival) = {
+        let (host_end, mut client) = channel_pair();
+        lobby.admit(Box::new(host_end));
+        client.recv_blocking().unwrap();
+        client.send(&hello()).unwrap();
+        client.set_codec(Codec::Binary);
+        lobby.poll();
+        client.recv_blocking().unwrap();
+        client.send(&NetMessage::Ready).unwrap();
+        (client, lobby.poll())
+    };
+    assert!(arrival.is_none());
+    assert_eq!(lobby.player_count(), 1);
+    assert!(matches!(client.try_recv().unwrap(), Some(NetMessage::Disconnect)));
+}
+
+#[test]
+fn room_host_routes_attacks_by_strategy() {
+    let (mut host, mut a, mut b) = room_of_three();
+    let mut tall = empty_snapshot(0);
+    tall.board = vec![1; crate::game::piece::BOARD_WIDTH * crate::game::piece::VISIBLE_HEIGHT];
+    host.send_board(tall);
+    b.send(&NetMessage::BoardState(empty_snapshot(0))).unwrap();
+    host.poll();
+    drain(&mut a);
+
+    // Guest 1 aims at the tallest stack, which is the host's.
+    let runs = vec![GarbageAttack { lines: 2, hole_column: 1 }, GarbageAttack { lines: 1, hole_column: 7 }];
+    a.send(&NetMessage::RoomAttack(runs)).unwrap();
+    let events = host.poll();
+    // Each run keeps its own hole.
+    assert!(events.iter().any(|e| matches!(e, RoomEvent::Garbage(1, g)
+        if g.iter().map(|r| (r.lines, r.hole_column)).eq([(2, 1), (1, 7)]))));
+    assert!(drain(&mut b).iter().all(|m| !matches!(m, NetMessage::RoomGarbage(..))));
+}
+
+#[test]
+fn room_host_relays_boards_to_other_players() {
+    let (mut host, mut a, mut b) = room_of_three();
+    a.send(&NetMessage::BoardState(empty_snapshot(42))).unwrap();
+    let events = host.poll();
+    assert!(matches!(&events[0], RoomEvent::Board(1, s) if s.score == 42));
+    assert!(matches!(&drain(&mut b)[0], NetMessage::RoomBoard(1, s) if s.score == 42));
+    assert!(drain(&mut a).is_empty());
+}
+
+#[test]
+fn room_ends_when_one_player_is_left() {
+    let (mut host, mut a, b) = room_of_three();
+    a.send(&NetMessage::PlayerDead).unwrap();
+    drop(b);
+    let events = host.poll();
+    assert!(matches!(events[0], RoomEvent::Ko(Ko { player: 1, place: 3, .. })));
+    assert!(matches!(events[1], RoomEvent::Ko(Ko { player: 2, place: 2, .. })));
+    assert!(matches!(&events[2], RoomEvent::Finished(p) if *p == vec![HOST_ID, 2, 1]));
+    assert!(drain(&mut a)
+        .iter()
+        .any(|m| matches!(m, NetMessage::RoomResult(p) if *p == vec![HOST_ID, 2, 1])));
+}
+
+#[test]
+fn room_guest_reads_host_messages() {
+    let (host_end, mut host) = channel_pair();
+    let mut guest = RoomGuest::new(2, Box::new(host_end));
+    host.send(&NetMessage::RoomGarbage(1, vec![GarbageAttack { lines: 2, hole_column: 0 }])).unwrap();
+    host.send(&NetMessage::RoomKo(Ko { player: 1, place: 3, by: Some(0) })).unwrap();
+    host.send(&NetMessage::Disconnect).unwrap();
+    let events = guest.poll();
+    assert!(matches!(events[0], RoomEvent::Garbage(1, _)));
+    assert!(matches!(events[1], RoomEvent::Ko(_)));
+    assert!(matches!(events[2], RoomEvent::HostLeft));
+
+    guest.send_attack(vec![GarbageAttack { lines: 1, hole_column: 0 }]);
+    assert!(matches!(host.try_recv().unwrap(), Some(NetMessage::RoomAttack(_))));
+}
+
+// --- LAN discovery tests ---
+
+#[test]
+fn beacon_roundtrip() {
+    let beacon = Beacon::new(21711, Settings::default(), true);
+    let decoded = Beacon::decode(&beacon.encode()).unwrap();
+    assert_eq!(decoded.port, 21711);
+    assert_eq!(decoded.version, PROTOCOL_VERSION);
+    assert_eq!(decoded.name, beacon.name);
+    assert!(decoded.room);
+    assert!(Beacon::decode(b"hello").is_none());
+    assert!(Beacon::decode(b"RTRS{not json").is_none());
+}
+
+#[test]
+fn finder_lists_announcing_hosts() {
+    let mut finder = HostFinder::bind(0).unwrap();
+    let port = finder.local_addr().unwrap().port();
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let beacon = Beacon::new(4000, Settings::default(), false);
+    socket.send_to(&beacon.encode(), ("127.0.0.1", port)).unwrap();
+    socket.send_to(&beacon.encode(), ("127.0.0.1", port)).unwrap();
+    socket.send_to(b"noise", ("127.0.0.1", port)).unwrap();
+
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(2);
+    while finder.hosts().is_empty() && std::time::Instant::now() < deadline {
+        finder.poll();
+    }
+    let hosts = finder.hosts();
+    assert_eq!(hosts.len(), 1);
+    assert_eq!(hosts[0].addr.to_string(), "127.0.0.1:4000");
+    assert!(hosts[0].compatible());
+}
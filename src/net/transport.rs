@@ -1,14 +1,43 @@
 use std::io::{self, Read, Write};
 use std::net::TcpStream;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
 use std::time::Duration;
 
+use super::codec::Codec;
 use super::protocol::NetMessage;
 
 const MAX_MESSAGE_LEN: usize = 64 * 1024;
+const RECV_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A link to the other side of a match. Only `recv_blocking` waits; a peer
+/// that has gone away shows up as `ConnectionAborted`.
+pub trait Transport {
+    fn send(&mut self, msg: &NetMessage) -> io::Result<()>;
+
+    /// The next complete message, or `None` if nothing has arrived yet.
+    fn try_recv(&mut self) -> io::Result<Option<NetMessage>>;
+
+    /// Waits up to ten seconds for the next message.
+    fn recv_blocking(&mut self) -> io::Result<NetMessage>;
+
+    /// Switches both directions to `codec` from the next message on.
+    fn set_codec(&mut self, codec: Codec);
+
+    /// Makes one attempt to bring back a link that dropped mid-match. Links
+    /// that cannot be resumed always say no.
+    fn reconnect(&mut self) -> bool {
+        false
+    }
+}
+
+fn peer_gone() -> io::Error {
+    io::Error::new(io::ErrorKind::ConnectionAborted, "peer disconnected")
+}
 
 pub struct Connection {
     stream: TcpStream,
     read_buf: Vec<u8>,
+    codec: Codec,
 }
 
 impl Connection {
@@ -17,20 +46,10 @@ impl Connection {
         Ok(Self {
             stream,
             read_buf: Vec::new(),
+            codec: Codec::Json,
         })
     }
 
-    pub fn send(&mut self, msg: &NetMessage) -> io::Result<()> {
-        let json = serde_json::to_string(msg)
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-        let payload = json.as_bytes();
-        let len = payload.len() as u32;
-        self.stream.write_all(&len.to_be_bytes())?;
-        self.stream.write_all(payload)?;
-        self.stream.flush()?;
-        Ok(())
-    }
-
     fn check_length(len: usize) -> io::Result<()> {
         if len > MAX_MESSAGE_LEN {
             return Err(io::Error::new(
@@ -59,20 +78,26 @@ impl Connection {
             return Ok(None);
         }
 
-        let json_bytes: Vec<u8> = self.read_buf.drain(..4 + len).skip(4).collect();
-        let msg: NetMessage = serde_json::from_slice(&json_bytes)
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-        Ok(Some(msg))
+        let payload: Vec<u8> = self.read_buf.drain(..4 + len).skip(4).collect();
+        self.codec.decode(&payload).map(Some)
     }
+}
 
-    pub fn try_recv(&mut self) -> io::Result<Option<NetMessage>> {
+impl Transport for Connection {
+    fn send(&mut self, msg: &NetMessage) -> io::Result<()> {
+        let payload = self.codec.encode(msg)?;
+        let len = payload.len() as u32;
+        self.stream.write_all(&len.to_be_bytes())?;
+        self.stream.write_all(&payload)?;
+        self.stream.flush()?;
+        Ok(())
+    }
+
+    fn try_recv(&mut self) -> io::Result<Option<NetMessage>> {
         let mut tmp = [0u8; 4096];
         match self.stream.read(&mut tmp) {
             Ok(0) => {
-                return Err(io::Error::new(
-                    io::ErrorKind::ConnectionAborted,
-                    "peer disconnected",
-                ));
+                return Err(peer_gone());
             }
             Ok(n) => {
                 self.read_buf.extend_from_slice(&tmp[..n]);
@@ -84,10 +109,10 @@ impl Connection {
         self.parse_message()
     }
 
-    pub fn recv_blocking(&mut self) -> io::Result<NetMessage> {
+    fn recv_blocking(&mut self) -> io::Result<NetMessage> {
         self.stream.set_nonblocking(false)?;
         self.stream
-            .set_read_timeout(Some(Duration::from_secs(10)))?;
+            .set_read_timeout(Some(RECV_TIMEOUT))?;
         let result = loop {
             match self.try_recv() {
                 Ok(Some(msg)) => break Ok(msg),
@@ -99,4 +124,54 @@ impl Connection {
         let _ = self.stream.set_nonblocking(true);
         result
     }
+
+    fn set_codec(&mut self, codec: Codec) {
+        self.codec = codec;
+    }
+}
+
+/// One end of an in-process link, for running both sides of a match in the
+/// same program. Messages are encoded into frames just as over TCP, so a
+/// codec mismatch fails here too.
+pub struct ChannelTransport {
+    tx: Sender<Vec<u8>>,
+    rx: Receiver<Vec<u8>>,
+    codec: Codec,
+}
+
+/// Two connected ends: whatever one sends, the other receives.
+pub fn channel_pair() -> (ChannelTransport, ChannelTransport) {
+    let (a_tx, b_rx) = mpsc::channel();
+    let (b_tx, a_rx) = mpsc::channel();
+    (
+        ChannelTransport { tx: a_tx, rx: a_rx, codec: Codec::Json },
+        ChannelTransport { tx: b_tx, rx: b_rx, codec: Codec::Json },
+    )
+}
+
+impl Transport for ChannelTransport {
+    fn send(&mut self, msg: &NetMessage) -> io::Result<()> {
+        let frame = self.codec.encode(msg)?;
+        self.tx.send(frame).map_err(|_| peer_gone())
+    }
+
+    fn try_recv(&mut self) -> io::Result<Option<NetMessage>> {
+        match self.rx.try_recv() {
+            Ok(frame) => self.codec.decode(&frame).map(Some),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(peer_gone()),
+        }
+    }
+
+    fn recv_blocking(&mut self) -> io::Result<NetMessage> {
+        match self.rx.recv_timeout(RECV_TIMEOUT) {
+            Ok(frame) => self.codec.decode(&frame),
+            Err(RecvTimeoutError::Timeout) => Err(io::Error::new(io::ErrorKind::TimedOut, "no message from peer")),
+            Err(RecvTimeoutError::Disconnected) => Err(peer_gone()),
+        }
+    }
+
+    fn set_codec(&mut self, codec: Codec) {
+        self.codec = codec;
+    }
 }
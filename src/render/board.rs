@@ -27,6 +27,7 @@ pub fn draw(stdout: &mut io::Stdout, game: &Game) -> io::Result<()> {
                 if slot < game.next_count && in_slot < 2 {
                     draw_piece_preview(
                         stdout,
+                        game.rotation_system,
                         game.next_queue[slot],
                         in_slot as i32,
                     )?;
@@ -4,6 +4,7 @@ use std::time::Duration;
 
 use crate::game::{Game, GameMode};
 use crate::game::piece::*;
+use crate::game::rotation::RotationSystem;
 
 pub(crate) const LEFT_W: usize = 12;
 
@@ -190,17 +191,21 @@ pub(crate) fn draw_board_cell(
 
 pub(crate) fn draw_piece_preview(
     stdout: &mut io::Stdout,
+    system: RotationSystem,
     kind: usize,
     preview_row: i32,
 ) -> io::Result<()> {
-    let blocks = &PIECE_STATES[kind][0];
+    let blocks = system.blocks(kind, 0);
     let color = PIECE_COLORS[kind];
     let min_row = blocks.iter().map(|b| b[0]).min().unwrap();
+    let min_col = blocks.iter().map(|b| b[1]).min().unwrap();
+    let max_col = blocks.iter().map(|b| b[1]).max().unwrap();
+    let left = (4 - (max_col - min_col + 1)) / 2;
     write!(stdout, "  ")?;
     for preview_col in 0..4i32 {
         let mut found = false;
         for b in blocks {
-            if b[0] - min_row == preview_row && b[1] + 1 == preview_col {
+            if b[0] - min_row == preview_row && b[1] - min_col + left == preview_col {
                 write!(stdout, "{}", "██".with(color))?;
                 found = true;
                 break;
@@ -322,9 +327,30 @@ pub(crate) fn draw_full_board_overlay(
     Ok(())
 }
 
+/// Trim `content` to the board height, keeping the first `header` rows fixed and
+/// scrolling the rest so that row `focus` stays visible.
+pub(crate) fn scroll_overlay(
+    content: Vec<Option<String>>,
+    header: usize,
+    focus: usize,
+) -> Vec<Option<String>> {
+    if content.len() <= VISIBLE_HEIGHT {
+        return content;
+    }
+    let body_rows = VISIBLE_HEIGHT - header;
+    let body_len = content.len() - header;
+    let first = (focus.saturating_sub(header) + 1)
+        .saturating_sub(body_rows)
+        .min(body_len - body_rows);
+    let mut rows = content;
+    let body: Vec<_> = rows.drain(header..).skip(first).take(body_rows).collect();
+    rows.extend(body);
+    rows
+}
+
 pub(crate) fn draw_right_panel(stdout: &mut io::Stdout, game: &Game, row: usize) -> io::Result<()> {
     let show_action = game.last_action.is_some()
-        && game.last_action_time.elapsed() < Duration::from_secs(3);
+        && game.since(game.last_action_time) < Duration::from_secs(3);
 
     match row {
         0 if game.hold_enabled => {
@@ -337,7 +363,7 @@ pub(crate) fn draw_right_panel(stdout: &mut io::Stdout, game: &Game, row: usize)
         2 | 3 if game.hold_enabled => {
             let pr = (row - 2) as i32;
             if let Some(kind) = game.hold {
-                draw_piece_preview(stdout, kind, pr)?;
+                draw_piece_preview(stdout, game.rotation_system, kind, pr)?;
             }
         }
         5 => match game.mode {
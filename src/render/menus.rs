@@ -5,11 +5,13 @@ use std::time::Duration;
 use crate::game::{Game, GameMode, format_option_or_inf};
 use crate::game::piece::*;
 use crate::game::records::Records;
+use crate::game::replay::Replay;
 use crate::game::settings::Settings;
+use crate::ui::keymap::{Action, Keymap};
 
 use super::{
     draw_board_bottom, draw_board_top, draw_full_board_overlay, draw_title, format_time,
-    menu_item, settings_toggle, settings_toggle_dim, settings_value, settings_value_dim, LEFT_W,
+    menu_item, scroll_overlay, settings_toggle, settings_toggle_dim, settings_value, settings_value_dim, LEFT_W,
 };
 
 pub fn draw_game_over(
@@ -25,7 +27,7 @@ pub fn draw_game_over(
 
     let title = if game.cleared {
         "CLEAR!"
-    } else if game.mode == GameMode::Ultra && game.elapsed >= Duration::from_secs(game.ultra_time as u64) {
+    } else if game.time_up() {
         "TIME'S UP!"
     } else {
         "GAME  OVER"
@@ -56,6 +58,8 @@ pub fn draw_game_over(
     content.push(Some(format!("{:>9}: {:<9}", "SCORE", game.score)));
     content.push(Some(format!("{:>9}: {:<9}", "LINES", game.lines)));
     content.push(Some(format!("{:>9}: {:<9}", "LEVEL", game.level)));
+    let seed_text = format!("{:^width$}", format!("#{:016X}", game.seed), width = inner_w);
+    content.push(Some(format!("{}", seed_text.as_str().with(Color::DarkGrey))));
     if let Some(r) = rank {
         let record_text = format!("NEW RECORD! #{}", r + 1);
         let padded = format!("{:^width$}", record_text, width = inner_w);
@@ -117,35 +121,42 @@ pub fn draw_mode_select(
         Some(menu_item("Start", selected == 1, inner_w)),
         Some(menu_item("Settings", selected == 2, inner_w)),
         Some(menu_item("Records", selected == 3, inner_w)),
-        Some(menu_item("Help", selected == 4, inner_w)),
-        Some(menu_item("Quit", selected == 5, inner_w)),
+        Some(menu_item("Replays", selected == 4, inner_w)),
+        Some(menu_item("Help", selected == 5, inner_w)),
+        Some(menu_item("Quit", selected == 6, inner_w)),
         None,
     ];
 
     draw_full_board_overlay(stdout, &content)
 }
 
-pub fn draw_help(stdout: &mut io::Stdout, selected: usize) -> io::Result<()> {
+pub fn draw_help(stdout: &mut io::Stdout, keymap: &Keymap, selected: usize) -> io::Result<()> {
     execute!(stdout, cursor::MoveTo(0, 0))?;
     draw_title(stdout)?;
 
     let inner_w = BOARD_WIDTH * 2;
 
-    let content: Vec<Option<String>> = vec![
+    let mut content: Vec<Option<String>> = vec![
         None,
         Some(format!("{:^width$}", "CONTROLS", width = inner_w)),
         None,
-        Some(format!("{:^width$}", format!("{:>6}  {:<9}", "←/→", "Move"), width = inner_w)),
-        Some(format!("{:^width$}", format!("{:>6}  {:<9}", "↓", "Soft drop"), width = inner_w)),
-        Some(format!("{:^width$}", format!("{:>6}  {:<9}", "Space", "Hard drop"), width = inner_w)),
-        Some(format!("{:^width$}", format!("{:>6}  {:<9}", "↑/X", "Rotate CW"), width = inner_w)),
-        Some(format!("{:^width$}", format!("{:>6}  {:<9}", "Z", "Rotate CCW"), width = inner_w)),
-        Some(format!("{:^width$}", format!("{:>6}  {:<9}", "C", "Hold"), width = inner_w)),
-        Some(format!("{:^width$}", format!("{:>6}  {:<9}", "Esc/P", "Pause"), width = inner_w)),
-        None,
-        Some(menu_item("Back", selected == 0, inner_w)),
-        None,
     ];
+    for action in Action::ALL {
+        let keys = if action == Action::Pause {
+            let bound = keymap.label(action);
+            if bound == "-" { "Esc".to_string() } else { format!("Esc/{}", bound) }
+        } else {
+            keymap.label(action)
+        };
+        content.push(Some(format!(
+            "{:^width$}",
+            format!("{:>7}  {:<10}", fit(&keys, 7), action.label()),
+            width = inner_w
+        )));
+    }
+    content.push(None);
+    content.push(Some(menu_item("Back", selected == 0, inner_w)));
+    content.push(None);
 
     let start_row = (VISIBLE_HEIGHT - content.len()) / 2;
 
@@ -234,24 +245,39 @@ pub fn draw_settings(
     content.push(Some(sv("Reset", &format_option_or_inf(settings.move_reset), mc + 2)));
     content.push(Some(st("Ghost", settings.ghost, mc + 3)));
     content.push(Some(st("Anim", settings.line_clear_anim, mc + 4)));
-    content.push(Some(st("Bag", settings.bag_randomizer, mc + 5)));
-    content.push(Some(st("SRS", settings.srs_enabled, mc + 6)));
+    content.push(Some(sv("Rand", settings.randomizer.label(), mc + 5)));
+    content.push(Some(sv("Rot", settings.rotation.label(), mc + 6)));
     content.push(Some(st("Hold", settings.hold_enabled, mc + 7)));
+    content.push(Some(st("180", settings.rotate_180, mc + 8)));
+    content.push(None);
+    content.push(Some(sv("DAS", &settings.das_ms.to_string(), mc + 9)));
+    content.push(Some(sv("ARR", &settings.arr_ms.to_string(), mc + 10)));
+    content.push(Some(sv("Rel", &settings.das_release_ms.to_string(), mc + 11)));
+    content.push(Some(sv("SDF", &format_option_or_inf(settings.soft_drop_factor), mc + 12)));
     content.push(None);
 
-    if in_game {
+    let focus = if in_game {
         content.push(Some(settings_toggle("BGM", bgm_on, selected == 0, inner_w)));
         content.push(Some(settings_toggle("SFX", sfx_on, selected == 1, inner_w)));
         content.push(None);
         content.push(Some(menu_item("Back", selected == 2, inner_w)));
+        content.len() - 1
     } else {
-        content.push(Some(settings_toggle("BGM", bgm_on, selected == mc + 8, inner_w)));
-        content.push(Some(settings_toggle("SFX", sfx_on, selected == mc + 9, inner_w)));
+        content.push(Some(settings_toggle("BGM", bgm_on, selected == mc + 13, inner_w)));
+        content.push(Some(settings_toggle("SFX", sfx_on, selected == mc + 14, inner_w)));
+        content.push(Some(menu_item("Controls", selected == mc + 15, inner_w)));
         content.push(None);
-        content.push(Some(menu_item("Back", selected == mc + 10, inner_w)));
-    }
+        content.push(Some(menu_item("Back", selected == mc + 16, inner_w)));
+        // Rows sit after the title and blank line, with a spacer before
+        // the handling, audio and Back groups.
+        2 + selected
+            + [mc + 9, mc + 13, mc + 16]
+                .iter()
+                .filter(|&&start| selected >= start)
+                .count()
+    };
 
-    draw_full_board_overlay(stdout, &content)
+    draw_full_board_overlay(stdout, &scroll_overlay(content, 2, focus))
 }
 
 pub fn draw_records(
@@ -274,12 +300,7 @@ pub fn draw_records(
     };
     let mode_label = format!("< {:^8} >", mode_name);
 
-    let list = match mode {
-        GameMode::Marathon => &records.marathon,
-        GameMode::Sprint => &records.sprint,
-        GameMode::Ultra => &records.ultra,
-        GameMode::Endless | GameMode::Versus => &records.endless,
-    };
+    let list = records.list(mode);
 
     let separator = "─".repeat(inner_w);
 
@@ -315,7 +336,12 @@ pub fn draw_records(
                     )
                 }
             };
-            content.push(Some(format!("{:^width$}", line, width = inner_w)));
+            let line = format!("{:^width$}", line, width = inner_w);
+            if selected == i + 1 {
+                content.push(Some(format!("{}", line.as_str().with(Color::Yellow))));
+            } else {
+                content.push(Some(line));
+            }
         } else {
             let line = match mode {
                 GameMode::Sprint => {
@@ -331,7 +357,109 @@ pub fn draw_records(
     }
 
     content.push(Some(separator));
-    content.push(Some(menu_item("Back", selected == 1, inner_w)));
+    let detail = selected.checked_sub(1).and_then(|i| list.get(i)).map(|r| {
+        let randomizer = r.randomizer.map_or("-", |kind| kind.label());
+        let line = format!("{}  {}", randomizer, r.date.get(..10).unwrap_or(""));
+        let padded = format!("{:^width$}", line, width = inner_w);
+        format!("{}", padded.as_str().with(Color::DarkGrey))
+    });
+    content.push(detail);
+    content.push(Some(menu_item("Back", selected == list.len() + 1, inner_w)));
+
+    draw_full_board_overlay(stdout, &content)
+}
+
+const REPLAY_LIST_ROWS: usize = 10;
+
+pub fn draw_replays(
+    stdout: &mut io::Stdout,
+    replays: &[Replay],
+    selected: usize,
+) -> io::Result<()> {
+    execute!(stdout, cursor::MoveTo(0, 0))?;
+    draw_title(stdout)?;
+
+    let inner_w = BOARD_WIDTH * 2;
+    let separator = "─".repeat(inner_w);
+
+    let mut content: Vec<Option<String>> = vec![
+        Some(format!("{:^width$}", "REPLAYS", width = inner_w)),
+        Some(separator.clone()),
+    ];
+
+    let first = selected
+        .min(replays.len())
+        .saturating_sub(REPLAY_LIST_ROWS - 1)
+        .min(replays.len().saturating_sub(REPLAY_LIST_ROWS));
+    for i in first..first + REPLAY_LIST_ROWS {
+        if let Some(r) = replays.get(i) {
+            let mode = match r.mode {
+                GameMode::Marathon => "MAR",
+                GameMode::Sprint => "SPR",
+                GameMode::Ultra => "ULT",
+                GameMode::Endless => "END",
+                GameMode::Versus => "VS",
+            };
+            let value = match r.mode {
+                GameMode::Sprint => format_time(Duration::from_millis(r.time)),
+                _ => r.score.to_string(),
+            };
+            let date = r.date.get(5..10).unwrap_or("");
+            let line = format!("{} {:>8} {}", mode, value, date);
+            content.push(Some(menu_item(&line, selected == i, inner_w)));
+        } else if i == 0 {
+            let padded = format!("{:^width$}", "No replays", width = inner_w);
+            content.push(Some(format!("{}", padded.as_str().with(Color::DarkGrey))));
+        } else {
+            content.push(None);
+        }
+    }
+
+    content.push(Some(separator));
+    content.push(Some(menu_item("Back", selected == replays.len(), inner_w)));
+
+    draw_full_board_overlay(stdout, &content)
+}
+
+/// Truncate `text` to at most `max` characters so it fits a fixed column.
+fn fit(text: &str, max: usize) -> String {
+    text.chars().take(max).collect()
+}
+
+pub fn draw_controls(
+    stdout: &mut io::Stdout,
+    keymap: &Keymap,
+    selected: usize,
+    capturing: bool,
+) -> io::Result<()> {
+    execute!(stdout, cursor::MoveTo(0, 0))?;
+    draw_title(stdout)?;
+
+    let inner_w = BOARD_WIDTH * 2;
+
+    let mut content: Vec<Option<String>> = vec![
+        Some(format!("{:^width$}", "CONTROLS", width = inner_w)),
+        None,
+    ];
+
+    for (i, action) in Action::ALL.into_iter().enumerate() {
+        let keys = if capturing && selected == i {
+            "...".to_string()
+        } else {
+            keymap.label(action)
+        };
+        let line = format!("{:<10} {:>7}", action.label(), fit(&keys, 7));
+        content.push(Some(menu_item(&line, selected == i, inner_w)));
+    }
+
+    content.push(None);
+    let hint = if capturing { "Press a key" } else { "Enter:add Bksp:clear" };
+    let padded = format!("{:^width$}", hint, width = inner_w);
+    content.push(Some(format!("{}", padded.as_str().with(Color::DarkGrey))));
+    content.push(None);
+    let n = Action::ALL.len();
+    content.push(Some(menu_item("Reset", selected == n, inner_w)));
+    content.push(Some(menu_item("Back", selected == n + 1, inner_w)));
 
     draw_full_board_overlay(stdout, &content)
 }
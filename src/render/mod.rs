@@ -1,9 +1,10 @@
 mod board;
 mod common;
 mod menus;
+pub mod royale;
 pub mod versus;
 
 pub use board::draw;
-pub use menus::{draw_game_over, draw_help, draw_mode_select, draw_pause, draw_records, draw_settings};
+pub use menus::{draw_controls, draw_game_over, draw_help, draw_mode_select, draw_pause, draw_records, draw_replays, draw_settings};
 
 pub(crate) use common::*;
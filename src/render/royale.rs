@@ -0,0 +1,176 @@
+use crossterm::style::{Color, Stylize};
+use crossterm::{cursor, execute};
+use std::io::{self, Write};
+
+use crate::game::piece::*;
+use crate::game::royale::PlayerId;
+use crate::game::Game;
+use crate::net::BoardSnapshot;
+
+use super::{color_for, draw_board_cell, draw_piece_preview, draw_title_padded, left_panel_pad, BoardRenderState, LEFT_W};
+
+/// Opponent boards per row of the grid.
+const MINI_COLUMNS: usize = 4;
+/// A label line plus half as many lines as the board has rows.
+const MINI_LINES: usize = 1 + VISIBLE_HEIGHT / 2;
+const MINI_W: usize = BOARD_WIDTH + 2;
+/// Next pieces that fit above the hold preview in the left panel.
+const ROYALE_NEXT: usize = 4;
+
+/// One opponent as shown in the grid beside the local board.
+pub struct MiniBoard<'a> {
+    pub id: PlayerId,
+    pub snapshot: Option<&'a BoardSnapshot>,
+    /// Set once the player has been knocked out.
+    pub place: Option<u8>,
+    pub kos: u32,
+}
+
+impl MiniBoard<'_> {
+    fn label(&self) -> String {
+        match self.place {
+            Some(place) => format!("P{} #{}", self.id + 1, place),
+            None if self.kos > 0 => format!("P{} KO:{}", self.id + 1, self.kos),
+            None => format!("P{}", self.id + 1),
+        }
+    }
+
+    fn cell_color(&self, row: usize, col: usize) -> Option<Color> {
+        let snap = self.snapshot?;
+        let dead = self.place.is_some();
+        let is_current = !dead
+            && snap
+                .current_cells
+                .iter()
+                .any(|&(r, c)| r as usize == row && c as usize == col);
+        if is_current {
+            return Some(PIECE_COLORS.get(snap.current_kind).copied().unwrap_or(Color::White));
+        }
+        let cell = snap.board.get(row * BOARD_WIDTH + col).copied().unwrap_or(EMPTY);
+        if cell == EMPTY {
+            None
+        } else if dead {
+            Some(Color::DarkGrey)
+        } else {
+            Some(color_for(cell))
+        }
+    }
+}
+
+/// Two board rows in one character, using half blocks.
+fn half_block(top: Option<Color>, bottom: Option<Color>) -> String {
+    match (top, bottom) {
+        (None, None) => " ".to_string(),
+        (Some(t), None) => format!("{}", "▀".with(t)),
+        (None, Some(b)) => format!("{}", "▄".with(b)),
+        (Some(t), Some(b)) if t == b => format!("{}", "█".with(t)),
+        (Some(t), Some(b)) => format!("{}", "▀".with(t).on(b)),
+    }
+}
+
+fn draw_mini_line(stdout: &mut io::Stdout, minis: &[MiniBoard], line: usize) -> io::Result<()> {
+    let grid_row = line / MINI_LINES;
+    let within = line % MINI_LINES;
+    for slot in 0..MINI_COLUMNS {
+        let Some(mini) = minis.get(grid_row * MINI_COLUMNS + slot) else {
+            break;
+        };
+        write!(stdout, " ")?;
+        if within == 0 {
+            let label = format!("{:^MINI_W$}", mini.label());
+            if mini.place.is_some() {
+                write!(stdout, "{}", label.as_str().with(Color::DarkGrey))?;
+            } else {
+                write!(stdout, "{}", label)?;
+            }
+            continue;
+        }
+        let top = (within - 1) * 2;
+        write!(stdout, "│")?;
+        for col in 0..BOARD_WIDTH {
+            write!(stdout, "{}", half_block(mini.cell_color(top, col), mini.cell_color(top + 1, col)))?;
+        }
+        write!(stdout, "│")?;
+    }
+    Ok(())
+}
+
+fn draw_left_panel(stdout: &mut io::Stdout, game: &Game, row: usize) -> io::Result<()> {
+    let next_shown = game.next_count.min(ROYALE_NEXT);
+    match row {
+        0 if next_shown > 0 => write!(stdout, "{:<LEFT_W$}", "  NEXT:"),
+        2..=13 if (row - 2) / 3 < next_shown && (row - 2) % 3 < 2 => {
+            let offset = row - 2;
+            draw_piece_preview(stdout, game.rotation_system, game.next_queue[offset / 3], (offset % 3) as i32)?;
+            left_panel_pad(stdout, 10)
+        }
+        15 if game.hold_enabled => {
+            if game.hold_used {
+                write!(stdout, "  {}", "HOLD:".with(Color::DarkGrey))?;
+                left_panel_pad(stdout, 7)
+            } else {
+                write!(stdout, "{:<LEFT_W$}", "  HOLD:")
+            }
+        }
+        17 | 18 if game.hold_enabled && game.hold.is_some() => {
+            draw_piece_preview(stdout, game.rotation_system, game.hold.unwrap_or(0), (row - 17) as i32)?;
+            left_panel_pad(stdout, 10)
+        }
+        _ => write!(stdout, "{:LEFT_W$}", ""),
+    }
+}
+
+/// The local board with its garbage bar, and every opponent as a mini board
+/// to the right of it. `status` is printed under the board.
+pub fn draw_royale(
+    stdout: &mut io::Stdout,
+    game: &Game,
+    minis: &[MiniBoard],
+    pending_garbage: u32,
+    status: &str,
+) -> io::Result<()> {
+    execute!(stdout, cursor::MoveTo(0, 0))?;
+
+    let state = BoardRenderState::from_game(game);
+    let garbage_bar_height = (pending_garbage as usize).min(VISIBLE_HEIGHT);
+    let bar_start_row = VISIBLE_HEIGHT - garbage_bar_height;
+
+    const ROYALE_TITLE_PAD: usize = 15;
+    draw_title_padded(stdout, ROYALE_TITLE_PAD)?;
+
+    write!(stdout, "{:LEFT_W$}╔", "")?;
+    for _ in 0..BOARD_WIDTH {
+        write!(stdout, "══")?;
+    }
+    write!(stdout, "╦═╗")?;
+    draw_mini_line(stdout, minis, 0)?;
+    write!(stdout, "\x1b[K\r\n")?;
+
+    for row in 0..VISIBLE_HEIGHT {
+        draw_left_panel(stdout, game, row)?;
+        write!(stdout, "║")?;
+        for col in 0..BOARD_WIDTH {
+            draw_board_cell(stdout, &game.board, row + BUFFER_HEIGHT, col, &state)?;
+        }
+        if row >= bar_start_row && garbage_bar_height > 0 {
+            write!(stdout, "║{}║", "█".with(Color::Red))?;
+        } else {
+            write!(stdout, "║ ║")?;
+        }
+        draw_mini_line(stdout, minis, row + 1)?;
+        write!(stdout, "\x1b[K\r\n")?;
+    }
+
+    write!(stdout, "{:LEFT_W$}╚", "")?;
+    for _ in 0..BOARD_WIDTH {
+        write!(stdout, "══")?;
+    }
+    write!(stdout, "╩═╝")?;
+    draw_mini_line(stdout, minis, VISIBLE_HEIGHT + 1)?;
+    write!(stdout, "\x1b[K\r\n")?;
+
+    write!(stdout, "{:LEFT_W$}{}\x1b[K\r\n", "", status)?;
+    write!(stdout, "\x1b[J")?;
+    stdout.flush()?;
+    Ok(())
+}
@@ -1,38 +1,39 @@
 use crossterm::style::{Color, Stylize};
 use crossterm::{cursor, execute};
 use std::io::{self, Write};
+use std::time::Duration;
 
-use crate::game::Game;
+use crate::game::{Game, format_option_or_inf};
+use crate::game::settings::Settings;
+use crate::game::stats::MatchStats;
 use crate::net::BoardSnapshot;
 use crate::game::piece::*;
 
-use super::{color_for, draw_board_cell, draw_full_board_overlay, draw_piece_preview, draw_right_panel, draw_title, draw_title_padded, left_panel_pad, menu_item, settings_toggle, BoardRenderState, LEFT_W};
+use super::{color_for, draw_board_cell, draw_full_board_overlay, draw_piece_preview, draw_right_panel, draw_title, draw_title_padded, left_panel_pad, menu_item, scroll_overlay, settings_toggle, settings_toggle_dim, settings_value, settings_value_dim, BoardRenderState, LEFT_W};
 
+/// The local board beside the opponent's. The bar between them shows
+/// incoming garbage: `ready_garbage` in red at the bottom, and above it in
+/// yellow the `waiting_garbage` still in its entry delay.
 pub fn draw_versus(
     stdout: &mut io::Stdout,
     game: &Game,
     opponent: &Option<BoardSnapshot>,
-    pending_garbage: u32,
+    ready_garbage: u32,
+    waiting_garbage: u32,
+    ping: Option<Duration>,
 ) -> io::Result<()> {
     execute!(stdout, cursor::MoveTo(0, 0))?;
 
     let state = BoardRenderState::from_game(game);
 
-    let garbage_bar_height = (pending_garbage as usize).min(VISIBLE_HEIGHT);
+    let garbage_bar_height = ((ready_garbage + waiting_garbage) as usize).min(VISIBLE_HEIGHT);
     let bar_start_row = VISIBLE_HEIGHT - garbage_bar_height;
+    let ready_start_row = VISIBLE_HEIGHT - (ready_garbage as usize).min(VISIBLE_HEIGHT);
 
     const VERSUS_TITLE_PAD: usize = 15;
     draw_title_padded(stdout, VERSUS_TITLE_PAD)?;
 
-    write!(stdout, "{:LEFT_W$}╔", "")?;
-    for _ in 0..BOARD_WIDTH {
-        write!(stdout, "══")?;
-    }
-    write!(stdout, "╦═╦")?;
-    for _ in 0..BOARD_WIDTH {
-        write!(stdout, "══")?;
-    }
-    write!(stdout, "╗\x1b[K\r\n")?;
+    draw_dual_edge(stdout, '╔', '╦', '╗')?;
 
     for row in 0..VISIBLE_HEIGHT {
         let board_row = row + BUFFER_HEIGHT;
@@ -45,7 +46,7 @@ pub fn draw_versus(
                 let slot = offset / 3;
                 let in_slot = offset % 3;
                 if slot < game.next_count && in_slot < 2 {
-                    draw_piece_preview(stdout, game.next_queue[slot], in_slot as i32)?;
+                    draw_piece_preview(stdout, game.rotation_system, game.next_queue[slot], in_slot as i32)?;
                     left_panel_pad(stdout, 10)?;
                 } else {
                     write!(stdout, "{:LEFT_W$}", "")?;
@@ -62,40 +63,15 @@ pub fn draw_versus(
             draw_board_cell(stdout, &game.board, board_row, col, &state)?;
         }
 
-        if row >= bar_start_row && garbage_bar_height > 0 {
+        if row >= ready_start_row {
             write!(stdout, "║{}║", "█".with(Color::Red))?;
+        } else if row >= bar_start_row {
+            write!(stdout, "║{}║", "█".with(Color::Yellow))?;
         } else {
             write!(stdout, "║ ║")?;
         }
 
-        if let Some(snap) = opponent {
-            for col in 0..BOARD_WIDTH {
-                let idx = row * BOARD_WIDTH + col;
-                let cell = snap.board.get(idx).copied().unwrap_or(0);
-
-                let is_current = snap
-                    .current_cells
-                    .iter()
-                    .any(|&(r, c)| r as usize == row && c as usize == col);
-
-                if is_current {
-                    let opp_color = if snap.current_kind < 7 {
-                        PIECE_COLORS[snap.current_kind]
-                    } else {
-                        Color::White
-                    };
-                    write!(stdout, "{}", "██".with(opp_color))?;
-                } else if cell == EMPTY {
-                    write!(stdout, "  ")?;
-                } else {
-                    write!(stdout, "{}", "██".with(color_for(cell)))?;
-                }
-            }
-        } else {
-            for _ in 0..BOARD_WIDTH {
-                write!(stdout, "  ")?;
-            }
-        }
+        draw_snapshot_row(stdout, opponent, row)?;
 
         write!(stdout, "║")?;
         draw_right_panel(stdout, game, row)?;
@@ -103,15 +79,110 @@ pub fn draw_versus(
         write!(stdout, "\x1b[K\r\n")?;
     }
 
-    write!(stdout, "{:LEFT_W$}╚", "")?;
+    draw_dual_edge(stdout, '╚', '╩', '╝')?;
+
+    if let Some(rtt) = ping {
+        draw_ping(stdout, rtt)?;
+    }
+
+    write!(stdout, "\x1b[J")?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// The round-trip time to the opponent, centred under their board.
+fn draw_ping(stdout: &mut io::Stdout, rtt: Duration) -> io::Result<()> {
+    let ms = rtt.as_millis();
+    let color = match ms {
+        0..50 => Color::Green,
+        50..150 => Color::Yellow,
+        _ => Color::Red,
+    };
+    let board_w = BOARD_WIDTH * 2;
+    // Past the left panel, the local board and the garbage bar.
+    let pad = LEFT_W + 1 + board_w + 3;
+    let text = format!("{:^board_w$}", format!("Ping {}ms", ms));
+    write!(stdout, "{:pad$}{}\x1b[K\r\n", "", text.as_str().with(color))
+}
+
+fn draw_dual_edge(stdout: &mut io::Stdout, left: char, mid: char, right: char) -> io::Result<()> {
+    write!(stdout, "{:LEFT_W$}{}", "", left)?;
     for _ in 0..BOARD_WIDTH {
         write!(stdout, "══")?;
     }
-    write!(stdout, "╩═╩")?;
+    write!(stdout, "{}═{}", mid, mid)?;
     for _ in 0..BOARD_WIDTH {
         write!(stdout, "══")?;
     }
-    write!(stdout, "╝\x1b[K\r\n")?;
+    write!(stdout, "{}\x1b[K\r\n", right)
+}
+
+fn draw_snapshot_row(stdout: &mut io::Stdout, snap: &Option<BoardSnapshot>, row: usize) -> io::Result<()> {
+    let Some(snap) = snap else {
+        for _ in 0..BOARD_WIDTH {
+            write!(stdout, "  ")?;
+        }
+        return Ok(());
+    };
+    for col in 0..BOARD_WIDTH {
+        let idx = row * BOARD_WIDTH + col;
+        let cell = snap.board.get(idx).copied().unwrap_or(0);
+
+        let is_current = snap
+            .current_cells
+            .iter()
+            .any(|&(r, c)| r as usize == row && c as usize == col);
+
+        if is_current {
+            let opp_color = if snap.current_kind < 7 {
+                PIECE_COLORS[snap.current_kind]
+            } else {
+                Color::White
+            };
+            write!(stdout, "{}", "██".with(opp_color))?;
+        } else if cell == EMPTY {
+            write!(stdout, "  ")?;
+        } else {
+            write!(stdout, "{}", "██".with(color_for(cell)))?;
+        }
+    }
+    Ok(())
+}
+
+fn snapshot_panel_line(name: &str, snap: &Option<BoardSnapshot>, row: usize) -> String {
+    match (row, snap) {
+        (0, _) => name.to_string(),
+        (2, Some(s)) => format!("SCORE: {}", s.score),
+        (3, Some(s)) => format!("LINES: {}", s.lines),
+        _ => String::new(),
+    }
+}
+
+/// Both players' boards side by side, as seen by a spectator. `slots[0]` is
+/// the host and sits on the left; the bar between them is its pending garbage.
+pub fn draw_spectate(stdout: &mut io::Stdout, slots: &[Option<BoardSnapshot>; 2]) -> io::Result<()> {
+    execute!(stdout, cursor::MoveTo(0, 0))?;
+
+    const VERSUS_TITLE_PAD: usize = 15;
+    draw_title_padded(stdout, VERSUS_TITLE_PAD)?;
+
+    let pending = slots[0].as_ref().map_or(0, |s| s.pending_garbage as usize);
+    let garbage_bar_height = pending.min(VISIBLE_HEIGHT);
+    let bar_start_row = VISIBLE_HEIGHT - garbage_bar_height;
+
+    draw_dual_edge(stdout, '╔', '╦', '╗')?;
+    for row in 0..VISIBLE_HEIGHT {
+        write!(stdout, "  {:<width$}║", snapshot_panel_line("HOST", &slots[0], row), width = LEFT_W - 2)?;
+        draw_snapshot_row(stdout, &slots[0], row)?;
+        if row >= bar_start_row && garbage_bar_height > 0 {
+            write!(stdout, "║{}║", "█".with(Color::Red))?;
+        } else {
+            write!(stdout, "║ ║")?;
+        }
+        draw_snapshot_row(stdout, &slots[1], row)?;
+        write!(stdout, "║  {}\x1b[K\r\n", snapshot_panel_line("GUEST", &slots[1], row))?;
+    }
+    draw_dual_edge(stdout, '╚', '╩', '╝')?;
 
     write!(stdout, "\x1b[J")?;
     stdout.flush()?;
@@ -162,7 +233,93 @@ pub fn draw_lobby_screen(
     draw_full_board_overlay(stdout, &content)
 }
 
-pub fn draw_versus_countdown(stdout: &mut io::Stdout, count: u8) -> io::Result<()> {
+/// Match settings on the setup screen; Ready and Leave follow them.
+pub const SETUP_ROWS: usize = 13;
+
+/// The pre-match screen: the host's settings, editable only by the host,
+/// and whether each side is ready.
+pub fn draw_match_setup(
+    stdout: &mut io::Stdout,
+    vs: &Settings,
+    is_host: bool,
+    ready: [bool; 2],
+    selected: usize,
+) -> io::Result<()> {
+    execute!(stdout, cursor::MoveTo(0, 0))?;
+    draw_title(stdout)?;
+
+    let inner_w = BOARD_WIDTH * 2;
+
+    let sv = |label: &str, value: &str, idx: usize| {
+        if is_host {
+            settings_value(label, value, selected == idx, inner_w)
+        } else {
+            settings_value_dim(label, value, inner_w)
+        }
+    };
+    let st = |label: &str, on: bool, idx: usize| {
+        if is_host {
+            settings_toggle(label, on, selected == idx, inner_w)
+        } else {
+            settings_toggle_dim(label, on, inner_w)
+        }
+    };
+    let status = |who: &str, ready: bool| {
+        let state = if ready { "READY" } else { "..." };
+        format!("{:^width$}", format!("{:<6}{:>6}", who, state), width = inner_w)
+    };
+
+    let lock_str = format!("{:.1}s", vs.lock_delay_ms as f32 / 1000.0);
+    let delay_str = format!("{:.1}s", vs.garbage.delay_ms as f32 / 1000.0);
+    let margin_str = vs.garbage.margin_secs.map_or("Off".to_string(), |s| format!("{}s", s));
+    let we_ready = ready[if is_host { 0 } else { 1 }];
+    let content: Vec<Option<String>> = vec![
+        Some(format!("{:^width$}", "MATCH SETUP", width = inner_w)),
+        None,
+        Some(sv("Level", &vs.level.to_string(), 0)),
+        Some(sv("Next", &vs.next_count.to_string(), 1)),
+        Some(sv("Lock", &lock_str, 2)),
+        Some(sv("Rot", vs.rotation.label(), 3)),
+        Some(st("Hold", vs.hold_enabled, 4)),
+        Some(st("Cncl", vs.garbage.cancel, 5)),
+        Some(sv("Atk", vs.garbage.table.label(), 6)),
+        Some(sv("Holes", vs.garbage.style.label(), 7)),
+        Some(sv("Delay", &delay_str, 8)),
+        Some(sv("Cap", &format_option_or_inf(vs.garbage.cap), 9)),
+        Some(sv("Margn", &margin_str, 10)),
+        Some(sv("Set", &set_label(vs.first_to), 11)),
+        Some(st("Check", vs.verify, 12)),
+        None,
+        Some(status("Host", ready[0])),
+        Some(status("Guest", ready[1])),
+        None,
+        Some(menu_item(if we_ready { "Unready" } else { "Ready" }, selected == SETUP_ROWS, inner_w)),
+        Some(menu_item("Leave", selected == SETUP_ROWS + 1, inner_w)),
+        None,
+    ];
+    // Settings follow the title and blank line; Ready and Leave come after
+    // the status block.
+    let focus = if selected < SETUP_ROWS { 2 + selected } else { 6 + selected };
+
+    draw_full_board_overlay(stdout, &scroll_overlay(content, 2, focus))
+}
+
+fn set_score_text(ours: u32, theirs: u32) -> String {
+    format!("You {} - {} Opp", ours, theirs)
+}
+
+/// "One" for one game, otherwise first to N.
+fn set_label(first_to: u32) -> String {
+    if first_to <= 1 {
+        "One".to_string()
+    } else {
+        format!("FT{}", first_to)
+    }
+}
+
+/// The countdown, with the game number and set score (ours first) above it
+/// when the match is a set.
+pub fn draw_versus_countdown(stdout: &mut io::Stdout, count: u8, set: Option<(u32, u32)>) -> io::Result<()> {
     execute!(stdout, cursor::MoveTo(0, 0))?;
     draw_title(stdout)?;
 
@@ -173,10 +330,18 @@ pub fn draw_versus_countdown(stdout: &mut io::Stdout, count: u8) -> io::Result<(
         count.to_string()
     };
 
+    let (game_line, score_line) = match set {
+        Some((ours, theirs)) => (
+            Some(format!("{:^width$}", format!("GAME {}", ours + theirs + 1), width = inner_w)),
+            Some(format!("{:^width$}", set_score_text(ours, theirs), width = inner_w)),
+        ),
+        None => (None, None),
+    };
+
     let content: Vec<Option<String>> = vec![
         None,
-        None,
-        None,
+        game_line,
+        score_line,
         Some(format!(
             "{}",
             format!("{:^width$}", text, width = inner_w)
@@ -190,9 +355,40 @@ pub fn draw_versus_countdown(stdout: &mut io::Stdout, count: u8) -> io::Result<(
     draw_full_board_overlay(stdout, &content)
 }
 
+/// Shown while a dropped match waits for the link to come back.
+pub fn draw_reconnecting(stdout: &mut io::Stdout, secs_left: u64) -> io::Result<()> {
+    execute!(stdout, cursor::MoveTo(0, 0))?;
+    draw_title(stdout)?;
+
+    let inner_w = BOARD_WIDTH * 2;
+
+    let content: Vec<Option<String>> = vec![
+        None,
+        Some(format!(
+            "{}",
+            format!("{:^width$}", "CONNECTION LOST", width = inner_w)
+                .as_str()
+                .with(Color::Red)
+        )),
+        None,
+        Some(format!("{:^width$}", "Reconnecting...", width = inner_w)),
+        Some(format!("{:^width$}", format!("{}s", secs_left), width = inner_w)),
+        None,
+    ];
+
+    draw_full_board_overlay(stdout, &content)
+}
+
+/// The result screen. `set` is the set score so far, ours first, when the
+/// match is a set. `stats` holds ours and, once it has arrived, the
+/// opponent's; without it only the outcome is shown. `next` labels the
+/// first item: a rematch, the next game of the set, or on to the set result.
 pub fn draw_versus_result(
     stdout: &mut io::Stdout,
     won: bool,
+    set: Option<(u32, u32)>,
+    stats: Option<(&MatchStats, Option<&MatchStats>)>,
+    next: &str,
     selected: usize,
 ) -> io::Result<()> {
     execute!(stdout, cursor::MoveTo(0, 0))?;
@@ -203,6 +399,43 @@ pub fn draw_versus_result(
     let title = if won { "YOU WIN!" } else { "YOU LOSE" };
     let title_color = if won { Color::Yellow } else { Color::Red };
 
+    let mut content: Vec<Option<String>> = vec![
+        None,
+        Some(format!(
+            "{}",
+            format!("{:^width$}", title, width = inner_w)
+                .as_str()
+                .with(title_color)
+        )),
+    ];
+    if let Some((ours, theirs)) = set {
+        content.push(Some(format!("{:^width$}", set_score_text(ours, theirs), width = inner_w)));
+    }
+    content.push(None);
+    if let Some((ours, theirs)) = stats {
+        content.push(Some(format!("{}", stat_line("", "YOU", "OPP").as_str().with(Color::DarkGrey))));
+        content.extend(stats_table(ours, theirs).into_iter().map(Some));
+        content.push(None);
+    }
+    content.push(Some(menu_item(next, selected == 0, inner_w)));
+    content.push(Some(menu_item("Menu", selected == 1, inner_w)));
+    if stats.is_none() {
+        content.push(None);
+    }
+
+    draw_full_board_overlay(stdout, &content)
+}
+
+/// Shown once a set is decided, with the final score (ours first).
+pub fn draw_set_result(stdout: &mut io::Stdout, won: bool, score: (u32, u32), selected: usize) -> io::Result<()> {
+    execute!(stdout, cursor::MoveTo(0, 0))?;
+    draw_title(stdout)?;
+
+    let inner_w = BOARD_WIDTH * 2;
+
+    let title = if won { "YOU WIN THE SET" } else { "YOU LOSE THE SET" };
+    let title_color = if won { Color::Yellow } else { Color::Red };
+
     let content: Vec<Option<String>> = vec![
         None,
         Some(format!(
@@ -212,6 +445,8 @@ pub fn draw_versus_result(
                 .with(title_color)
         )),
         None,
+        Some(format!("{:^width$}", set_score_text(score.0, score.1), width = inner_w)),
+        None,
         Some(menu_item("Rematch", selected == 0, inner_w)),
         Some(menu_item("Menu", selected == 1, inner_w)),
         None,
@@ -220,6 +455,33 @@ pub fn draw_versus_result(
     draw_full_board_overlay(stdout, &content)
 }
 
+fn stat_line(label: &str, ours: &str, theirs: &str) -> String {
+    format!(" {:<5}{:>7}{:>6} ", label, ours, theirs)
+}
+
+type StatValue = fn(&MatchStats) -> String;
+
+/// One row per stat, ours beside the opponent's; "-" while theirs is missing.
+fn stats_table(ours: &MatchStats, theirs: Option<&MatchStats>) -> Vec<String> {
+    let rows: [(&str, StatValue); 12] = [
+        ("Sent", |s| s.attack_sent.to_string()),
+        ("Recv", |s| s.attack_received.to_string()),
+        ("Cncl", |s| s.attack_cancelled.to_string()),
+        ("APM", |s| format!("{:.1}", s.apm())),
+        ("PPS", |s| format!("{:.2}", s.pps())),
+        ("VS", |s| format!("{:.1}", s.vs_score())),
+        ("Combo", |s| s.max_combo.to_string()),
+        ("B2B", |s| s.max_b2b.to_string()),
+        ("TSS", |s| s.tspins[0].to_string()),
+        ("TSD", |s| s.tspins[1].to_string()),
+        ("TST", |s| s.tspins[2].to_string()),
+        ("Mini", |s| s.tspin_minis.to_string()),
+    ];
+    rows.iter()
+        .map(|(label, value)| stat_line(label, &value(ours), &theirs.map_or("-".to_string(), value)))
+        .collect()
+}
+
 pub fn draw_versus_waiting_rematch(stdout: &mut io::Stdout, selected: usize) -> io::Result<()> {
     execute!(stdout, cursor::MoveTo(0, 0))?;
     draw_title(stdout)?;
@@ -5,21 +5,25 @@ use crate::game::GameMode;
 use crate::game::records::Records;
 use crate::game::settings::Settings;
 
+use super::cpu;
+use super::royale::{self, RoomLobbyResult};
 use super::menus::{self, VersusAction};
-use super::versus::{self, LobbyResult};
-use super::session;
+use super::versus::{self, JoinRole, LobbyResult};
+use super::keymap::Keymap;
+use super::{save_settings, session, spectate};
 
 fn run_versus_flow(
     stdout: &mut io::Stdout,
     music: &mut Option<audio::MusicPlayer>,
     settings: &mut Settings,
+    keymap: &Keymap,
 ) -> io::Result<()> {
     loop {
         match menus::run_versus_menu(stdout, music, settings)? {
             VersusAction::Host(port) => {
-                match versus::run_host_lobby(stdout, music, port)? {
+                match versus::run_host_lobby(stdout, music, settings, port)? {
                     LobbyResult::Connected(mut conn, vs_settings) => {
-                        if versus::run_versus(stdout, music, &mut conn, &vs_settings, true)? {
+                        if versus::run_versus(stdout, music, &mut *conn, &vs_settings, true, settings, keymap)? {
                             return Ok(());
                         }
                     }
@@ -28,9 +32,9 @@ fn run_versus_flow(
                 }
             }
             VersusAction::Join(addr) => {
-                match versus::run_client_lobby(stdout, music, &addr)? {
+                match versus::run_client_lobby(stdout, music, &addr, JoinRole::Player)? {
                     LobbyResult::Connected(mut conn, vs_settings) => {
-                        if versus::run_versus(stdout, music, &mut conn, &vs_settings, false)? {
+                        if versus::run_versus(stdout, music, &mut *conn, &vs_settings, false, settings, keymap)? {
                             return Ok(());
                         }
                     }
@@ -38,6 +42,39 @@ fn run_versus_flow(
                     LobbyResult::Menu => return Ok(()),
                 }
             }
+            VersusAction::Watch(addr) => {
+                match versus::run_client_lobby(stdout, music, &addr, JoinRole::Spectator)? {
+                    LobbyResult::Connected(mut conn, _) => spectate::run_spectate(stdout, music, &mut *conn)?,
+                    LobbyResult::Back => continue,
+                    LobbyResult::Menu => return Ok(()),
+                }
+            }
+            VersusAction::HostRoom(port) => {
+                match royale::run_room_host_lobby(stdout, music, settings, port)? {
+                    RoomLobbyResult::Start(mut link, roster, vs_settings) => {
+                        royale::run_room_match(stdout, music, &mut *link, &roster, &vs_settings, settings, keymap)?;
+                    }
+                    RoomLobbyResult::Back => continue,
+                    RoomLobbyResult::Menu => return Ok(()),
+                }
+            }
+            VersusAction::JoinRoom(addr) => {
+                let (conn, vs_settings) = match versus::run_client_lobby(stdout, music, &addr, JoinRole::Room)? {
+                    LobbyResult::Connected(conn, vs_settings) => (conn, vs_settings),
+                    LobbyResult::Back => continue,
+                    LobbyResult::Menu => return Ok(()),
+                };
+                match royale::run_room_guest_lobby(stdout, music, conn, vs_settings, settings)? {
+                    RoomLobbyResult::Start(mut link, roster, vs_settings) => {
+                        royale::run_room_match(stdout, music, &mut *link, &roster, &vs_settings, settings, keymap)?;
+                    }
+                    RoomLobbyResult::Back => continue,
+                    RoomLobbyResult::Menu => return Ok(()),
+                }
+            }
+            VersusAction::Cpu(difficulty) => {
+                cpu::run_cpu_versus(stdout, music, settings, keymap, difficulty)?;
+            }
             VersusAction::Back => return Ok(()),
         }
     }
@@ -45,19 +82,24 @@ fn run_versus_flow(
 
 pub fn run_app(stdout: &mut io::Stdout) -> io::Result<()> {
     let mut music = audio::MusicPlayer::new();
-    let mut settings = Settings::default();
+    let mut settings = Settings::load();
     let mut records = Records::load();
+    let mut keymap = Keymap::load();
+    if let Some(m) = music.as_mut() {
+        m.set_enabled(settings.bgm, settings.sfx);
+    }
 
     loop {
-        let mode = match menus::select_mode(stdout, &mut music, &mut settings, &mut records)? {
+        let mode = match menus::select_mode(stdout, &mut music, &mut settings, &mut records, &mut keymap)? {
             Some(m) => m,
             None => return Ok(()),
         };
         if mode == GameMode::Versus {
-            run_versus_flow(stdout, &mut music, &mut settings)?;
+            run_versus_flow(stdout, &mut music, &mut settings, &keymap)?;
+            save_settings(&mut settings, &music);
             continue;
         }
-        if session::run_game(stdout, mode, &mut music, &mut settings, &mut records)? {
+        if session::run_game(stdout, mode, &mut music, &mut settings, &mut records, &mut keymap)? {
             return Ok(());
         }
     }
@@ -0,0 +1,275 @@
+use crossterm::event::{self, KeyCode};
+use crossterm::{execute, terminal};
+use std::io;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::audio::{self, Sfx};
+use crate::game::bot::{Bot, BotDifficulty};
+use crate::game::clock::ManualClock;
+use crate::game::garbage::{GarbageEvent, GarbageQueue, GarbageRules, HoleGenerator};
+use crate::game::rng::random_seed;
+use crate::game::settings::Settings;
+use crate::game::{Game, GameInput, GameMode};
+use crate::net::BoardSnapshot;
+use crate::render;
+
+use super::input::{self, InputState};
+use super::keymap::Keymap;
+use super::versus::handle_forfeit_key;
+use super::{menu_nav, play_menu_sfx, read_key};
+
+/// The CPU side of an offline match. Its game runs on a manual clock that is
+/// advanced to wall time every frame, so the bot sees the same gravity, lock
+/// delay and ARE as the player.
+struct CpuOpponent {
+    game: Game,
+    bot: Bot,
+    garbage_queue: GarbageQueue,
+    rules: GarbageRules,
+    /// Holes for the garbage the CPU sends.
+    holes: HoleGenerator,
+    last_update: Instant,
+    next_piece_at: Instant,
+}
+
+impl CpuOpponent {
+    /// `hole_seed` should match the player's, so both sides get the same holes.
+    fn new(settings: &Settings, difficulty: BotDifficulty, hole_seed: u64) -> Self {
+        let now = Instant::now();
+        let clock = Arc::new(ManualClock::starting_at(now));
+        Self {
+            game: Game::with_manual_clock(GameMode::Versus, settings, random_seed(), clock),
+            bot: Bot::new(difficulty, settings.garbage.table),
+            garbage_queue: GarbageQueue::with_delay(Duration::from_millis(settings.garbage.delay_ms as u64)),
+            rules: settings.garbage,
+            holes: HoleGenerator::new(settings.garbage.style, hole_seed),
+            last_update: now,
+            next_piece_at: now + difficulty.piece_interval(),
+        }
+    }
+
+    fn can_place(&self, now: Instant) -> bool {
+        now >= self.next_piece_at
+            && !self.game.game_over
+            && !self.game.in_are()
+            && !self.game.is_animating()
+            && !self.game.is_garbage_animating()
+    }
+
+    /// Catches the CPU's game up to now, placing a piece if one is due.
+    /// Returns the garbage it sends.
+    fn update(&mut self) -> Vec<GarbageEvent> {
+        let now = Instant::now();
+        let dt = now.saturating_duration_since(self.last_update);
+        self.last_update = now;
+
+        let inputs = if self.can_place(now) {
+            self.bot.plan(&self.game)
+        } else {
+            Vec::new()
+        };
+        let lines_before = self.game.lines;
+        let fired = self.game.step(&inputs, dt);
+
+        if !inputs.contains(&GameInput::HardDrop) && !fired.contains(&GameInput::Lock) {
+            return Vec::new();
+        }
+        self.next_piece_at = now + self.bot.difficulty.piece_interval();
+        let sent = settle_lock(&mut self.game, &mut self.garbage_queue, lines_before, self.rules);
+        self.holes.deal(sent)
+    }
+
+    fn snapshot(&self) -> BoardSnapshot {
+        BoardSnapshot::from_game(&self.game, self.garbage_queue.total_pending())
+    }
+}
+
+/// After a piece locks: a line clear counters pending garbage and returns the
+/// attack left over to send, anything else lets the ready garbage rise.
+fn settle_lock(game: &mut Game, garbage_queue: &mut GarbageQueue, lines_before: u32, rules: GarbageRules) -> u32 {
+    if game.lines > lines_before {
+        let attack = game.last_action.as_ref().map_or(0, |a| rules.attack(a, game.elapsed));
+        garbage_queue.counter(attack, rules)
+    } else {
+        game.begin_garbage_rise(garbage_queue.take_ready(game.now(), rules.cap));
+        0
+    }
+}
+
+fn run_cpu_countdown(stdout: &mut io::Stdout, music: &mut Option<audio::MusicPlayer>) -> io::Result<()> {
+    for count in (1..=3).rev() {
+        render::versus::draw_versus_countdown(stdout, count, None)?;
+        play_menu_sfx(music, Sfx::MenuMove);
+        std::thread::sleep(Duration::from_secs(1));
+    }
+    Ok(())
+}
+
+/// Versus against a local bot, exchanging garbage through the same
+/// `GarbageQueue` rules as a LAN match.
+pub fn run_cpu_versus(
+    stdout: &mut io::Stdout,
+    music: &mut Option<audio::MusicPlayer>,
+    settings: &Settings,
+    keymap: &Keymap,
+    difficulty: BotDifficulty,
+) -> io::Result<()> {
+    execute!(stdout, terminal::Clear(terminal::ClearType::All))?;
+
+    loop {
+        run_cpu_countdown(stdout, music)?;
+
+        let mut game_settings = *settings;
+        game_settings.level_cap = Some(game_settings.level);
+        let mut game = Game::new(GameMode::Versus, &game_settings, random_seed());
+        let mut garbage_queue = GarbageQueue::with_delay(Duration::from_millis(game_settings.garbage.delay_ms as u64));
+        let hole_seed = random_seed();
+        let mut holes = HoleGenerator::new(game_settings.garbage.style, hole_seed);
+        let mut cpu = CpuOpponent::new(&game_settings, difficulty, hole_seed);
+
+        let mut inp = InputState::new(keymap, settings);
+        let mut forfeit_sel: Option<usize> = None;
+
+        if let Some(m) = music.as_mut() {
+            m.play();
+        }
+        execute!(stdout, terminal::Clear(terminal::ClearType::All))?;
+
+        while !game.game_over && !cpu.game.game_over {
+            game.update_elapsed();
+
+            if let Some(sel) = forfeit_sel {
+                let (bgm_on, sfx_on) = match music.as_ref() {
+                    Some(m) => (m.bgm_enabled(), m.sfx_enabled()),
+                    None => (false, false),
+                };
+                render::versus::draw_versus_forfeit(stdout, bgm_on, sfx_on, sel)?;
+            } else {
+                let ready = garbage_queue.ready_lines(game.now());
+                let waiting = garbage_queue.total_pending() - ready;
+                let ready = ready + game.garbage_anim_remaining();
+                render::versus::draw_versus(stdout, &game, &Some(cpu.snapshot()), ready, waiting, None)?;
+            }
+
+            // The bot keeps playing while the forfeit menu is open, as a
+            // remote opponent would.
+            let sent = cpu.update();
+            if !sent.is_empty() {
+                for run in sent {
+                    garbage_queue.push_at(run, game.now());
+                }
+                if let Some(m) = music.as_ref() {
+                    m.play_sfx(Sfx::GarbageReceived);
+                }
+            }
+
+            if game.is_animating() && !game.update_animation() {
+                game.finish_clear();
+                inp.last_tick = Instant::now();
+            }
+
+            if game.is_garbage_animating() {
+                if game.update_garbage_animation() {
+                    if let Some(m) = music.as_ref() {
+                        m.play_sfx(Sfx::GarbageReceived);
+                    }
+                } else {
+                    inp.last_tick = Instant::now();
+                }
+            }
+
+            let timeout = if forfeit_sel.is_some() || game.is_animating() || game.is_garbage_animating() {
+                Duration::from_millis(16)
+            } else {
+                input::compute_timeout(&game, &inp).min(Duration::from_millis(16))
+            };
+
+            let mut lines_before = game.lines;
+            let mut locked = false;
+            if event::poll(timeout)? {
+                if let Some(code) = read_key()? {
+                    if forfeit_sel.is_some() {
+                        handle_forfeit_key(stdout, music, code, &mut forfeit_sel, &mut game, &mut inp)?;
+                    } else if inp.keymap.is_pause(code) {
+                        if let Some(m) = music.as_ref() {
+                            m.play_sfx(Sfx::Pause);
+                        }
+                        forfeit_sel = Some(0);
+                    } else if !game.is_animating() && !game.is_garbage_animating() {
+                        locked = input::handle_game_key(code, &mut game, &mut inp, music);
+                    }
+                }
+            }
+
+            if locked {
+                send_to_cpu(&mut game, &mut garbage_queue, &mut holes, &mut cpu, lines_before);
+                lines_before = game.lines;
+            }
+
+            if !game.is_animating() && !game.is_garbage_animating() {
+                let in_are = game.in_are();
+                let locked = input::update_game_timers(&mut game, &mut inp, music);
+                if locked && !in_are {
+                    send_to_cpu(&mut game, &mut garbage_queue, &mut holes, &mut cpu, lines_before);
+                }
+            }
+        }
+
+        let won = cpu.game.game_over && !game.game_over;
+        if let Some(m) = music.as_mut() {
+            m.stop();
+            m.play_sfx(if won { Sfx::VersusWin } else { Sfx::VersusLose });
+        }
+
+        if !run_cpu_result_screen(stdout, music, won)? {
+            return Ok(());
+        }
+    }
+}
+
+fn send_to_cpu(
+    game: &mut Game,
+    garbage_queue: &mut GarbageQueue,
+    holes: &mut HoleGenerator,
+    cpu: &mut CpuOpponent,
+    lines_before: u32,
+) {
+    let sent = settle_lock(game, garbage_queue, lines_before, cpu.rules);
+    for run in holes.deal(sent) {
+        cpu.garbage_queue.push_at(run, cpu.game.now());
+    }
+}
+
+/// Returns whether the player asked for a rematch.
+fn run_cpu_result_screen(
+    stdout: &mut io::Stdout,
+    music: &mut Option<audio::MusicPlayer>,
+    won: bool,
+) -> io::Result<bool> {
+    let mut sel: usize = 0;
+    loop {
+        render::versus::draw_versus_result(stdout, won, None, None, "Rematch", sel)?;
+        if let Some(code) = read_key()? {
+            match code {
+                KeyCode::Up | KeyCode::Down => {
+                    sel = menu_nav(sel, 2, code);
+                    play_menu_sfx(music, Sfx::MenuMove);
+                }
+                KeyCode::Enter => {
+                    if sel == 0 {
+                        play_menu_sfx(music, Sfx::MenuSelect);
+                        return Ok(true);
+                    }
+                    play_menu_sfx(music, Sfx::MenuBack);
+                    return Ok(false);
+                }
+                KeyCode::Esc => {
+                    play_menu_sfx(music, Sfx::MenuBack);
+                    return Ok(false);
+                }
+                _ => {}
+            }
+        }
+    }
+}
@@ -3,7 +3,12 @@ use std::io;
 use std::time::{Duration, Instant};
 
 use crate::audio::{self, Sfx};
-use crate::game::{Game, LastMove, ARE_DELAY};
+use crate::game::lockstep::{LockstepAction, LockstepLog};
+use crate::game::replay::ReplayRecorder;
+use crate::game::settings::Settings;
+use crate::game::{Game, GameInput, LastMove, ARE_DELAY};
+
+use super::keymap::{Action, Keymap};
 
 fn force_quit() -> io::Error {
     io::Error::new(io::ErrorKind::Interrupted, "force quit")
@@ -23,9 +28,33 @@ pub(crate) fn read_key() -> io::Result<Option<KeyCode>> {
     }
 }
 
-pub(crate) const DAS_DELAY: Duration = Duration::from_millis(167);
-pub(crate) const ARR_INTERVAL: Duration = Duration::from_millis(33);
-pub(crate) const DAS_RELEASE: Duration = Duration::from_millis(100);
+/// Poll interval while DAS is charged with ARR 0, so the piece keeps sliding
+/// into gaps it falls past.
+const INSTANT_ARR_POLL: Duration = Duration::from_millis(16);
+
+/// Per-player key handling, taken from the local settings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Handling {
+    pub das: Duration,
+    /// Zero moves the piece all the way to the wall once DAS is charged.
+    pub arr: Duration,
+    /// Terminals do not report key releases, so DAS is dropped once no repeat
+    /// event has arrived for this long.
+    pub das_release: Duration,
+    /// Cells per soft-drop key event; `None` drops to the floor without locking.
+    pub soft_drop_factor: Option<u32>,
+}
+
+impl Handling {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            das: Duration::from_millis(settings.das_ms as u64),
+            arr: Duration::from_millis(settings.arr_ms as u64),
+            das_release: Duration::from_millis(settings.das_release_ms as u64),
+            soft_drop_factor: settings.soft_drop_factor,
+        }
+    }
+}
 
 pub(crate) struct DasState {
     pub direction: i32,
@@ -79,7 +108,7 @@ pub(crate) fn play_clear_sfx(music: &audio::MusicPlayer, game: &Game, prev_level
         }
     } else if !game.game_over {
         if let Some(action) = &game.last_action {
-            if game.last_action_time.elapsed().as_millis() < 100 {
+            if game.since(game.last_action_time).as_millis() < 100 {
                 if action.label.contains("Mini T-Spin") {
                     music.play_sfx(Sfx::TSpinMini);
                 } else if action.label.contains("T-Spin") {
@@ -99,15 +128,31 @@ pub(crate) struct InputState {
     pub irs: Option<i32>,
     pub ihs: bool,
     pub last_tick: Instant,
+    pub recorder: Option<ReplayRecorder>,
+    /// Inputs waiting to go to the opponent in a verified match.
+    pub lockstep: Option<LockstepLog>,
+    pub keymap: Keymap,
+    pub handling: Handling,
 }
 
 impl InputState {
-    pub fn new() -> Self {
+    pub fn new(keymap: &Keymap, settings: &Settings) -> Self {
         Self {
             das: None,
             irs: None,
             ihs: false,
             last_tick: Instant::now(),
+            recorder: None,
+            lockstep: None,
+            keymap: keymap.clone(),
+            handling: Handling::from_settings(settings),
+        }
+    }
+
+    pub fn recording(keymap: &Keymap, settings: &Settings) -> Self {
+        Self {
+            recorder: Some(ReplayRecorder::new()),
+            ..Self::new(keymap, settings)
         }
     }
 
@@ -116,6 +161,51 @@ impl InputState {
         self.irs = None;
         self.ihs = false;
         self.last_tick = Instant::now();
+        if let Some(r) = &mut self.recorder {
+            r.clear();
+        }
+    }
+
+    /// Log an input that was just applied to `game`, if this session is
+    /// recorded or verified.
+    pub fn record(&mut self, game: &Game, input: GameInput) {
+        if let Some(r) = &mut self.recorder {
+            r.record(game.since(game.game_start), input);
+        }
+        if let Some(log) = &mut self.lockstep {
+            log.push(game, LockstepAction::Input(input));
+        }
+    }
+}
+
+fn move_input(dir: i32) -> GameInput {
+    if dir < 0 {
+        GameInput::MoveLeft
+    } else {
+        GameInput::MoveRight
+    }
+}
+
+/// Rotate by `turn` quarter turns clockwise (-1 for counter-clockwise).
+fn rotate(game: &mut Game, input: &mut InputState, music: &Option<audio::MusicPlayer>, turn: i32) {
+    match turn {
+        1 => {
+            game.rotate_cw();
+            input.record(game, GameInput::RotateCw);
+        }
+        -1 => {
+            game.rotate_ccw();
+            input.record(game, GameInput::RotateCcw);
+        }
+        _ => {
+            game.rotate_180();
+            input.record(game, GameInput::Rotate180);
+        }
+    }
+    if game.last_move == LastMove::Rotate {
+        if let Some(m) = music.as_ref() {
+            m.play_sfx(Sfx::Rotate);
+        }
     }
 }
 
@@ -132,57 +222,55 @@ pub(crate) fn handle_game_key(
     input: &mut InputState,
     music: &Option<audio::MusicPlayer>,
 ) -> bool {
-    match code {
-        KeyCode::Left | KeyCode::Right => {
-            let dir = if code == KeyCode::Left { -1 } else { 1 };
+    let Some(action) = input.keymap.action_for(code) else {
+        return false;
+    };
+    match action {
+        Action::MoveLeft | Action::MoveRight => {
+            let dir = if action == Action::MoveLeft { -1 } else { 1 };
             if let Some(d) = input.das.as_mut().filter(|d| d.direction == dir) {
                 d.last_event = Instant::now();
             } else {
                 input.das = Some(DasState::new(dir));
                 if !game.in_are() && game.move_piece(0, dir) {
+                    input.record(game, move_input(dir));
                     play_move_sfx(music);
                 }
             }
             false
         }
-        KeyCode::Down => {
+        Action::SoftDrop => {
             if !game.in_are() {
-                game.soft_drop();
-            }
-            false
-        }
-        KeyCode::Up | KeyCode::Char('x') | KeyCode::Char('X') => {
-            if game.in_are() {
-                input.irs = Some(1);
-            } else {
-                game.rotate_cw();
-                if game.last_move == LastMove::Rotate {
-                    if let Some(m) = music.as_ref() {
-                        m.play_sfx(Sfx::Rotate);
+                let cells = input.handling.soft_drop_factor.unwrap_or(u32::MAX);
+                for _ in 0..cells {
+                    if !game.soft_drop() {
+                        break;
                     }
+                    input.record(game, GameInput::SoftDrop);
                 }
             }
             false
         }
-        KeyCode::Char('z') | KeyCode::Char('Z') => {
+        Action::RotateCw | Action::RotateCcw | Action::Rotate180 => {
+            let turn = match action {
+                Action::RotateCw => 1,
+                Action::RotateCcw => -1,
+                _ => 2,
+            };
             if game.in_are() {
-                input.irs = Some(-1);
+                input.irs = Some(turn);
             } else {
-                game.rotate_ccw();
-                if game.last_move == LastMove::Rotate {
-                    if let Some(m) = music.as_ref() {
-                        m.play_sfx(Sfx::Rotate);
-                    }
-                }
+                rotate(game, input, music, turn);
             }
             false
         }
-        KeyCode::Char('c') | KeyCode::Char('C') => {
+        Action::Hold => {
             if game.in_are() {
                 input.ihs = true;
             } else {
                 let was_used = game.hold_used;
                 game.hold_piece();
+                input.record(game, GameInput::Hold);
                 if !was_used && game.hold_used {
                     if let Some(m) = music.as_ref() {
                         m.play_sfx(Sfx::Hold);
@@ -191,13 +279,14 @@ pub(crate) fn handle_game_key(
             }
             false
         }
-        KeyCode::Char(' ') => {
+        Action::HardDrop => {
             if !game.in_are() {
                 let prev_level = game.level;
                 if let Some(m) = music.as_ref() {
                     m.play_sfx(Sfx::HardDrop);
                 }
                 game.hard_drop();
+                input.record(game, GameInput::HardDrop);
                 if let Some(m) = music.as_ref() {
                     play_clear_sfx(m, game, prev_level);
                 }
@@ -206,7 +295,7 @@ pub(crate) fn handle_game_key(
             }
             false
         }
-        _ => false,
+        Action::Pause => false,
     }
 }
 
@@ -219,25 +308,35 @@ pub(crate) fn update_game_timers(
 ) -> bool {
     // DAS release
     if let Some(d) = &input.das {
-        if d.last_event.elapsed() >= DAS_RELEASE {
+        if d.last_event.elapsed() >= input.handling.das_release {
             input.das = None;
         }
     }
 
     // DAS auto-repeat
+    let handling = input.handling;
     if let Some(d) = &mut input.das {
         if !game.in_are() {
-            if !d.charged && d.start.elapsed() >= DAS_DELAY {
+            let dir = d.direction;
+            let mut moves = 0;
+            if !d.charged && d.start.elapsed() >= handling.das {
                 d.charged = true;
                 d.last_arr_move = Instant::now();
-                if game.move_piece(0, d.direction) {
-                    play_move_sfx(music);
-                }
-            } else if d.charged && d.last_arr_move.elapsed() >= ARR_INTERVAL {
+                moves = if handling.arr.is_zero() { usize::MAX } else { 1 };
+            } else if d.charged && d.last_arr_move.elapsed() >= handling.arr {
                 d.last_arr_move = Instant::now();
-                if game.move_piece(0, d.direction) {
-                    play_move_sfx(music);
+                moves = if handling.arr.is_zero() { usize::MAX } else { 1 };
+            }
+            let mut moved = false;
+            for _ in 0..moves {
+                if !game.move_piece(0, dir) {
+                    break;
                 }
+                input.record(game, move_input(dir));
+                moved = true;
+            }
+            if moved {
+                play_move_sfx(music);
             }
         }
     }
@@ -245,33 +344,29 @@ pub(crate) fn update_game_timers(
     // ARE check with IRS/IHS
     if game.in_are() {
         if game.check_are() {
+            input.record(game, GameInput::AreEnd);
             if input.ihs {
                 input.ihs = false;
                 let was_used = game.hold_used;
                 game.hold_piece();
+                input.record(game, GameInput::Hold);
                 if !was_used && game.hold_used {
                     if let Some(m) = music.as_ref() {
                         m.play_sfx(Sfx::Hold);
                     }
                 }
             }
-            if let Some(dir) = input.irs.take() {
-                if dir > 0 {
-                    game.rotate_cw();
-                } else {
-                    game.rotate_ccw();
-                }
-                if game.last_move == LastMove::Rotate {
-                    if let Some(m) = music.as_ref() {
-                        m.play_sfx(Sfx::Rotate);
-                    }
-                }
+            if let Some(turn) = input.irs.take() {
+                rotate(game, input, music, turn);
             }
             input.last_tick = Instant::now();
             if let Some(d) = &mut input.das {
                 if d.charged {
-                    while game.move_piece(0, d.direction) {}
+                    let dir = d.direction;
                     d.last_arr_move = Instant::now();
+                    while game.move_piece(0, dir) {
+                        input.record(game, move_input(dir));
+                    }
                 }
             }
         }
@@ -279,22 +374,22 @@ pub(crate) fn update_game_timers(
     }
 
     // Lock delay
-    if let Some(lock_start) = game.lock_delay {
-        if lock_start.elapsed() >= game.lock_delay_duration() {
-            game.lock_delay = None;
-            let prev_level = game.level;
-            game.lock_and_begin_clear();
-            if let Some(m) = music.as_ref() {
-                play_clear_sfx(m, game, prev_level);
-            }
-            input.last_tick = Instant::now();
-            return true;
+    if game.lock_delay_expired() {
+        game.lock_delay = None;
+        let prev_level = game.level;
+        game.lock_and_begin_clear();
+        input.record(game, GameInput::Lock);
+        if let Some(m) = music.as_ref() {
+            play_clear_sfx(m, game, prev_level);
         }
+        input.last_tick = Instant::now();
+        return true;
     }
 
     // Gravity tick
     if input.last_tick.elapsed() >= game.drop_interval() {
         game.tick();
+        input.record(game, GameInput::Gravity);
         input.last_tick = Instant::now();
     }
 
@@ -308,7 +403,7 @@ pub(crate) fn compute_timeout(game: &Game, input: &InputState) -> Duration {
     } else {
         let gravity_remaining = game.drop_interval().saturating_sub(input.last_tick.elapsed());
         if let Some(lock_start) = game.lock_delay {
-            let lock_remaining = game.lock_delay_duration().saturating_sub(lock_start.elapsed());
+            let lock_remaining = game.lock_delay_duration().saturating_sub(game.since(lock_start));
             gravity_remaining.min(lock_remaining)
         } else {
             gravity_remaining
@@ -316,15 +411,18 @@ pub(crate) fn compute_timeout(game: &Game, input: &InputState) -> Duration {
     };
 
     if let Some(are_start) = game.are_timer {
-        timeout = timeout.min(ARE_DELAY.saturating_sub(are_start.elapsed()));
+        timeout = timeout.min(ARE_DELAY.saturating_sub(game.since(are_start)));
     }
 
     if let Some(d) = &input.das {
-        timeout = timeout.min(DAS_RELEASE.saturating_sub(d.last_event.elapsed()));
+        let h = &input.handling;
+        timeout = timeout.min(h.das_release.saturating_sub(d.last_event.elapsed()));
         if !d.charged {
-            timeout = timeout.min(DAS_DELAY.saturating_sub(d.start.elapsed()));
+            timeout = timeout.min(h.das.saturating_sub(d.start.elapsed()));
+        } else if h.arr.is_zero() {
+            timeout = timeout.min(INSTANT_ARR_POLL);
         } else {
-            timeout = timeout.min(ARR_INTERVAL.saturating_sub(d.last_arr_move.elapsed()));
+            timeout = timeout.min(h.arr.saturating_sub(d.last_arr_move.elapsed()));
         }
     }
 
@@ -342,6 +440,7 @@ pub(crate) fn toggle_bgm(music: &mut Option<audio::MusicPlayer>) {
         m.toggle_bgm();
         m.play_sfx(Sfx::MenuMove);
     }
+    save_audio(music);
 }
 
 pub(crate) fn toggle_sfx(music: &mut Option<audio::MusicPlayer>) {
@@ -349,6 +448,25 @@ pub(crate) fn toggle_sfx(music: &mut Option<audio::MusicPlayer>) {
         m.toggle_sfx();
         m.play_sfx(Sfx::MenuMove);
     }
+    save_audio(music);
+}
+
+/// Write the audio toggles to disk as soon as they change, on top of the
+/// settings already saved there. Toggles made mid-game would otherwise be
+/// lost if the game is never left through a menu that saves.
+fn save_audio(music: &Option<audio::MusicPlayer>) {
+    if music.is_some() {
+        save_settings(&mut Settings::load(), music);
+    }
+}
+
+/// Copy the current audio toggles into `settings` and write them to disk.
+pub(crate) fn save_settings(settings: &mut Settings, music: &Option<audio::MusicPlayer>) {
+    if let Some(m) = music.as_ref() {
+        settings.bgm = m.bgm_enabled();
+        settings.sfx = m.sfx_enabled();
+    }
+    settings.save();
 }
 
 pub(crate) fn menu_nav(sel: usize, count: usize, code: KeyCode) -> usize {
@@ -0,0 +1,267 @@
+use crossterm::event::KeyCode;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::game::records::data_dir;
+
+const MAX_KEYS_PER_ACTION: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateCw,
+    RotateCcw,
+    Rotate180,
+    Hold,
+    Pause,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::SoftDrop,
+        Action::HardDrop,
+        Action::RotateCw,
+        Action::RotateCcw,
+        Action::Rotate180,
+        Action::Hold,
+        Action::Pause,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "Left",
+            Action::MoveRight => "Right",
+            Action::SoftDrop => "Soft drop",
+            Action::HardDrop => "Hard drop",
+            Action::RotateCw => "Rotate CW",
+            Action::RotateCcw => "Rotate CCW",
+            Action::Rotate180 => "Rotate 180",
+            Action::Hold => "Hold",
+            Action::Pause => "Pause",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Keymap {
+    #[serde(with = "key_names")]
+    pub move_left: Vec<KeyCode>,
+    #[serde(with = "key_names")]
+    pub move_right: Vec<KeyCode>,
+    #[serde(with = "key_names")]
+    pub soft_drop: Vec<KeyCode>,
+    #[serde(with = "key_names")]
+    pub hard_drop: Vec<KeyCode>,
+    #[serde(with = "key_names")]
+    pub rotate_cw: Vec<KeyCode>,
+    #[serde(with = "key_names")]
+    pub rotate_ccw: Vec<KeyCode>,
+    #[serde(with = "key_names")]
+    pub rotate_180: Vec<KeyCode>,
+    #[serde(with = "key_names")]
+    pub hold: Vec<KeyCode>,
+    #[serde(with = "key_names")]
+    pub pause: Vec<KeyCode>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            move_left: vec![KeyCode::Left],
+            move_right: vec![KeyCode::Right],
+            soft_drop: vec![KeyCode::Down],
+            hard_drop: vec![KeyCode::Char(' ')],
+            rotate_cw: vec![KeyCode::Up, KeyCode::Char('x')],
+            rotate_ccw: vec![KeyCode::Char('z')],
+            rotate_180: vec![KeyCode::Char('a')],
+            hold: vec![KeyCode::Char('c')],
+            pause: vec![KeyCode::Char('p')],
+        }
+    }
+}
+
+fn normalize(code: KeyCode) -> KeyCode {
+    match code {
+        KeyCode::Char(c) => KeyCode::Char(c.to_ascii_lowercase()),
+        other => other,
+    }
+}
+
+fn keymap_path() -> PathBuf {
+    let mut path = data_dir();
+    path.push("keymap.json");
+    path
+}
+
+impl Keymap {
+    pub fn load() -> Self {
+        let path = keymap_path();
+        match fs::read_to_string(&path) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_default(),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) {
+        let path = keymap_path();
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(data) = serde_json::to_string_pretty(self) {
+            let _ = fs::write(&path, data);
+        }
+    }
+
+    pub fn keys(&self, action: Action) -> &Vec<KeyCode> {
+        match action {
+            Action::MoveLeft => &self.move_left,
+            Action::MoveRight => &self.move_right,
+            Action::SoftDrop => &self.soft_drop,
+            Action::HardDrop => &self.hard_drop,
+            Action::RotateCw => &self.rotate_cw,
+            Action::RotateCcw => &self.rotate_ccw,
+            Action::Rotate180 => &self.rotate_180,
+            Action::Hold => &self.hold,
+            Action::Pause => &self.pause,
+        }
+    }
+
+    fn keys_mut(&mut self, action: Action) -> &mut Vec<KeyCode> {
+        match action {
+            Action::MoveLeft => &mut self.move_left,
+            Action::MoveRight => &mut self.move_right,
+            Action::SoftDrop => &mut self.soft_drop,
+            Action::HardDrop => &mut self.hard_drop,
+            Action::RotateCw => &mut self.rotate_cw,
+            Action::RotateCcw => &mut self.rotate_ccw,
+            Action::Rotate180 => &mut self.rotate_180,
+            Action::Hold => &mut self.hold,
+            Action::Pause => &mut self.pause,
+        }
+    }
+
+    pub fn action_for(&self, code: KeyCode) -> Option<Action> {
+        let code = normalize(code);
+        Action::ALL
+            .into_iter()
+            .find(|&a| self.keys(a).contains(&code))
+    }
+
+    /// Esc always pauses so the game can never be left without a way out.
+    pub fn is_pause(&self, code: KeyCode) -> bool {
+        code == KeyCode::Esc || self.action_for(code) == Some(Action::Pause)
+    }
+
+    /// Add `code` to `action`, taking it away from any other action first.
+    /// The oldest binding is dropped once an action has too many keys.
+    pub fn bind(&mut self, action: Action, code: KeyCode) {
+        let code = normalize(code);
+        for a in Action::ALL {
+            self.keys_mut(a).retain(|&k| k != code);
+        }
+        let keys = self.keys_mut(action);
+        keys.push(code);
+        if keys.len() > MAX_KEYS_PER_ACTION {
+            keys.remove(0);
+        }
+    }
+
+    pub fn clear(&mut self, action: Action) {
+        self.keys_mut(action).clear();
+    }
+
+    pub fn label(&self, action: Action) -> String {
+        let keys = self.keys(action);
+        if keys.is_empty() {
+            return "-".to_string();
+        }
+        keys.iter().map(|&k| key_label(k)).collect::<Vec<_>>().join("/")
+    }
+}
+
+pub fn key_label(code: KeyCode) -> String {
+    match code {
+        KeyCode::Left => "←".to_string(),
+        KeyCode::Right => "→".to_string(),
+        KeyCode::Up => "↑".to_string(),
+        KeyCode::Down => "↓".to_string(),
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_ascii_uppercase().to_string(),
+        other => key_name(other),
+    }
+}
+
+fn key_name(code: KeyCode) -> String {
+    match code {
+        KeyCode::Left => "Left".to_string(),
+        KeyCode::Right => "Right".to_string(),
+        KeyCode::Up => "Up".to_string(),
+        KeyCode::Down => "Down".to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Tab => "Tab".to_string(),
+        KeyCode::Backspace => "Backspace".to_string(),
+        KeyCode::Delete => "Delete".to_string(),
+        KeyCode::Insert => "Insert".to_string(),
+        KeyCode::Home => "Home".to_string(),
+        KeyCode::End => "End".to_string(),
+        KeyCode::PageUp => "PageUp".to_string(),
+        KeyCode::PageDown => "PageDown".to_string(),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        _ => "?".to_string(),
+    }
+}
+
+fn parse_key_name(name: &str) -> Option<KeyCode> {
+    let code = match name {
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "Enter" => KeyCode::Enter,
+        "Tab" => KeyCode::Tab,
+        "Backspace" => KeyCode::Backspace,
+        "Delete" => KeyCode::Delete,
+        "Insert" => KeyCode::Insert,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "PageUp" => KeyCode::PageUp,
+        "PageDown" => KeyCode::PageDown,
+        "Esc" => KeyCode::Esc,
+        "Space" => KeyCode::Char(' '),
+        _ => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c.to_ascii_lowercase()),
+                (Some('F'), Some(_)) => KeyCode::F(name[1..].parse().ok()?),
+                _ => return None,
+            }
+        }
+    };
+    Some(code)
+}
+
+/// Keys are stored by name so the file stays readable and hand-editable.
+mod key_names {
+    use crossterm::event::KeyCode;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(keys: &[KeyCode], s: S) -> Result<S::Ok, S::Error> {
+        let names: Vec<String> = keys.iter().map(|&k| super::key_name(k)).collect();
+        names.serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<KeyCode>, D::Error> {
+        let names: Vec<String> = Vec::deserialize(d)?;
+        Ok(names.iter().filter_map(|n| super::parse_key_name(n)).collect())
+    }
+}
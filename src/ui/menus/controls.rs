@@ -0,0 +1,65 @@
+use crossterm::event::KeyCode;
+use std::io;
+
+use crate::audio::{self, Sfx};
+use crate::render;
+use crate::ui::keymap::{Action, Keymap};
+use crate::ui::{menu_nav, play_menu_sfx, read_key};
+
+pub(crate) fn run_controls(
+    stdout: &mut io::Stdout,
+    music: &mut Option<audio::MusicPlayer>,
+    keymap: &mut Keymap,
+) -> io::Result<()> {
+    let idx_reset = Action::ALL.len();
+    let idx_back = idx_reset + 1;
+    let count = idx_back + 1;
+    let mut sel: usize = 0;
+
+    loop {
+        render::draw_controls(stdout, keymap, sel, false)?;
+        if let Some(code) = read_key()? {
+            match code {
+                KeyCode::Up | KeyCode::Down => {
+                    sel = menu_nav(sel, count, code);
+                    play_menu_sfx(music, Sfx::MenuMove);
+                }
+                KeyCode::Enter if sel < idx_reset => {
+                    play_menu_sfx(music, Sfx::MenuSelect);
+                    render::draw_controls(stdout, keymap, sel, true)?;
+                    let key = loop {
+                        if let Some(k) = read_key()? {
+                            break k;
+                        }
+                    };
+                    if key == KeyCode::Esc {
+                        play_menu_sfx(music, Sfx::MenuBack);
+                    } else {
+                        keymap.bind(Action::ALL[sel], key);
+                        keymap.save();
+                        play_menu_sfx(music, Sfx::MenuMove);
+                    }
+                }
+                KeyCode::Backspace | KeyCode::Delete if sel < idx_reset => {
+                    keymap.clear(Action::ALL[sel]);
+                    keymap.save();
+                    play_menu_sfx(music, Sfx::MenuMove);
+                }
+                KeyCode::Enter if sel == idx_reset => {
+                    *keymap = Keymap::default();
+                    keymap.save();
+                    play_menu_sfx(music, Sfx::MenuSelect);
+                }
+                KeyCode::Enter if sel == idx_back => {
+                    play_menu_sfx(music, Sfx::MenuBack);
+                    return Ok(());
+                }
+                KeyCode::Esc => {
+                    play_menu_sfx(music, Sfx::MenuBack);
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+    }
+}
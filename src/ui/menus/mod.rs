@@ -1,7 +1,11 @@
+mod controls;
 mod modes;
+mod replays;
 mod settings;
 mod versus;
 
+pub(crate) use controls::run_controls;
 pub(super) use modes::select_mode;
-pub(crate) use settings::run_settings;
+pub(crate) use replays::run_replays;
+pub(crate) use settings::{cycle, run_settings};
 pub(super) use versus::{run_versus_menu, VersusAction};
@@ -6,19 +6,21 @@ use crate::game::GameMode;
 use crate::game::records::Records;
 use crate::render;
 use crate::game::settings::Settings;
+use crate::ui::keymap::Keymap;
 use crate::ui::{menu_nav, play_menu_sfx, read_key};
 
-use super::run_settings;
+use super::{run_replays, run_settings};
 
 pub fn select_mode(
     stdout: &mut io::Stdout,
     music: &mut Option<audio::MusicPlayer>,
     settings: &mut Settings,
     records: &mut Records,
+    keymap: &mut Keymap,
 ) -> io::Result<Option<GameMode>> {
     let mut mode = GameMode::Marathon;
     let mut sel: usize = 0;
-    let count: usize = 6;
+    let count: usize = 7;
 
     loop {
         render::draw_mode_select(stdout, mode, sel)?;
@@ -43,13 +45,16 @@ pub fn select_mode(
                         return Ok(Some(mode));
                     } else if sel == 2 {
                         play_menu_sfx(music, Sfx::MenuSelect);
-                        run_settings(stdout, music, settings, mode, false)?;
+                        run_settings(stdout, music, settings, keymap, mode, false)?;
                     } else if sel == 3 {
                         play_menu_sfx(music, Sfx::MenuSelect);
                         run_records(stdout, music, records, mode)?;
                     } else if sel == 4 {
                         play_menu_sfx(music, Sfx::MenuSelect);
-                        render::draw_help(stdout, 0)?;
+                        run_replays(stdout, music)?;
+                    } else if sel == 5 {
+                        play_menu_sfx(music, Sfx::MenuSelect);
+                        render::draw_help(stdout, keymap, 0)?;
                         loop {
                             if let Some(code) = read_key()? {
                                 if code == KeyCode::Enter || code == KeyCode::Esc {
@@ -58,7 +63,7 @@ pub fn select_mode(
                                 }
                             }
                         }
-                    } else if sel == 5 {
+                    } else if sel == 6 {
                         return Ok(None);
                     }
                 }
@@ -75,37 +80,29 @@ fn run_records(
     initial_mode: GameMode,
 ) -> io::Result<()> {
     let mut mode = initial_mode;
-    let mut sel: usize = 1;
+    let mut sel: usize = records.list(mode).len() + 1;
 
     loop {
+        // The mode switcher, then one row per record, then Back.
+        let count = records.list(mode).len() + 2;
         render::draw_records(stdout, records, mode, sel)?;
         if let Some(code) = read_key()? {
             match code {
-                KeyCode::Up => {
-                    sel = sel.checked_sub(1).unwrap_or(1);
+                KeyCode::Up | KeyCode::Down => {
+                    sel = menu_nav(sel, count, code);
                     play_menu_sfx(music, Sfx::MenuMove);
                 }
-                KeyCode::Down => {
-                    sel = 1;
+                KeyCode::Left if sel == 0 => {
+                    mode = mode.prev();
                     play_menu_sfx(music, Sfx::MenuMove);
                 }
-                KeyCode::Left => {
-                    if sel == 0 {
-                        mode = mode.prev();
-                        play_menu_sfx(music, Sfx::MenuMove);
-                    }
-                }
-                KeyCode::Right => {
-                    if sel == 0 {
-                        mode = mode.next();
-                        play_menu_sfx(music, Sfx::MenuMove);
-                    }
+                KeyCode::Right if sel == 0 => {
+                    mode = mode.next();
+                    play_menu_sfx(music, Sfx::MenuMove);
                 }
-                KeyCode::Enter => {
-                    if sel == 1 {
-                        play_menu_sfx(music, Sfx::MenuBack);
-                        return Ok(());
-                    }
+                KeyCode::Enter if sel == count - 1 => {
+                    play_menu_sfx(music, Sfx::MenuBack);
+                    return Ok(());
                 }
                 KeyCode::Esc => {
                     play_menu_sfx(music, Sfx::MenuBack);
@@ -0,0 +1,44 @@
+use crossterm::event::KeyCode;
+use std::io;
+
+use crate::audio::{self, Sfx};
+use crate::game::replay::Replay;
+use crate::render;
+use crate::ui::replay::run_replay;
+use crate::ui::{menu_nav, play_menu_sfx, read_key};
+
+pub(crate) fn run_replays(
+    stdout: &mut io::Stdout,
+    music: &mut Option<audio::MusicPlayer>,
+) -> io::Result<()> {
+    let replays = Replay::load_all();
+    let mut sel: usize = 0;
+
+    loop {
+        let count = replays.len() + 1;
+        render::draw_replays(stdout, &replays, sel)?;
+        if let Some(code) = read_key()? {
+            match code {
+                KeyCode::Up | KeyCode::Down => {
+                    sel = menu_nav(sel, count, code);
+                    play_menu_sfx(music, Sfx::MenuMove);
+                }
+                KeyCode::Enter => {
+                    if sel == replays.len() {
+                        play_menu_sfx(music, Sfx::MenuBack);
+                        return Ok(());
+                    }
+                    play_menu_sfx(music, Sfx::MenuSelect);
+                    if run_replay(stdout, music, &replays[sel])? {
+                        return Ok(());
+                    }
+                }
+                KeyCode::Esc => {
+                    play_menu_sfx(music, Sfx::MenuBack);
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+    }
+}
@@ -4,9 +4,14 @@ use std::io;
 use crate::audio::{self, Sfx};
 use crate::game::GameMode;
 use crate::game::piece::MAX_NEXT_COUNT;
+use crate::game::randomizer::RandomizerKind;
+use crate::game::rotation::RotationSystem;
 use crate::render;
 use crate::game::settings::Settings;
-use crate::ui::{menu_nav, play_menu_sfx, read_key, toggle_bgm, toggle_sfx};
+use crate::ui::keymap::Keymap;
+use crate::ui::{menu_nav, play_menu_sfx, read_key, save_settings, toggle_bgm, toggle_sfx};
+
+use super::run_controls;
 
 fn adjust_level_cap(settings: &mut Settings, direction: i32) {
     match (settings.level_cap, direction) {
@@ -18,6 +23,25 @@ fn adjust_level_cap(settings: &mut Settings, direction: i32) {
     }
 }
 
+/// Step through `options` with wrap-around; Enter (direction 0) moves forward like Right.
+pub(crate) fn cycle<T: Copy + PartialEq>(options: &[T], current: T, direction: i32) -> T {
+    let idx = options.iter().position(|&o| o == current).unwrap_or(0) as i32;
+    let step = if direction == 0 { 1 } else { direction };
+    options[(idx + step).rem_euclid(options.len() as i32) as usize]
+}
+
+const SOFT_DROP_FACTORS: [u32; 6] = [1, 2, 5, 10, 20, 40];
+
+fn adjust_soft_drop_factor(settings: &mut Settings, direction: i32) {
+    let last = SOFT_DROP_FACTORS.len();
+    let idx = match settings.soft_drop_factor {
+        Some(n) => SOFT_DROP_FACTORS.iter().position(|&f| f >= n).unwrap_or(last - 1),
+        None => last,
+    };
+    let idx = (idx as i32 + direction).clamp(0, last as i32) as usize;
+    settings.soft_drop_factor = SOFT_DROP_FACTORS.get(idx).copied();
+}
+
 fn adjust_setting(settings: &mut Settings, sel: usize, direction: i32, mode: GameMode) {
     let mc = mode.setting_count();
 
@@ -80,11 +104,24 @@ fn adjust_setting(settings: &mut Settings, sel: usize, direction: i32, mode: Gam
     } else if sel == mc + 4 {
         settings.line_clear_anim = !settings.line_clear_anim;
     } else if sel == mc + 5 {
-        settings.bag_randomizer = !settings.bag_randomizer;
+        settings.randomizer = cycle(&RandomizerKind::ALL, settings.randomizer, direction);
     } else if sel == mc + 6 {
-        settings.srs_enabled = !settings.srs_enabled;
+        settings.rotation = cycle(&RotationSystem::ALL, settings.rotation, direction);
     } else if sel == mc + 7 {
         settings.hold_enabled = !settings.hold_enabled;
+    } else if sel == mc + 8 {
+        settings.rotate_180 = !settings.rotate_180;
+    } else if sel == mc + 9 {
+        let v = settings.das_ms as i32 + direction * 10;
+        settings.das_ms = v.clamp(0, 500) as u32;
+    } else if sel == mc + 10 {
+        let v = settings.arr_ms as i32 + direction * 5;
+        settings.arr_ms = v.clamp(0, 100) as u32;
+    } else if sel == mc + 11 {
+        let v = settings.das_release_ms as i32 + direction * 10;
+        settings.das_release_ms = v.clamp(30, 500) as u32;
+    } else if sel == mc + 12 {
+        adjust_soft_drop_factor(settings, direction);
     }
 }
 
@@ -92,6 +129,20 @@ pub(crate) fn run_settings(
     stdout: &mut io::Stdout,
     music: &mut Option<audio::MusicPlayer>,
     settings: &mut Settings,
+    keymap: &mut Keymap,
+    mode: GameMode,
+    in_game: bool,
+) -> io::Result<()> {
+    let result = settings_loop(stdout, music, settings, keymap, mode, in_game);
+    save_settings(settings, music);
+    result
+}
+
+fn settings_loop(
+    stdout: &mut io::Stdout,
+    music: &mut Option<audio::MusicPlayer>,
+    settings: &mut Settings,
+    keymap: &mut Keymap,
     mode: GameMode,
     in_game: bool,
 ) -> io::Result<()> {
@@ -136,13 +187,14 @@ pub(crate) fn run_settings(
     }
 
     let mc = mode.setting_count();
-    let count = mc + 11;
-    let idx_bgm = mc + 8;
-    let idx_sfx = mc + 9;
-    let idx_back = mc + 10;
+    let count = mc + 17;
+    let idx_bgm = mc + 13;
+    let idx_sfx = mc + 14;
+    let idx_controls = mc + 15;
+    let idx_back = mc + 16;
 
     let is_toggle = |s: usize| -> bool {
-        s > mc + 2 && s <= mc + 7
+        s > mc + 2 && s <= mc + 8
     };
 
     loop {
@@ -176,6 +228,9 @@ pub(crate) fn run_settings(
                         toggle_bgm(music);
                     } else if sel == idx_sfx {
                         toggle_sfx(music);
+                    } else if sel == idx_controls {
+                        play_menu_sfx(music, Sfx::MenuSelect);
+                        run_controls(stdout, music, keymap)?;
                     } else if sel == idx_back {
                         play_menu_sfx(music, Sfx::MenuBack);
                         return Ok(());
@@ -1,17 +1,26 @@
-use crossterm::event::KeyCode;
+use crossterm::event::{self, KeyCode};
 use crossterm::style::{Color, Stylize};
 use crossterm::{cursor, execute};
 use std::io;
+use std::time::Duration;
 
 use crate::audio::{self, Sfx};
+use crate::game::bot::BotDifficulty;
 use crate::game::piece::BOARD_WIDTH;
 use crate::render;
 use crate::game::settings::Settings;
+use crate::net::discovery::{FoundHost, HostFinder};
 use crate::ui::{menu_nav, play_menu_sfx, read_key};
 
+use super::settings::cycle;
+
 pub enum VersusAction {
     Host(u16),
     Join(String),
+    Watch(String),
+    HostRoom(u16),
+    JoinRoom(String),
+    Cpu(BotDifficulty),
     Back,
 }
 
@@ -24,6 +33,7 @@ enum InputResult {
 fn draw_versus_menu(
     stdout: &mut io::Stdout,
     selected: usize,
+    difficulty: BotDifficulty,
 ) -> io::Result<()> {
     execute!(stdout, cursor::MoveTo(0, 0))?;
     render::draw_title(stdout)?;
@@ -36,14 +46,79 @@ fn draw_versus_menu(
         None,
         Some(render::menu_item("Host Game", selected == 0, inner_w)),
         Some(render::menu_item("Join Game", selected == 1, inner_w)),
+        Some(render::menu_item("Watch Game", selected == 2, inner_w)),
+        Some(render::menu_item("Host Room", selected == 3, inner_w)),
+        Some(render::menu_item("Join Room", selected == 4, inner_w)),
+        Some(render::menu_item("vs CPU", selected == 5, inner_w)),
+        Some(render::settings_value("CPU", difficulty.label(), selected == 6, inner_w)),
         None,
-        Some(render::menu_item("Back", selected == 2, inner_w)),
+        Some(render::menu_item("Back", selected == 7, inner_w)),
         None,
     ];
 
     render::draw_full_board_overlay(stdout, &content)
 }
 
+/// Discovered hosts shown at once; the rest wait for a free slot.
+const MAX_LISTED_HOSTS: usize = 6;
+const HOST_NAME_W: usize = 11;
+
+fn host_label(host: &FoundHost) -> String {
+    let name: String = host.beacon.name.chars().take(HOST_NAME_W).collect();
+    format!("{} :{}", name, host.beacon.port)
+}
+
+fn draw_host_browser(
+    stdout: &mut io::Stdout,
+    title: &str,
+    hosts: &[&FoundHost],
+    searching: bool,
+    error: &str,
+    selected: usize,
+) -> io::Result<()> {
+    execute!(stdout, cursor::MoveTo(0, 0))?;
+    render::draw_title(stdout)?;
+
+    let inner_w = BOARD_WIDTH * 2;
+
+    let mut content: Vec<Option<String>> = vec![
+        None,
+        Some(format!("{:^width$}", title, width = inner_w)),
+        None,
+    ];
+
+    if hosts.is_empty() {
+        let status = if searching { "Searching LAN..." } else { "LAN search off" };
+        content.push(Some(format!("{:^width$}", status, width = inner_w)));
+    }
+    for (i, host) in hosts.iter().enumerate() {
+        let label = host_label(host);
+        if host.compatible() {
+            content.push(Some(render::menu_item(&label, selected == i, inner_w)));
+        } else {
+            content.push(Some(render::centered_line(&label, selected == i, inner_w, true)));
+        }
+    }
+
+    content.push(None);
+    content.push(Some(render::menu_item("Enter IP", selected == hosts.len(), inner_w)));
+    content.push(Some(render::menu_item("Back", selected == hosts.len() + 1, inner_w)));
+    content.push(None);
+
+    if !error.is_empty() {
+        content.push(Some(format!(
+            "{}",
+            format!("{:^width$}", error, width = inner_w).as_str().with(Color::Red)
+        )));
+    } else if let Some(host) = hosts.get(selected) {
+        content.push(Some(format!("{:^width$}", host.addr.ip().to_string(), width = inner_w)));
+        let detail = format!("Level {}", host.beacon.settings.level);
+        content.push(Some(format!("{:^width$}", detail, width = inner_w)));
+    }
+
+    render::draw_full_board_overlay(stdout, &content)
+}
+
 fn draw_input_screen(
     stdout: &mut io::Stdout,
     title: &str,
@@ -93,13 +168,13 @@ fn draw_input_screen(
 pub fn run_versus_menu(
     stdout: &mut io::Stdout,
     music: &mut Option<audio::MusicPlayer>,
-    _settings: &mut Settings,
+    settings: &mut Settings,
 ) -> io::Result<VersusAction> {
     let mut sel: usize = 0;
-    let count: usize = 3;
+    let count: usize = 8;
 
     loop {
-        draw_versus_menu(stdout, sel)?;
+        draw_versus_menu(stdout, sel, settings.bot_difficulty)?;
 
         if let Some(code) = read_key()? {
             match code {
@@ -107,10 +182,15 @@ pub fn run_versus_menu(
                     sel = menu_nav(sel, count, code);
                     play_menu_sfx(music, Sfx::MenuMove);
                 }
+                KeyCode::Left | KeyCode::Right if sel == 6 => {
+                    let direction = if code == KeyCode::Left { -1 } else { 1 };
+                    settings.bot_difficulty = cycle(&BotDifficulty::ALL, settings.bot_difficulty, direction);
+                    play_menu_sfx(music, Sfx::MenuMove);
+                }
                 KeyCode::Enter => match sel {
                     0 => {
                         play_menu_sfx(music, Sfx::MenuSelect);
-                        match run_port_input(stdout, music)? {
+                        match run_port_input(stdout, music, "HOST GAME")? {
                             InputResult::Confirm(port) => {
                                 if let Ok(p) = port.parse::<u16>() {
                                     return Ok(VersusAction::Host(p));
@@ -122,13 +202,45 @@ pub fn run_versus_menu(
                     }
                     1 => {
                         play_menu_sfx(music, Sfx::MenuSelect);
-                        match run_addr_input(stdout, music)? {
+                        match run_host_browser(stdout, music, "JOIN GAME", false)? {
                             InputResult::Confirm(addr) => return Ok(VersusAction::Join(addr)),
                             InputResult::Back => continue,
                             InputResult::Cancel => return Ok(VersusAction::Back),
                         }
                     }
                     2 => {
+                        play_menu_sfx(music, Sfx::MenuSelect);
+                        match run_host_browser(stdout, music, "WATCH GAME", false)? {
+                            InputResult::Confirm(addr) => return Ok(VersusAction::Watch(addr)),
+                            InputResult::Back => continue,
+                            InputResult::Cancel => return Ok(VersusAction::Back),
+                        }
+                    }
+                    3 => {
+                        play_menu_sfx(music, Sfx::MenuSelect);
+                        match run_port_input(stdout, music, "HOST ROOM")? {
+                            InputResult::Confirm(port) => {
+                                if let Ok(p) = port.parse::<u16>() {
+                                    return Ok(VersusAction::HostRoom(p));
+                                }
+                            }
+                            InputResult::Back => continue,
+                            InputResult::Cancel => return Ok(VersusAction::Back),
+                        }
+                    }
+                    4 => {
+                        play_menu_sfx(music, Sfx::MenuSelect);
+                        match run_host_browser(stdout, music, "JOIN ROOM", true)? {
+                            InputResult::Confirm(addr) => return Ok(VersusAction::JoinRoom(addr)),
+                            InputResult::Back => continue,
+                            InputResult::Cancel => return Ok(VersusAction::Back),
+                        }
+                    }
+                    5 | 6 => {
+                        play_menu_sfx(music, Sfx::MenuSelect);
+                        return Ok(VersusAction::Cpu(settings.bot_difficulty));
+                    }
+                    7 => {
                         play_menu_sfx(music, Sfx::MenuBack);
                         return Ok(VersusAction::Back);
                     }
@@ -170,11 +282,9 @@ fn run_text_input(
                     sel = menu_nav(sel, count, code);
                     play_menu_sfx(music, Sfx::MenuMove);
                 }
-                KeyCode::Char(c) if sel == 0 && char_filter(c) => {
-                    if input.len() < max_len {
-                        input.push(c);
-                        error.clear();
-                    }
+                KeyCode::Char(c) if sel == 0 && char_filter(c) && input.len() < max_len => {
+                    input.push(c);
+                    error.clear();
                 }
                 KeyCode::Backspace if sel == 0 => {
                     input.pop();
@@ -212,11 +322,12 @@ fn run_text_input(
 fn run_port_input(
     stdout: &mut io::Stdout,
     music: &mut Option<audio::MusicPlayer>,
+    title: &str,
 ) -> io::Result<InputResult> {
     run_text_input(
         stdout,
         music,
-        "HOST GAME",
+        title,
         "Port",
         "21711",
         5,
@@ -229,15 +340,78 @@ fn run_port_input(
     )
 }
 
+/// Lists the hosts announcing themselves on the LAN. Typing an address is
+/// kept as a fallback for when broadcasts do not get through. `room` picks
+/// battle royale rooms over 1v1 games.
+fn run_host_browser(
+    stdout: &mut io::Stdout,
+    music: &mut Option<audio::MusicPlayer>,
+    title: &str,
+    room: bool,
+) -> io::Result<InputResult> {
+    let mut finder = HostFinder::new().ok();
+    let mut sel: usize = 0;
+    let mut error = String::new();
+
+    loop {
+        if let Some(f) = finder.as_mut() {
+            f.poll();
+        }
+        let hosts: Vec<&FoundHost> = finder
+            .iter()
+            .flat_map(|f| f.hosts())
+            .filter(|h| h.beacon.room == room)
+            .take(MAX_LISTED_HOSTS)
+            .collect();
+        let count = hosts.len() + 2;
+        sel = sel.min(count - 1);
+        draw_host_browser(stdout, title, &hosts, finder.is_some(), &error, sel)?;
+
+        if !event::poll(Duration::from_millis(100))? {
+            continue;
+        }
+        let Some(code) = read_key()? else {
+            continue;
+        };
+        match code {
+            KeyCode::Up | KeyCode::Down => {
+                sel = menu_nav(sel, count, code);
+                error.clear();
+                play_menu_sfx(music, Sfx::MenuMove);
+            }
+            KeyCode::Enter if sel < hosts.len() => {
+                if hosts[sel].compatible() {
+                    play_menu_sfx(music, Sfx::MenuSelect);
+                    return Ok(InputResult::Confirm(hosts[sel].addr.to_string()));
+                }
+                error = "Version mismatch".to_string();
+            }
+            KeyCode::Enter if sel == hosts.len() => {
+                play_menu_sfx(music, Sfx::MenuSelect);
+                match run_addr_input(stdout, music, title)? {
+                    InputResult::Back => continue,
+                    other => return Ok(other),
+                }
+            }
+            KeyCode::Enter | KeyCode::Esc => {
+                play_menu_sfx(music, Sfx::MenuBack);
+                return Ok(InputResult::Back);
+            }
+            _ => {}
+        }
+    }
+}
+
 fn run_addr_input(
     stdout: &mut io::Stdout,
     music: &mut Option<audio::MusicPlayer>,
+    title: &str,
 ) -> io::Result<InputResult> {
     'ip: loop {
         let ip = match run_text_input(
             stdout,
             music,
-            "JOIN GAME",
+            title,
             "IP Address",
             "127.0.0.1",
             15,
@@ -263,7 +437,7 @@ fn run_addr_input(
         match run_text_input(
             stdout,
             music,
-            "JOIN GAME",
+            title,
             "Port",
             "21711",
             5,
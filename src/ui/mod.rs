@@ -1,8 +1,16 @@
 mod app;
+mod cpu;
 pub mod input;
+pub mod keymap;
 mod menus;
+mod replay;
+mod royale;
 mod session;
+mod spectate;
 mod versus;
 
+#[cfg(test)]
+mod tests;
+
 pub use app::run_app;
-pub(crate) use input::{menu_nav, play_menu_sfx, read_key, toggle_bgm, toggle_sfx};
+pub(crate) use input::{menu_nav, play_menu_sfx, read_key, save_settings, toggle_bgm, toggle_sfx};
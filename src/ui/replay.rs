@@ -0,0 +1,100 @@
+use crossterm::{
+    event::{self, KeyCode},
+    execute,
+    terminal,
+};
+use std::io;
+use std::time::Duration;
+
+use crate::audio::{self, Sfx};
+use crate::game::Game;
+use crate::game::replay::Replay;
+use crate::render;
+
+use super::{menu_nav, play_menu_sfx, read_key};
+
+/// Play a recording back through a fresh `Game` built from its seed and settings.
+/// Returns true if the user asked to go back to the main menu.
+pub fn run_replay(
+    stdout: &mut io::Stdout,
+    music: &mut Option<audio::MusicPlayer>,
+    replay: &Replay,
+) -> io::Result<bool> {
+    loop {
+        let mut game = Game::new(replay.mode, &replay.settings, replay.seed);
+        let mut next_event = 0;
+        if let Some(m) = music.as_mut() {
+            m.play();
+        }
+        execute!(stdout, terminal::Clear(terminal::ClearType::All))?;
+
+        loop {
+            game.update_elapsed();
+            let now_ms = game.elapsed.as_millis() as u64;
+            while let Some(ev) = replay.events.get(next_event) {
+                if ev.at_ms > now_ms {
+                    break;
+                }
+                game.apply_input(ev.input);
+                next_event += 1;
+            }
+
+            if game.is_animating() {
+                game.update_animation();
+            }
+
+            render::draw(stdout, &game)?;
+
+            if next_event >= replay.events.len() {
+                break;
+            }
+
+            if event::poll(Duration::from_millis(16))? {
+                if let Some(KeyCode::Esc) = read_key()? {
+                    if let Some(m) = music.as_mut() {
+                        m.play_sfx(Sfx::MenuBack);
+                        m.stop();
+                    }
+                    return Ok(false);
+                }
+            }
+        }
+
+        game.game_over = true;
+        game.elapsed = Duration::from_millis(replay.time);
+        if let Some(m) = music.as_mut() {
+            m.stop();
+            if game.cleared {
+                m.play_sfx(Sfx::Clear);
+            } else {
+                m.play_sfx(Sfx::GameOver);
+            }
+        }
+
+        let mut sel: usize = 0;
+        let count: usize = 2;
+        loop {
+            render::draw_game_over(stdout, &game, sel, None)?;
+            if let Some(code) = read_key()? {
+                match code {
+                    KeyCode::Up | KeyCode::Down => {
+                        sel = menu_nav(sel, count, code);
+                        play_menu_sfx(music, Sfx::MenuMove);
+                    }
+                    KeyCode::Enter => {
+                        play_menu_sfx(music, Sfx::MenuSelect);
+                        if sel == 0 {
+                            break;
+                        }
+                        return Ok(true);
+                    }
+                    KeyCode::Esc => {
+                        play_menu_sfx(music, Sfx::MenuBack);
+                        return Ok(false);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,490 @@
+use crossterm::event::{self, KeyCode};
+use crossterm::{execute, terminal};
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::audio::{self, Sfx};
+use crate::game::garbage::{GarbageEvent, GarbageQueue, HoleGenerator};
+use crate::game::rng::random_seed;
+use crate::game::royale::{PlayerId, TargetStrategy, HOST_ID, MAX_PLAYERS, MIN_PLAYERS};
+use crate::game::settings::Settings;
+use crate::game::{Game, GameMode};
+use crate::net::discovery::{Beacon, BeaconSender};
+use crate::net::room::{RoomEvent, RoomGuest, RoomLink, RoomLobby};
+use crate::net::spectate::SpectatorHub;
+use crate::net::transport::Transport;
+use crate::net::{BoardSnapshot, GarbageAttack, NetMessage};
+use crate::render;
+use crate::render::royale::MiniBoard;
+
+use super::input::{self, InputState};
+use super::keymap::Keymap;
+use super::menus::cycle;
+use super::versus::{follow_countdown, handle_forfeit_key, outgoing_attack, BOARD_SYNC_INTERVAL};
+use super::{menu_nav, play_menu_sfx, read_key};
+
+pub enum RoomLobbyResult {
+    /// The match is about to start with everyone in the roster.
+    Start(Box<dyn RoomLink>, Vec<PlayerId>, Settings),
+    Back,
+    Menu,
+}
+
+fn player_name(id: PlayerId) -> String {
+    format!("P{}", id + 1)
+}
+
+fn target_item(strategy: TargetStrategy) -> String {
+    format!("Target < {} >", strategy.label())
+}
+
+fn show_notice(
+    stdout: &mut io::Stdout,
+    music: &mut Option<audio::MusicPlayer>,
+    title: &str,
+    line: &str,
+) -> io::Result<()> {
+    execute!(stdout, terminal::Clear(terminal::ClearType::All))?;
+    loop {
+        render::versus::draw_lobby_screen(stdout, title, &[line], "", &["Back"], 0)?;
+        if let Some(KeyCode::Enter | KeyCode::Esc) = read_key()? {
+            play_menu_sfx(music, Sfx::MenuBack);
+            return Ok(());
+        }
+    }
+}
+
+fn draw_countdown_tick(
+    stdout: &mut io::Stdout,
+    music: &mut Option<audio::MusicPlayer>,
+    count: u8,
+) -> io::Result<()> {
+    render::versus::draw_versus_countdown(stdout, count, None)?;
+    play_menu_sfx(music, Sfx::MenuMove);
+    Ok(())
+}
+
+/// Seats players until the host starts the match, which needs at least
+/// `MIN_PLAYERS` including the host.
+pub fn run_room_host_lobby(
+    stdout: &mut io::Stdout,
+    music: &mut Option<audio::MusicPlayer>,
+    settings: &mut Settings,
+    port: u16,
+) -> io::Result<RoomLobbyResult> {
+    let listener = crate::net::host::listen_nonblocking(port)?;
+    let vs = *settings;
+    let mut lobby = RoomLobby::new(SpectatorHub::new(Some(listener), vs));
+    let mut beacon = BeaconSender::new(&Beacon::new(port, vs, true)).ok();
+
+    let addr_lines: Vec<String> = match crate::net::host::local_ip() {
+        Some(ip) => vec![format!("IP: {}", ip), format!("Port: {}", port)],
+        None => vec![format!("Port: {}", port)],
+    };
+
+    let mut sel: usize = 0;
+    let mut error = String::new();
+    loop {
+        if let Some(b) = beacon.as_mut() {
+            b.tick();
+        }
+
+        let players = format!("Players: {}/{}", lobby.player_count(), MAX_PLAYERS);
+        let lines: Vec<&str> = std::iter::once("Waiting...")
+            .chain(addr_lines.iter().map(|s| s.as_str()))
+            .chain(std::iter::once(players.as_str()))
+            .collect();
+        let target = target_item(settings.target_strategy);
+        render::versus::draw_lobby_screen(
+            stdout, "HOST ROOM", &lines, &error, &["Start", &target, "Back", "Menu"], sel,
+        )?;
+
+        if event::poll(Duration::from_millis(100))? {
+            if let Some(code) = read_key()? {
+                match code {
+                    KeyCode::Up | KeyCode::Down => {
+                        sel = menu_nav(sel, 4, code);
+                        play_menu_sfx(music, Sfx::MenuMove);
+                    }
+                    KeyCode::Left | KeyCode::Right | KeyCode::Enter if sel == 1 => {
+                        let direction = if code == KeyCode::Left { -1 } else { 1 };
+                        settings.target_strategy = cycle(&TargetStrategy::ALL, settings.target_strategy, direction);
+                        play_menu_sfx(music, Sfx::MenuMove);
+                    }
+                    KeyCode::Enter => match sel {
+                        0 if lobby.player_count() < MIN_PLAYERS => {
+                            error = format!("Need {} players", MIN_PLAYERS);
+                        }
+                        0 => {
+                            play_menu_sfx(music, Sfx::MenuSelect);
+                            let roster = lobby.roster();
+                            for count in (1..=3).rev() {
+                                lobby.broadcast(&NetMessage::Countdown(count));
+                                draw_countdown_tick(stdout, music, count)?;
+                                std::thread::sleep(Duration::from_secs(1));
+                            }
+                            lobby.broadcast(&NetMessage::GameStart);
+                            let host = lobby.start(settings.target_strategy, random_seed());
+                            return Ok(RoomLobbyResult::Start(Box::new(host), roster, vs));
+                        }
+                        2 => {
+                            play_menu_sfx(music, Sfx::MenuBack);
+                            return Ok(RoomLobbyResult::Back);
+                        }
+                        _ => {
+                            play_menu_sfx(music, Sfx::MenuBack);
+                            return Ok(RoomLobbyResult::Menu);
+                        }
+                    },
+                    KeyCode::Esc => {
+                        play_menu_sfx(music, Sfx::MenuBack);
+                        return Ok(RoomLobbyResult::Back);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if let Some(e) = lobby.poll() {
+            error = format!("{}", e);
+        }
+    }
+}
+
+/// Waits in a host's room until it starts the match.
+pub fn run_room_guest_lobby(
+    stdout: &mut io::Stdout,
+    music: &mut Option<audio::MusicPlayer>,
+    mut conn: Box<dyn Transport>,
+    vs: Settings,
+    settings: &mut Settings,
+) -> io::Result<RoomLobbyResult> {
+    let me = match conn.recv_blocking()? {
+        NetMessage::RoomJoined(id) => id,
+        NetMessage::Disconnect => {
+            show_notice(stdout, music, "JOIN ROOM", "Room is full")?;
+            return Ok(RoomLobbyResult::Back);
+        }
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "expected RoomJoined",
+            ));
+        }
+    };
+    conn.send(&NetMessage::SetTargeting(settings.target_strategy))?;
+
+    let you = format!("You: {}", player_name(me));
+    let mut roster: Vec<PlayerId> = Vec::new();
+    let mut sel: usize = 0;
+    loop {
+        let players = match roster.len() {
+            0 => format!("Players: -/{}", MAX_PLAYERS),
+            n => format!("Players: {}/{}", n, MAX_PLAYERS),
+        };
+        let target = target_item(settings.target_strategy);
+        render::versus::draw_lobby_screen(
+            stdout, "JOIN ROOM", &["Waiting for host...", &you, &players], "", &[&target, "Leave"], sel,
+        )?;
+
+        if event::poll(Duration::from_millis(50))? {
+            if let Some(code) = read_key()? {
+                match code {
+                    KeyCode::Up | KeyCode::Down => {
+                        sel = menu_nav(sel, 2, code);
+                        play_menu_sfx(music, Sfx::MenuMove);
+                    }
+                    KeyCode::Left | KeyCode::Right | KeyCode::Enter if sel == 0 => {
+                        let direction = if code == KeyCode::Left { -1 } else { 1 };
+                        settings.target_strategy = cycle(&TargetStrategy::ALL, settings.target_strategy, direction);
+                        let _ = conn.send(&NetMessage::SetTargeting(settings.target_strategy));
+                        play_menu_sfx(music, Sfx::MenuMove);
+                    }
+                    KeyCode::Enter | KeyCode::Esc => {
+                        play_menu_sfx(music, Sfx::MenuBack);
+                        let _ = conn.send(&NetMessage::Disconnect);
+                        return Ok(RoomLobbyResult::Back);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        loop {
+            match conn.try_recv() {
+                Ok(Some(NetMessage::RoomPlayers(players))) => roster = players,
+                Ok(Some(NetMessage::Countdown(count))) => {
+                    draw_countdown_tick(stdout, music, count)?;
+                    if !follow_countdown(&mut *conn, |n| draw_countdown_tick(stdout, music, n))? {
+                        show_notice(stdout, music, "JOIN ROOM", "Host left")?;
+                        return Ok(RoomLobbyResult::Back);
+                    }
+                    let guest = RoomGuest::new(me, conn);
+                    return Ok(RoomLobbyResult::Start(Box::new(guest), roster, vs));
+                }
+                Ok(Some(NetMessage::Disconnect)) => {
+                    show_notice(stdout, music, "JOIN ROOM", "Host left")?;
+                    return Ok(RoomLobbyResult::Back);
+                }
+                Ok(Some(_)) => {}
+                Ok(None) => break,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(_) => {
+                    show_notice(stdout, music, "JOIN ROOM", "Host left")?;
+                    return Ok(RoomLobbyResult::Back);
+                }
+            }
+        }
+    }
+}
+
+/// Another player as the local side knows them.
+struct Rival {
+    id: PlayerId,
+    snapshot: Option<BoardSnapshot>,
+    place: Option<u8>,
+    kos: u32,
+}
+
+/// One room match, from the countdown ending to the final standings. Once the
+/// local player is out they keep watching until the match ends; a guest may
+/// leave with Esc, but the host relays for everyone and stays to the end.
+#[allow(clippy::too_many_arguments)]
+pub fn run_room_match(
+    stdout: &mut io::Stdout,
+    music: &mut Option<audio::MusicPlayer>,
+    link: &mut dyn RoomLink,
+    roster: &[PlayerId],
+    vs_settings: &Settings,
+    settings: &Settings,
+    keymap: &Keymap,
+) -> io::Result<()> {
+    let me = link.me();
+    let can_leave = me != HOST_ID;
+    let mut game_settings = *vs_settings;
+    game_settings.level_cap = Some(game_settings.level);
+    let mut game = Game::new(GameMode::Versus, &game_settings, random_seed());
+    let mut garbage_queue = GarbageQueue::with_delay(Duration::from_millis(vs_settings.garbage.delay_ms as u64));
+    let mut holes = HoleGenerator::new(vs_settings.garbage.style, random_seed());
+    let mut rivals: Vec<Rival> = roster
+        .iter()
+        .filter(|&&id| id != me)
+        .map(|&id| Rival { id, snapshot: None, place: None, kos: 0 })
+        .collect();
+    let mut my_place: Option<u8> = None;
+    let mut my_kos: u32 = 0;
+
+    let mut inp = InputState::new(keymap, settings);
+    let mut last_board_sync = Instant::now();
+    let mut reported_death = false;
+    let mut forfeit_sel: Option<usize> = None;
+
+    if let Some(m) = music.as_mut() {
+        m.play();
+    }
+    execute!(stdout, terminal::Clear(terminal::ClearType::All))?;
+
+    let placements = 'round: loop {
+        if game.game_over && !reported_death {
+            reported_death = true;
+            forfeit_sel = None;
+            link.send_board(BoardSnapshot::from_game(&game, garbage_queue.total_pending()));
+            link.report_death();
+            if let Some(m) = music.as_mut() {
+                m.stop();
+                m.play_sfx(Sfx::GameOver);
+            }
+        }
+
+        if !game.game_over {
+            game.update_elapsed();
+        }
+
+        if let Some(sel) = forfeit_sel {
+            let (bgm_on, sfx_on) = match music.as_ref() {
+                Some(m) => (m.bgm_enabled(), m.sfx_enabled()),
+                None => (false, false),
+            };
+            render::versus::draw_versus_forfeit(stdout, bgm_on, sfx_on, sel)?;
+        } else {
+            let minis: Vec<MiniBoard> = rivals
+                .iter()
+                .map(|r| MiniBoard {
+                    id: r.id,
+                    snapshot: r.snapshot.as_ref(),
+                    place: r.place,
+                    kos: r.kos,
+                })
+                .collect();
+            let alive = rivals.iter().filter(|r| r.place.is_none()).count() + usize::from(my_place.is_none());
+            let mut status = format!(
+                "{}  ALIVE: {}/{}  KOs: {}  TARGET: {}",
+                player_name(me),
+                alive,
+                roster.len(),
+                my_kos,
+                settings.target_strategy.label(),
+            );
+            if let Some(place) = my_place {
+                status.push_str(&format!("  #{}", place));
+                if can_leave {
+                    status.push_str("  Esc: leave");
+                }
+            }
+            let pending = garbage_queue.total_pending() + game.garbage_anim_remaining();
+            render::royale::draw_royale(stdout, &game, &minis, pending, &status)?;
+        }
+
+        if !reported_death && last_board_sync.elapsed() >= BOARD_SYNC_INTERVAL {
+            link.send_board(BoardSnapshot::from_game(&game, garbage_queue.total_pending()));
+            last_board_sync = Instant::now();
+        }
+
+        for event in link.poll() {
+            match event {
+                RoomEvent::Board(id, snap) => {
+                    if let Some(rival) = rivals.iter_mut().find(|r| r.id == id) {
+                        rival.snapshot = Some(snap);
+                    }
+                }
+                RoomEvent::Garbage(_, runs) => {
+                    if !game.game_over {
+                        for run in runs {
+                            let event = GarbageEvent {
+                                lines: run.lines,
+                                hole_column: run.hole_column,
+                            };
+                            garbage_queue.push_at(event, game.now());
+                        }
+                        if let Some(m) = music.as_ref() {
+                            m.play_sfx(Sfx::GarbageReceived);
+                        }
+                    }
+                }
+                RoomEvent::Ko(ko) => {
+                    if ko.player == me {
+                        my_place = Some(ko.place);
+                    } else if let Some(rival) = rivals.iter_mut().find(|r| r.id == ko.player) {
+                        rival.place = Some(ko.place);
+                    }
+                    if ko.by == Some(me) {
+                        my_kos += 1;
+                    } else if let Some(rival) = rivals.iter_mut().find(|r| Some(r.id) == ko.by) {
+                        rival.kos += 1;
+                    }
+                }
+                RoomEvent::Finished(placements) => break 'round Some(placements),
+                RoomEvent::HostLeft => break 'round None,
+            }
+        }
+
+        if game.game_over {
+            if event::poll(Duration::from_millis(16))? {
+                if let Some(KeyCode::Esc) = read_key()? {
+                    if can_leave {
+                        play_menu_sfx(music, Sfx::MenuBack);
+                        return Ok(());
+                    }
+                }
+            }
+            continue;
+        }
+
+        if game.is_animating() && !game.update_animation() {
+            game.finish_clear();
+            inp.last_tick = Instant::now();
+        }
+
+        if game.is_garbage_animating() {
+            if game.update_garbage_animation() {
+                if let Some(m) = music.as_ref() {
+                    m.play_sfx(Sfx::GarbageReceived);
+                }
+            } else {
+                inp.last_tick = Instant::now();
+            }
+        }
+
+        let timeout = if forfeit_sel.is_some() || game.is_animating() || game.is_garbage_animating() {
+            Duration::from_millis(16)
+        } else {
+            input::compute_timeout(&game, &inp).min(Duration::from_millis(16))
+        };
+
+        let mut locked = false;
+        if event::poll(timeout)? {
+            if let Some(code) = read_key()? {
+                if forfeit_sel.is_some() {
+                    handle_forfeit_key(stdout, music, code, &mut forfeit_sel, &mut game, &mut inp)?;
+                } else if inp.keymap.is_pause(code) {
+                    if let Some(m) = music.as_ref() {
+                        m.play_sfx(Sfx::Pause);
+                    }
+                    forfeit_sel = Some(0);
+                } else if !game.is_animating() && !game.is_garbage_animating() {
+                    locked = input::handle_game_key(code, &mut game, &mut inp, music);
+                }
+            }
+        }
+
+        if !game.is_animating() && !game.is_garbage_animating() && !game.game_over {
+            let in_are = game.in_are();
+            if input::update_game_timers(&mut game, &mut inp, music) && !in_are {
+                locked = true;
+            }
+        }
+
+        if locked {
+            let runs = outgoing_attack(&mut game, &mut garbage_queue, vs_settings.garbage, &mut holes, music);
+            // The runs of a lock travel together, so they all hit the
+            // same target and keep their own holes.
+            if !runs.is_empty() {
+                link.send_attack(
+                    runs.iter()
+                        .map(|run| GarbageAttack {
+                            lines: run.lines,
+                            hole_column: run.hole_column,
+                        })
+                        .collect(),
+                );
+            }
+        }
+    };
+
+    if let Some(m) = music.as_mut() {
+        m.stop();
+    }
+    match placements {
+        Some(placements) => run_room_result_screen(stdout, music, me, &placements),
+        None => show_notice(stdout, music, "ROOM", "Host left"),
+    }
+}
+
+fn run_room_result_screen(
+    stdout: &mut io::Stdout,
+    music: &mut Option<audio::MusicPlayer>,
+    me: PlayerId,
+    placements: &[PlayerId],
+) -> io::Result<()> {
+    if let Some(m) = music.as_ref() {
+        m.play_sfx(if placements.first() == Some(&me) { Sfx::VersusWin } else { Sfx::VersusLose });
+    }
+    let lines: Vec<String> = placements
+        .iter()
+        .enumerate()
+        .map(|(i, &id)| {
+            let you = if id == me { " (you)" } else { "" };
+            format!("{}. {}{}", i + 1, player_name(id), you)
+        })
+        .collect();
+    let line_refs: Vec<&str> = lines.iter().map(|s| s.as_str()).collect();
+
+    execute!(stdout, terminal::Clear(terminal::ClearType::All))?;
+    loop {
+        render::versus::draw_lobby_screen(stdout, "RESULTS", &line_refs, "", &["Menu"], 0)?;
+        if let Some(KeyCode::Enter | KeyCode::Esc) = read_key()? {
+            play_menu_sfx(music, Sfx::MenuBack);
+            return Ok(());
+        }
+    }
+}
@@ -7,12 +7,14 @@ use std::io;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use crate::audio::{self, Sfx};
-use crate::game::{Game, GameMode};
+use crate::game::{Game, GameInput, GameMode};
 use crate::game::records;
+use crate::game::rng::random_seed;
 use crate::render;
 use crate::game::settings::Settings;
 
 use super::input::{self, InputState};
+use super::keymap::Keymap;
 use super::menus::run_settings;
 use super::{menu_nav, play_menu_sfx, read_key};
 
@@ -73,9 +75,10 @@ pub fn run_game(
     music: &mut Option<audio::MusicPlayer>,
     settings: &mut Settings,
     records: &mut records::Records,
+    keymap: &mut Keymap,
 ) -> io::Result<bool> {
-    let mut game = Game::new(mode, settings);
-    let mut inp = InputState::new();
+    let mut game = Game::new(mode, settings, random_seed());
+    let mut inp = InputState::recording(keymap, settings);
     if let Some(m) = music.as_mut() {
         m.play();
     }
@@ -94,12 +97,16 @@ pub fn run_game(
 
             let time_ms = Some(game.elapsed.as_millis() as u64);
             let now = iso8601_now();
+            if let Some(recorder) = inp.recorder.as_mut() {
+                recorder.finish(&game, settings, now.clone()).save();
+            }
             let record = records::ScoreRecord {
                 score: game.score,
                 lines: game.lines,
                 level: game.level,
                 time: time_ms,
                 date: now,
+                randomizer: Some(settings.randomizer),
             };
             let valid_for_record = match mode {
                 GameMode::Marathon => settings.marathon_goal == 150,
@@ -140,7 +147,7 @@ pub fn run_game(
                     }
                 }
             }
-            game = Game::new(mode, settings);
+            game = Game::new(mode, settings, random_seed());
             inp.reset();
             if let Some(m) = music.as_mut() {
                 m.play();
@@ -150,8 +157,9 @@ pub fn run_game(
         }
 
         game.update_elapsed();
-        if game.mode == GameMode::Ultra && game.elapsed >= Duration::from_secs(game.ultra_time as u64) {
+        if game.time_up() {
             game.game_over = true;
+            inp.record(&game, GameInput::TimeUp);
         }
 
         render::draw(stdout, &game)?;
@@ -164,6 +172,7 @@ pub fn run_game(
                 continue;
             } else {
                 game.finish_clear();
+                inp.record(&game, GameInput::ClearDone);
                 inp.last_tick = Instant::now();
                 continue;
             }
@@ -182,7 +191,7 @@ pub fn run_game(
         if event::poll(timeout)? {
             if let Some(code) = read_key()? {
                 match code {
-                    KeyCode::Esc | KeyCode::Char('p') | KeyCode::Char('P') => {
+                    c if inp.keymap.is_pause(c) => {
                         if let Some(m) = music.as_mut() {
                             m.play_sfx(Sfx::Pause);
                             m.pause();
@@ -207,11 +216,11 @@ pub fn run_game(
                                         }
                                         1 => {
                                             play_menu_sfx(music, Sfx::MenuSelect);
-                                            run_settings(stdout, music, settings, mode, true)?;
+                                            run_settings(stdout, music, settings, keymap, mode, true)?;
                                         }
                                         2 => {
                                             play_menu_sfx(music, Sfx::MenuSelect);
-                                            render::draw_help(stdout, 0)?;
+                                            render::draw_help(stdout, keymap, 0)?;
                                             loop {
                                                 if let Some(code) = read_key()? {
                                                     if code == KeyCode::Enter || code == KeyCode::Esc {
@@ -234,7 +243,7 @@ pub fn run_game(
                                         }
                                         _ => {}
                                     },
-                                    KeyCode::Esc | KeyCode::Char('p') | KeyCode::Char('P') => {
+                                    c if inp.keymap.is_pause(c) => {
                                         if let Some(m) = music.as_ref() {
                                             m.play_sfx(Sfx::Resume);
                                         }
@@ -246,7 +255,7 @@ pub fn run_game(
                         }
                         if retry {
                             play_menu_sfx(music, Sfx::MenuSelect);
-                            game = Game::new(mode, settings);
+                            game = Game::new(mode, settings, random_seed());
                             inp.reset();
                             if let Some(m) = music.as_mut() {
                                 m.play();
@@ -260,7 +269,7 @@ pub fn run_game(
                         game.reset_game_start();
                         inp.last_tick = Instant::now();
                         if game.lock_delay.is_some() {
-                            game.lock_delay = Some(Instant::now());
+                            game.lock_delay = Some(game.now());
                         }
                         if let Some(d) = &mut inp.das {
                             d.reset_timers();
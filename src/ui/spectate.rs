@@ -0,0 +1,152 @@
+use crossterm::event::{self, KeyCode};
+use crossterm::{execute, terminal};
+use std::io;
+use std::time::Duration;
+
+use crate::audio::{self, Sfx};
+use crate::net::transport::Transport;
+use crate::net::{BoardSnapshot, NetMessage, HOST_SLOT};
+use crate::render;
+
+use super::{play_menu_sfx, read_key};
+
+/// What a spectator has seen of the match so far.
+#[derive(Default)]
+pub(super) struct SpectatorView {
+    pub boards: [Option<BoardSnapshot>; 2],
+    pub countdown: Option<u8>,
+    pub winner: Option<u8>,
+    pub host_left: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Screen {
+    Waiting,
+    Countdown(u8),
+    Match,
+    Result(u8),
+}
+
+impl SpectatorView {
+    /// Folds one message from the host into the view. Returns true for a
+    /// garbage attack, which is the only thing a spectator hears.
+    pub(super) fn apply(&mut self, msg: NetMessage) -> bool {
+        match msg {
+            NetMessage::Countdown(n) => {
+                self.boards = [None, None];
+                self.winner = None;
+                self.countdown = Some(n);
+            }
+            NetMessage::GameStart => self.countdown = None,
+            NetMessage::SpectatorBoard(slot, snap) => {
+                if let Some(board) = self.boards.get_mut(slot as usize) {
+                    *board = Some(snap);
+                }
+            }
+            NetMessage::SpectatorGarbage(..) => return true,
+            NetMessage::SpectatorResult(slot) => self.winner = Some(slot),
+            NetMessage::Disconnect => self.host_left = true,
+            _ => {}
+        }
+        false
+    }
+
+    fn screen(&self) -> Screen {
+        if let Some(slot) = self.winner {
+            Screen::Result(slot)
+        } else if let Some(n) = self.countdown {
+            Screen::Countdown(n)
+        } else if self.boards.iter().any(Option::is_some) {
+            Screen::Match
+        } else {
+            Screen::Waiting
+        }
+    }
+}
+
+/// Watches a LAN match until the host leaves or Esc is pressed. Nothing the
+/// spectator does is sent to the host except leaving.
+pub fn run_spectate(
+    stdout: &mut io::Stdout,
+    music: &mut Option<audio::MusicPlayer>,
+    conn: &mut dyn Transport,
+) -> io::Result<()> {
+    execute!(stdout, terminal::Clear(terminal::ClearType::All))?;
+
+    let mut view = SpectatorView::default();
+    let mut last_screen = view.screen();
+    loop {
+        loop {
+            match conn.try_recv() {
+                Ok(Some(msg)) => {
+                    if view.apply(msg) {
+                        if let Some(m) = music.as_ref() {
+                            m.play_sfx(Sfx::GarbageReceived);
+                        }
+                    }
+                }
+                Ok(None) => break,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(_) => {
+                    view.host_left = true;
+                    break;
+                }
+            }
+        }
+
+        if view.host_left {
+            return run_host_left_screen(stdout, music);
+        }
+
+        let screen = view.screen();
+        if screen != last_screen {
+            execute!(stdout, terminal::Clear(terminal::ClearType::All))?;
+            if let Screen::Countdown(_) = screen {
+                play_menu_sfx(music, Sfx::MenuMove);
+            }
+            last_screen = screen;
+        }
+
+        match screen {
+            Screen::Waiting => render::versus::draw_lobby_screen(
+                stdout, "WATCH GAME", &["Connected!", "Waiting..."], "", &["Leave"], 0,
+            )?,
+            Screen::Countdown(n) => render::versus::draw_versus_countdown(stdout, n, None)?,
+            Screen::Match => render::versus::draw_spectate(stdout, &view.boards)?,
+            Screen::Result(slot) => {
+                let winner = if slot == HOST_SLOT { "HOST WINS" } else { "GUEST WINS" };
+                render::versus::draw_lobby_screen(
+                    stdout, "WATCH GAME", &[winner, "Waiting..."], "", &["Leave"], 0,
+                )?
+            }
+        }
+
+        if event::poll(Duration::from_millis(16))? {
+            if let Some(code) = read_key()? {
+                let leave = match code {
+                    KeyCode::Esc => true,
+                    KeyCode::Enter => screen != Screen::Match && !matches!(screen, Screen::Countdown(_)),
+                    _ => false,
+                };
+                if leave {
+                    play_menu_sfx(music, Sfx::MenuBack);
+                    let _ = conn.send(&NetMessage::Disconnect);
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+fn run_host_left_screen(stdout: &mut io::Stdout, music: &mut Option<audio::MusicPlayer>) -> io::Result<()> {
+    execute!(stdout, terminal::Clear(terminal::ClearType::All))?;
+    loop {
+        render::versus::draw_lobby_screen(
+            stdout, "WATCH GAME", &["Host left"], "", &["Back"], 0,
+        )?;
+        if let Some(KeyCode::Enter | KeyCode::Esc) = read_key()? {
+            play_menu_sfx(music, Sfx::MenuBack);
+            return Ok(());
+        }
+    }
+}
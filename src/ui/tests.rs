@@ -0,0 +1,560 @@
+use crossterm::event::KeyCode;
+use std::time::{Duration, Instant};
+
+use crate::game::garbage::{
+    AttackTable, GarbageEvent, GarbageQueue, GarbageRules, GarbageStyle, HoleGenerator, MARGIN_STEP_SECS,
+    MAX_GARBAGE_CAP, MAX_MARGIN_SECS,
+};
+use crate::game::piece::{Piece, BOARD_HEIGHT, BOARD_WIDTH, GARBAGE_CELL, VISIBLE_HEIGHT};
+use crate::game::set::SetScore;
+use crate::game::settings::{Settings, MAX_FIRST_TO};
+use crate::game::stats::MatchStats;
+use crate::game::lockstep::{LockstepAction, LockstepLog, Mismatch, Verifier};
+use crate::game::{Game, GameInput, GameMode};
+use crate::net::transport::{channel_pair, Transport};
+use crate::net::{BoardSnapshot, GarbageAttack, NetMessage, GUEST_SLOT, HOST_SLOT, PROTOCOL_VERSION};
+use crate::render::scroll_overlay;
+use crate::ui::input::Handling;
+use crate::ui::keymap::{Action, Keymap};
+use crate::ui::spectate::SpectatorView;
+use crate::ui::versus::{
+    adjust_match_setting, follow_countdown, host_countdown, perform_handshake, poll_peer, process_post_lock,
+    verification_failure, Opponent, ReadyCheck, Rematch, ResultAction, SetupEvent,
+};
+
+// --- Keymap tests ---
+
+#[test]
+fn keymap_default_bindings() {
+    let km = Keymap::default();
+    assert_eq!(km.action_for(KeyCode::Left), Some(Action::MoveLeft));
+    assert_eq!(km.action_for(KeyCode::Char('X')), Some(Action::RotateCw));
+    assert_eq!(km.action_for(KeyCode::Char(' ')), Some(Action::HardDrop));
+    assert_eq!(km.action_for(KeyCode::Tab), None);
+    assert!(km.is_pause(KeyCode::Esc));
+    assert!(km.is_pause(KeyCode::Char('P')));
+}
+
+#[test]
+fn keymap_bind_steals_key() {
+    let mut km = Keymap::default();
+    km.bind(Action::Hold, KeyCode::Char('Z'));
+    assert_eq!(km.action_for(KeyCode::Char('z')), Some(Action::Hold));
+    assert!(km.keys(Action::RotateCcw).is_empty());
+}
+
+#[test]
+fn keymap_serde_roundtrip() {
+    let mut km = Keymap::default();
+    km.bind(Action::Pause, KeyCode::F(5));
+    let json = serde_json::to_string(&km).unwrap();
+    let back: Keymap = serde_json::from_str(&json).unwrap();
+    for action in Action::ALL {
+        assert_eq!(back.keys(action), km.keys(action));
+    }
+}
+
+#[test]
+fn keymap_missing_fields_use_defaults() {
+    let km: Keymap = serde_json::from_str(r#"{"hold": ["Tab"]}"#).unwrap();
+    assert_eq!(km.keys(Action::Hold), &vec![KeyCode::Tab]);
+    assert_eq!(km.keys(Action::MoveLeft), &vec![KeyCode::Left]);
+}
+
+// --- Handling tests ---
+
+#[test]
+fn handling_from_settings() {
+    let settings = Settings {
+        das_ms: 100,
+        arr_ms: 0,
+        soft_drop_factor: None,
+        ..Settings::default()
+    };
+    let h = Handling::from_settings(&settings);
+    assert_eq!(h.das, Duration::from_millis(100));
+    assert!(h.arr.is_zero());
+    assert_eq!(h.das_release, Duration::from_millis(100));
+    assert_eq!(h.soft_drop_factor, None);
+}
+
+#[test]
+fn scroll_overlay_keeps_focus_visible() {
+    let content: Vec<Option<String>> = (0..30).map(|i| Some(i.to_string())).collect();
+
+    let top = scroll_overlay(content.clone(), 2, 3);
+    assert_eq!(top.len(), VISIBLE_HEIGHT);
+    assert_eq!(top[2].as_deref(), Some("2"));
+
+    let bottom = scroll_overlay(content.clone(), 2, 29);
+    assert_eq!(bottom.len(), VISIBLE_HEIGHT);
+    assert_eq!(bottom[0].as_deref(), Some("0"));
+    assert_eq!(bottom[VISIBLE_HEIGHT - 1].as_deref(), Some("29"));
+
+    let short = scroll_overlay(content[..5].to_vec(), 2, 4);
+    assert_eq!(short.len(), 5);
+}
+
+// --- Versus flow tests (in-memory transport) ---
+
+#[test]
+fn handshake_over_channels() {
+    let (mut host, mut client) = channel_pair();
+    let peer = std::thread::spawn(move || perform_handshake(&mut client, false));
+    perform_handshake(&mut host, true).unwrap();
+    peer.join().unwrap().unwrap();
+}
+
+#[test]
+fn handshake_rejects_version_mismatch() {
+    let (mut host, mut client) = channel_pair();
+    client
+        .send(&NetMessage::Hello {
+            version: PROTOCOL_VERSION + 1,
+            binary: true,
+        })
+        .unwrap();
+    let err = perform_handshake(&mut host, true).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn countdown_reaches_client() {
+    let (mut host, mut client) = channel_pair();
+    let mut sent = Vec::new();
+    host_countdown(&mut host, |n| {
+        sent.push(n);
+        Ok(())
+    })
+    .unwrap();
+    let mut seen = Vec::new();
+    let started = follow_countdown(&mut client, |n| {
+        seen.push(n);
+        Ok(())
+    })
+    .unwrap();
+    assert!(started);
+    assert_eq!(sent, vec![3, 2, 1]);
+    assert_eq!(seen, sent);
+}
+
+#[test]
+fn countdown_stops_on_disconnect() {
+    let (mut host, mut client) = channel_pair();
+    host.send(&NetMessage::Countdown(3)).unwrap();
+    host.send(&NetMessage::Disconnect).unwrap();
+    assert!(!follow_countdown(&mut client, |_| Ok(())).unwrap());
+}
+
+fn versus_game() -> Game {
+    let settings = Settings {
+        line_clear_anim: false,
+        ..Settings::default()
+    };
+    Game::new(GameMode::Versus, &settings, 0)
+}
+
+/// Fills the bottom four rows except the right wall and drops a vertical I
+/// into it. One stray block stays behind so it is not an all clear.
+fn drop_tetris(game: &mut Game) {
+    for row in &mut game.board[BOARD_HEIGHT - 4..] {
+        row[..BOARD_WIDTH - 1].fill(GARBAGE_CELL);
+    }
+    game.board[BOARD_HEIGHT - 5][0] = GARBAGE_CELL;
+    game.current = Piece::new(0, game.rotation_system);
+    game.rotate_cw();
+    while game.move_piece(0, 1) {}
+    game.hard_drop();
+    assert_eq!(game.lines, 4);
+}
+
+/// `process_post_lock` with throwaway holes and stats.
+fn post_lock(game: &mut Game, queue: &mut GarbageQueue, rules: GarbageRules, conn: &mut dyn Transport) {
+    let mut holes = HoleGenerator::new(GarbageStyle::Clean, 0);
+    process_post_lock(game, queue, rules, &mut holes, &mut MatchStats::default(), conn, &None).unwrap();
+}
+
+#[test]
+fn garbage_reaches_opponent_once() {
+    let (mut a, mut b) = channel_pair();
+    let mut game = versus_game();
+    let mut queue = GarbageQueue::new();
+
+    drop_tetris(&mut game);
+    game.end_are();
+    post_lock(&mut game, &mut queue, GarbageRules::default(), &mut a);
+
+    let mut their_queue = GarbageQueue::new();
+    let mut opponent = Opponent::default();
+    let poll = poll_peer(&mut b, &mut their_queue, Instant::now(), &mut opponent);
+    assert_eq!(poll.attacks, 1);
+    assert_eq!(their_queue.total_pending(), 4);
+
+    // A later lock that clears nothing must not repeat the attack.
+    game.hard_drop();
+    post_lock(&mut game, &mut queue, GarbageRules::default(), &mut a);
+    assert_eq!(poll_peer(&mut b, &mut their_queue, Instant::now(), &mut opponent).attacks, 0);
+    assert_eq!(their_queue.total_pending(), 4);
+}
+
+#[test]
+fn garbage_cancels_pending_before_sending() {
+    let (mut a, mut b) = channel_pair();
+    let mut game = versus_game();
+    let mut queue = GarbageQueue::new();
+    queue.push(GarbageEvent {
+        lines: 3,
+        hole_column: 0,
+    });
+
+    let mut stats = MatchStats::default();
+    drop_tetris(&mut game);
+    let mut holes = HoleGenerator::new(GarbageStyle::Clean, 0);
+    process_post_lock(&mut game, &mut queue, GarbageRules::default(), &mut holes, &mut stats, &mut a, &None).unwrap();
+    assert_eq!(queue.total_pending(), 0);
+    assert_eq!((stats.attack_sent, stats.attack_cancelled), (1, 3));
+    assert_eq!((stats.pieces, stats.garbage_cleared), (1, 4));
+
+    let mut their_queue = GarbageQueue::new();
+    poll_peer(&mut b, &mut their_queue, Instant::now(), &mut Opponent::default());
+    assert_eq!(their_queue.total_pending(), 1);
+}
+
+#[test]
+fn garbage_skips_cancel_when_rules_say_so() {
+    let (mut a, mut b) = channel_pair();
+    let mut game = versus_game();
+    let mut queue = GarbageQueue::new();
+    queue.push(GarbageEvent {
+        lines: 3,
+        hole_column: 0,
+    });
+
+    drop_tetris(&mut game);
+    let rules = GarbageRules {
+        cancel: false,
+        ..GarbageRules::default()
+    };
+    post_lock(&mut game, &mut queue, rules, &mut a);
+    assert_eq!(queue.total_pending(), 3);
+
+    let mut their_queue = GarbageQueue::new();
+    poll_peer(&mut b, &mut their_queue, Instant::now(), &mut Opponent::default());
+    assert_eq!(their_queue.total_pending(), 4);
+}
+
+#[test]
+fn delayed_garbage_waits_and_rises_under_the_cap() {
+    let (mut a, _b) = channel_pair();
+    let mut game = versus_game();
+    let rules = GarbageRules {
+        cap: Some(2),
+        ..GarbageRules::default()
+    };
+    let mut queue = GarbageQueue::with_delay(Duration::from_secs(60));
+    queue.push(GarbageEvent {
+        lines: 3,
+        hole_column: 0,
+    });
+
+    game.hard_drop();
+    post_lock(&mut game, &mut queue, rules, &mut a);
+    assert!(!game.is_garbage_animating());
+    assert_eq!(queue.total_pending(), 3);
+
+    let mut queue = GarbageQueue::new();
+    queue.push(GarbageEvent {
+        lines: 3,
+        hole_column: 0,
+    });
+    game.end_are();
+    game.hard_drop();
+    post_lock(&mut game, &mut queue, rules, &mut a);
+    assert_eq!(game.garbage_anim_remaining(), 2);
+    assert_eq!(queue.total_pending(), 1);
+}
+
+#[test]
+fn peer_messages_update_opponent() {
+    let (mut a, mut b) = channel_pair();
+    a.send(&NetMessage::PlayerDead).unwrap();
+    let mut opponent = Opponent::default();
+    let poll = poll_peer(&mut b, &mut GarbageQueue::new(), Instant::now(), &mut opponent);
+    assert!(opponent.dead);
+    assert!(!poll.disconnected);
+
+    a.send(&NetMessage::Disconnect).unwrap();
+    assert!(poll_peer(&mut b, &mut GarbageQueue::new(), Instant::now(), &mut opponent).disconnected);
+
+    drop(a);
+    let poll = poll_peer(&mut b, &mut GarbageQueue::new(), Instant::now(), &mut opponent);
+    assert!(poll.dropped);
+    assert!(!poll.disconnected);
+}
+
+#[test]
+fn peer_pings_are_answered_in_passing() {
+    let (mut a, mut b) = channel_pair();
+    a.send(&NetMessage::Ping(3)).unwrap();
+    let poll = poll_peer(&mut b, &mut GarbageQueue::new(), Instant::now(), &mut Opponent::default());
+    assert_eq!(poll.attacks, 0);
+    assert!(!poll.dropped);
+    assert!(matches!(a.try_recv().unwrap(), Some(NetMessage::Pong(3))));
+}
+
+#[test]
+fn rematch_after_opponent_asked() {
+    let (mut a, mut b) = channel_pair();
+    let (mut ra, mut rb) = (Rematch::default(), Rematch::default());
+    assert_eq!(ra.request(&mut a), None);
+    assert_eq!(rb.poll(&mut b), None);
+    assert_eq!(rb.request(&mut b), Some(ResultAction::Rematch));
+    assert_eq!(ra.poll(&mut a), Some(ResultAction::Rematch));
+}
+
+#[test]
+fn rematch_when_requests_cross() {
+    let (mut a, mut b) = channel_pair();
+    let (mut ra, mut rb) = (Rematch::default(), Rematch::default());
+    assert_eq!(ra.request(&mut a), None);
+    assert_eq!(rb.request(&mut b), None);
+    assert_eq!(ra.poll(&mut a), Some(ResultAction::Rematch));
+    assert_eq!(rb.poll(&mut b), Some(ResultAction::Rematch));
+}
+
+#[test]
+fn match_stats_reach_the_other_side() {
+    let (mut a, mut b) = channel_pair();
+    let ours = MatchStats {
+        pieces: 40,
+        attack_sent: 12,
+        ..MatchStats::default()
+    };
+    a.send(&NetMessage::PlayerDead).unwrap();
+    a.send(&NetMessage::MatchStats(ours.clone())).unwrap();
+    let mut opponent = Opponent::default();
+    poll_peer(&mut b, &mut GarbageQueue::new(), Instant::now(), &mut opponent);
+    assert_eq!(opponent.stats, Some(ours.clone()));
+
+    // Stats sent after the other side reached its result screen.
+    let mut rematch = Rematch::default();
+    a.send(&NetMessage::MatchStats(ours.clone())).unwrap();
+    assert_eq!(rematch.poll(&mut b), None);
+    assert_eq!(rematch.their_stats, Some(ours));
+}
+
+#[test]
+fn verified_match_flags_a_bad_board_on_both_sides() {
+    let (mut a, mut b) = channel_pair();
+    let settings = Settings::default();
+    let game = Game::new(GameMode::Versus, &settings, 7);
+    let mut log = LockstepLog::default();
+    log.push(&game, LockstepAction::Input(GameInput::MoveLeft));
+    let mut batch = log.batch(&game, false);
+    // The board they report without having made the move.
+    batch.hash = Some(game.state_hash());
+    a.send(&NetMessage::Inputs(batch)).unwrap();
+
+    let mut checker = Opponent {
+        verifier: Some(Verifier::new(&settings, 7, 0)),
+        ..Opponent::default()
+    };
+    poll_peer(&mut b, &mut GarbageQueue::new(), Instant::now(), &mut checker);
+    assert_eq!(
+        verification_failure(&mut b, &checker),
+        Some((Mismatch::Board, "in opponent's game"))
+    );
+
+    let mut checked = Opponent::default();
+    poll_peer(&mut a, &mut GarbageQueue::new(), Instant::now(), &mut checked);
+    assert_eq!(checked.flagged, Some(Mismatch::Board));
+    assert_eq!(verification_failure(&mut a, &checked).map(|(m, _)| m.title()), Some("DESYNC"));
+}
+
+#[test]
+fn guest_takes_the_hosts_set_score() {
+    let (mut host, mut guest) = channel_pair();
+    let mut score = SetScore::new(3);
+    score.record(true);
+    host.send(&NetMessage::PlayerDead).unwrap();
+    host.send(&NetMessage::SetScore(score)).unwrap();
+    let mut opponent = Opponent::default();
+    poll_peer(&mut guest, &mut GarbageQueue::new(), Instant::now(), &mut opponent);
+    assert_eq!(opponent.set_score, Some(score));
+
+    score.record(false);
+    let mut rematch = Rematch::default();
+    host.send(&NetMessage::SetScore(score)).unwrap();
+    assert_eq!(rematch.poll(&mut guest), None);
+    assert_eq!(rematch.set_score.map(|s| s.from_side(false)), Some((1, 1)));
+}
+
+#[test]
+fn rematch_ends_when_opponent_leaves() {
+    let (mut a, mut b) = channel_pair();
+    let mut ra = Rematch::default();
+    b.send(&NetMessage::Disconnect).unwrap();
+    assert_eq!(ra.poll(&mut a), Some(ResultAction::Menu));
+    drop(b);
+    assert_eq!(ra.poll(&mut a), Some(ResultAction::Menu));
+}
+
+// --- Match setup tests ---
+
+#[test]
+fn match_starts_once_both_ready() {
+    let (mut host, mut guest) = channel_pair();
+    let (mut hc, mut gc) = (ReadyCheck::default(), ReadyCheck::default());
+    let (mut host_vs, mut guest_vs) = (Settings::default(), Settings::default());
+
+    hc.toggle(&mut host);
+    assert!(!hc.try_start(&mut host, &mut host_vs));
+    assert_eq!(gc.poll(&mut guest, &mut guest_vs), None);
+    assert!(gc.peer_ready);
+
+    gc.toggle(&mut guest);
+    assert_eq!(hc.poll(&mut host, &mut host_vs), None);
+    assert!(hc.try_start(&mut host, &mut host_vs));
+    assert_eq!(gc.poll(&mut guest, &mut guest_vs), Some(SetupEvent::Start));
+    // Both sides deal garbage holes from the host's seed.
+    assert_eq!(guest_vs.garbage.seed, host_vs.garbage.seed);
+}
+
+#[test]
+fn settings_change_reaches_guest_and_clears_ready() {
+    let (mut host, mut guest) = channel_pair();
+    let (mut hc, mut gc) = (ReadyCheck::default(), ReadyCheck::default());
+    let mut host_vs = Settings::default();
+    let mut guest_vs = Settings::default();
+
+    gc.toggle(&mut guest);
+    hc.poll(&mut host, &mut host_vs);
+    assert!(hc.peer_ready);
+
+    adjust_match_setting(&mut host_vs, 0, 1);
+    adjust_match_setting(&mut host_vs, 5, 1);
+    adjust_match_setting(&mut host_vs, 6, -1);
+    hc.settings_changed(&mut host, &host_vs);
+    assert!(!hc.peer_ready);
+
+    assert_eq!(gc.poll(&mut guest, &mut guest_vs), None);
+    assert!(!gc.we_ready);
+    assert_eq!(guest_vs.level, 2);
+    assert!(!guest_vs.garbage.cancel);
+    assert_eq!(guest_vs.garbage.table, AttackTable::LinesMinusOne);
+}
+
+#[test]
+fn ready_for_old_settings_is_ignored() {
+    let (mut host, mut guest) = channel_pair();
+    let (mut hc, mut gc) = (ReadyCheck::default(), ReadyCheck::default());
+    let (mut host_vs, mut guest_vs) = (Settings::default(), Settings::default());
+
+    // The guest readies up while the host is changing the level.
+    gc.toggle(&mut guest);
+    adjust_match_setting(&mut host_vs, 0, 1);
+    hc.settings_changed(&mut host, &host_vs);
+    hc.toggle(&mut host);
+    assert_eq!(hc.poll(&mut host, &mut host_vs), None);
+    assert!(!hc.peer_ready);
+    assert!(!hc.try_start(&mut host, &mut host_vs));
+
+    assert_eq!(gc.poll(&mut guest, &mut guest_vs), None);
+    assert!(!gc.we_ready);
+    assert_eq!(guest_vs.level, 2);
+
+    gc.toggle(&mut guest);
+    assert_eq!(hc.poll(&mut host, &mut host_vs), None);
+    assert!(hc.try_start(&mut host, &mut host_vs));
+}
+
+#[test]
+fn host_keeps_only_versus_rules_from_setup() {
+    let mut saved = Settings::default();
+    let mut vs = saved;
+    adjust_match_setting(&mut vs, 0, 1);
+    adjust_match_setting(&mut vs, 5, 1);
+    adjust_match_setting(&mut vs, 11, 1);
+    adjust_match_setting(&mut vs, 12, 1);
+    saved.keep_match_rules(&vs);
+    assert_eq!(saved.level, Settings::default().level);
+    assert_eq!(saved.garbage.cancel, vs.garbage.cancel);
+    assert_eq!(saved.first_to, vs.first_to);
+    assert!(saved.verify);
+}
+
+#[test]
+fn setup_ends_when_peer_leaves() {
+    let (mut host, guest) = channel_pair();
+    let mut hc = ReadyCheck::default();
+    drop(guest);
+    assert_eq!(hc.poll(&mut host, &mut Settings::default()), Some(SetupEvent::PeerLeft));
+}
+
+#[test]
+fn match_setting_rows_stay_in_range() {
+    let mut vs = Settings {
+        level: 1,
+        ..Default::default()
+    };
+    adjust_match_setting(&mut vs, 0, -1);
+    assert_eq!(vs.level, 1);
+    vs.lock_delay_ms = 2000;
+    adjust_match_setting(&mut vs, 2, 1);
+    assert_eq!(vs.lock_delay_ms, 2000);
+    vs.next_count = 0;
+    adjust_match_setting(&mut vs, 1, -1);
+    assert_eq!(vs.next_count, 0);
+    adjust_match_setting(&mut vs, 8, -1);
+    assert_eq!(vs.garbage.delay_ms, 0);
+    adjust_match_setting(&mut vs, 11, -1);
+    assert_eq!(vs.first_to, 1);
+    vs.first_to = MAX_FIRST_TO;
+    adjust_match_setting(&mut vs, 11, 1);
+    assert_eq!(vs.first_to, MAX_FIRST_TO);
+}
+
+#[test]
+fn cap_and_margin_rows_wrap_through_off() {
+    let mut vs = Settings::default();
+    adjust_match_setting(&mut vs, 9, 1);
+    assert_eq!(vs.garbage.cap, None);
+    adjust_match_setting(&mut vs, 9, -1);
+    assert_eq!(vs.garbage.cap, Some(MAX_GARBAGE_CAP));
+    vs.garbage.cap = Some(1);
+    adjust_match_setting(&mut vs, 9, -1);
+    assert_eq!(vs.garbage.cap, Some(1));
+
+    adjust_match_setting(&mut vs, 10, -1);
+    assert_eq!(vs.garbage.margin_secs, None);
+    adjust_match_setting(&mut vs, 10, 1);
+    assert_eq!(vs.garbage.margin_secs, Some(MARGIN_STEP_SECS));
+    adjust_match_setting(&mut vs, 10, -1);
+    assert_eq!(vs.garbage.margin_secs, None);
+    vs.garbage.margin_secs = Some(MAX_MARGIN_SECS);
+    adjust_match_setting(&mut vs, 10, 1);
+    assert_eq!(vs.garbage.margin_secs, Some(MAX_MARGIN_SECS));
+}
+
+// --- Spectator view tests ---
+
+#[test]
+fn spectator_view_follows_match() {
+    let game = versus_game();
+    let mut view = SpectatorView::default();
+    view.apply(NetMessage::Countdown(3));
+    assert_eq!(view.countdown, Some(3));
+    view.apply(NetMessage::GameStart);
+    assert_eq!(view.countdown, None);
+
+    view.apply(NetMessage::SpectatorBoard(GUEST_SLOT, BoardSnapshot::from_game(&game, 0)));
+    assert!(view.boards[HOST_SLOT as usize].is_none());
+    assert!(view.boards[GUEST_SLOT as usize].is_some());
+    assert!(view.apply(NetMessage::SpectatorGarbage(HOST_SLOT, GarbageAttack { lines: 2, hole_column: 0 })));
+
+    view.apply(NetMessage::SpectatorResult(HOST_SLOT));
+    assert_eq!(view.winner, Some(HOST_SLOT));
+    view.apply(NetMessage::Countdown(3));
+    assert_eq!(view.winner, None);
+    assert!(view.boards.iter().all(Option::is_none));
+}
@@ -4,59 +4,69 @@ use std::io;
 use std::time::{Duration, Instant};
 
 use crate::audio::{self, Sfx};
-use crate::game::garbage::{calculate_attack, GarbageEvent, GarbageQueue};
-use crate::game::{Game, GameMode};
-use crate::net::transport::Connection;
-use crate::net::{BoardSnapshot, GarbageAttack, MatchOutcome, NetMessage, PROTOCOL_VERSION};
+use crate::game::garbage::{
+    AttackTable, GarbageEvent, GarbageQueue, GarbageRules, GarbageStyle, HoleGenerator, MARGIN_STEP_SECS,
+    MAX_GARBAGE_CAP, MAX_MARGIN_SECS,
+};
+use crate::game::lockstep::{piece_seed, LockstepAction, LockstepLog, Mismatch, Verifier};
+use crate::game::{Game, GameInput, GameMode};
+use crate::game::rng::random_seed;
+use crate::net::delta::{BoardDecoder, BoardEncoder};
+use crate::net::discovery::{Beacon, BeaconSender};
+use crate::net::keepalive::Heartbeat;
+use crate::net::session::{GuestLink, ResumableLink, RESUME_GRACE};
+use crate::net::spectate::{Arrival, Broadcast, SpectatorHub};
+use crate::net::transport::Transport;
+use crate::net::{hello, negotiate, BoardSnapshot, GarbageAttack, MatchOutcome, NetMessage};
 use crate::game::piece::*;
+use crate::game::rotation::RotationSystem;
 use crate::render;
-use crate::game::settings::Settings;
+use crate::render::versus::SETUP_ROWS;
+use crate::game::set::SetScore;
+use crate::game::settings::{Settings, MAX_FIRST_TO};
+use crate::game::stats::MatchStats;
 
 use super::input::{self, InputState};
+use super::keymap::Keymap;
+use super::menus::cycle;
 use super::{menu_nav, play_menu_sfx, read_key, toggle_bgm, toggle_sfx};
 
-const BOARD_SYNC_INTERVAL: Duration = Duration::from_millis(66);
+pub(super) const BOARD_SYNC_INTERVAL: Duration = Duration::from_millis(66);
+
+/// How long a verified game waits for the opponent's last inputs once it
+/// is over.
+const VERIFY_GRACE: Duration = Duration::from_secs(3);
 
 pub enum LobbyResult {
-    Connected(Connection, Settings),
+    Connected(Box<dyn Transport>, Settings),
     Back,
     Menu,
 }
 
-fn check_version(msg: NetMessage) -> io::Result<()> {
-    match msg {
-        NetMessage::Hello { version } if version == PROTOCOL_VERSION => Ok(()),
-        NetMessage::Hello { version } => Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            format!("protocol version mismatch: local={}, remote={}", PROTOCOL_VERSION, version),
-        )),
-        _ => Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            "expected Hello",
-        )),
-    }
-}
-
-fn perform_handshake(conn: &mut Connection, is_host: bool) -> io::Result<()> {
-    if is_host {
-        conn.send(&NetMessage::Hello { version: PROTOCOL_VERSION })?;
-        check_version(conn.recv_blocking()?)
+pub(super) fn perform_handshake(conn: &mut dyn Transport, is_host: bool) -> io::Result<()> {
+    let codec = if is_host {
+        conn.send(&hello())?;
+        negotiate(conn.recv_blocking()?)?
     } else {
-        check_version(conn.recv_blocking()?)?;
-        conn.send(&NetMessage::Hello { version: PROTOCOL_VERSION })
-    }
+        let codec = negotiate(conn.recv_blocking()?)?;
+        conn.send(&hello())?;
+        codec
+    };
+    conn.set_codec(codec);
+    Ok(())
 }
 
-fn show_handshake_error(
+fn show_lobby_error(
     stdout: &mut io::Stdout,
     music: &mut Option<audio::MusicPlayer>,
     title: &str,
+    line: &str,
     error_msg: &str,
 ) -> io::Result<LobbyResult> {
     let mut sel: usize = 0;
     loop {
         render::versus::draw_lobby_screen(
-            stdout, title, &["Handshake failed"], error_msg, &["Back"], sel,
+            stdout, title, &[line], error_msg, &["Back"], sel,
         )?;
         if let Some(code) = read_key()? {
             match code {
@@ -74,12 +84,19 @@ fn show_handshake_error(
     }
 }
 
+/// Waits for an opponent, then runs the match setup with them. The match
+/// settings start from `settings` and are edited as a copy; only the
+/// Versus-only rules picked there are kept in `settings` for next time.
 pub fn run_host_lobby(
     stdout: &mut io::Stdout,
     music: &mut Option<audio::MusicPlayer>,
+    settings: &mut Settings,
     port: u16,
 ) -> io::Result<LobbyResult> {
     let listener = crate::net::host::listen_nonblocking(port)?;
+    let mut vs = *settings;
+    let mut hub = SpectatorHub::new(Some(listener), vs);
+    let mut beacon = BeaconSender::new(&Beacon::new(port, vs, false)).ok();
 
     let addr_lines: Vec<String> = match crate::net::host::local_ip() {
         Some(ip) => vec![format!("IP: {}", ip), format!("Port: {}", port)],
@@ -88,13 +105,23 @@ pub fn run_host_lobby(
     let fw_hint = format!("FW: TCP {}", port);
 
     let mut sel: usize = 0;
+    let mut error = "";
     loop {
+        if let Some(b) = beacon.as_mut() {
+            b.tick();
+        }
+
+        let watching = match hub.spectator_count() {
+            0 => None,
+            n => Some(format!("Watching: {}", n)),
+        };
         let lines: Vec<&str> = std::iter::once("Listening...")
             .chain(addr_lines.iter().map(|s| s.as_str()))
+            .chain(watching.as_deref())
             .chain(std::iter::once(fw_hint.as_str()))
             .collect();
         render::versus::draw_lobby_screen(
-            stdout, "HOST GAME", &lines, "", &["Back", "Menu"], sel,
+            stdout, "HOST GAME", &lines, error, &["Back", "Menu"], sel,
         )?;
 
         if event::poll(Duration::from_millis(100))? {
@@ -117,34 +144,56 @@ pub fn run_host_lobby(
             }
         }
 
-        match crate::net::host::try_accept(&listener) {
-            Ok(Some(mut conn)) => {
-                render::versus::draw_lobby_screen(
-                    stdout, "HOST GAME", &["Connected!", "Starting..."], "", &[], 0,
-                )?;
-
-                if let Err(e) = perform_handshake(&mut conn, true) {
-                    return show_handshake_error(stdout, music, "HOST GAME", &format!("{}", e));
-                }
-
-                let vs = Settings::default();
-                conn.send(&NetMessage::LobbySettings(vs))?;
-
-                let msg = conn.recv_blocking()?;
-                match msg {
-                    NetMessage::Ready => {}
-                    _ => {
-                        return Err(io::Error::new(
-                            io::ErrorKind::InvalidData,
-                            "expected Ready",
-                        ));
+        match hub.poll() {
+            Some(Arrival::Player(conn)) => {
+                play_menu_sfx(music, Sfx::MenuSelect);
+                let mut conn = Broadcast::new(conn, hub);
+                let event = run_match_setup(stdout, music, &mut conn, &mut vs, true)?;
+                settings.keep_match_rules(&vs);
+                match event {
+                    SetupEvent::Start => return Ok(LobbyResult::Connected(Box::new(conn), vs)),
+                    SetupEvent::Leave => return Ok(LobbyResult::Back),
+                    SetupEvent::PeerLeft => {
+                        hub = conn.into_hub();
+                        beacon = BeaconSender::new(&Beacon::new(port, vs, false)).ok();
+                        error = "Opponent left";
                     }
                 }
-
-                return Ok(LobbyResult::Connected(conn, vs));
             }
-            Ok(None) => {}
-            Err(e) => return Err(e),
+            Some(Arrival::RoomPlayer(mut conn) | Arrival::Resume(_, mut conn)) => {
+                let _ = conn.send(&NetMessage::Disconnect);
+            }
+            Some(Arrival::Rejected(e)) => {
+                return show_lobby_error(stdout, music, "HOST GAME", "Handshake failed", &format!("{}", e));
+            }
+            None => {}
+        }
+    }
+}
+
+/// Why a client is connecting to a host.
+#[derive(Clone, Copy, PartialEq)]
+pub enum JoinRole {
+    Player,
+    Spectator,
+    Room,
+}
+
+impl JoinRole {
+    fn title(self) -> &'static str {
+        match self {
+            JoinRole::Player => "JOIN GAME",
+            JoinRole::Spectator => "WATCH GAME",
+            JoinRole::Room => "JOIN ROOM",
+        }
+    }
+
+    /// What the client answers the host's lobby settings with.
+    fn reply(self) -> NetMessage {
+        match self {
+            JoinRole::Player => NetMessage::Ready,
+            JoinRole::Spectator => NetMessage::Spectate,
+            JoinRole::Room => NetMessage::JoinRoom,
         }
     }
 }
@@ -153,7 +202,9 @@ pub fn run_client_lobby(
     stdout: &mut io::Stdout,
     music: &mut Option<audio::MusicPlayer>,
     addr: &str,
+    role: JoinRole,
 ) -> io::Result<LobbyResult> {
+    let title = role.title();
     let (ip, port) = addr.rsplit_once(':').unwrap_or((addr, ""));
     let addr_lines: Vec<String> = vec![format!("IP: {}", ip), format!("Port: {}", port)];
     let addr_refs: Vec<&str> = addr_lines.iter().map(|s| s.as_str()).collect();
@@ -163,7 +214,7 @@ pub fn run_client_lobby(
             .chain(addr_refs.iter().copied())
             .collect();
         render::versus::draw_lobby_screen(
-            stdout, "JOIN GAME", &lines, "", &[], 0,
+            stdout, title, &lines, "", &[], 0,
         )?;
     }
 
@@ -175,7 +226,7 @@ pub fn run_client_lobby(
             let count: usize = 3;
             loop {
                 render::versus::draw_lobby_screen(
-                    stdout, "JOIN GAME", &addr_refs, &error_msg, &["Retry", "Back", "Menu"], sel,
+                    stdout, title, &addr_refs, &error_msg, &["Retry", "Back", "Menu"], sel,
                 )?;
                 if let Some(code) = read_key()? {
                     match code {
@@ -186,7 +237,7 @@ pub fn run_client_lobby(
                         KeyCode::Enter => match sel {
                             0 => {
                                 play_menu_sfx(music, Sfx::MenuSelect);
-                                return run_client_lobby(stdout, music, addr);
+                                return run_client_lobby(stdout, music, addr, role);
                             }
                             1 => {
                                 play_menu_sfx(music, Sfx::MenuBack);
@@ -209,16 +260,16 @@ pub fn run_client_lobby(
     };
 
     render::versus::draw_lobby_screen(
-        stdout, "JOIN GAME", &["Connected!", "Starting..."], "", &[], 0,
+        stdout, title, &["Connected!", "Starting..."], "", &[], 0,
     )?;
 
     if let Err(e) = perform_handshake(&mut conn, false) {
-        return show_handshake_error(stdout, music, "JOIN GAME", &format!("{}", e));
+        return show_lobby_error(stdout, music, title, "Handshake failed", &format!("{}", e));
     }
 
     let msg = conn.recv_blocking()?;
-    let vs = match msg {
-        NetMessage::LobbySettings(vs) => vs,
+    let mut vs = match msg {
+        NetMessage::LobbySettings(_, vs) => vs,
         _ => {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidData,
@@ -227,68 +278,288 @@ pub fn run_client_lobby(
         }
     };
 
-    conn.send(&NetMessage::Ready)?;
+    conn.send(&role.reply())?;
 
     play_menu_sfx(music, Sfx::MenuSelect);
 
-    Ok(LobbyResult::Connected(conn, vs))
+    if role != JoinRole::Player {
+        return Ok(LobbyResult::Connected(Box::new(conn), vs));
+    }
+
+    let mut link = GuestLink::new(addr, conn);
+    match run_match_setup(stdout, music, &mut link, &mut vs, false)? {
+        SetupEvent::Start => Ok(LobbyResult::Connected(Box::new(link), vs)),
+        SetupEvent::Leave => Ok(LobbyResult::Back),
+        SetupEvent::PeerLeft => show_lobby_error(stdout, music, title, "Host left", ""),
+    }
+}
+
+/// How the match setup screen ended.
+#[derive(Debug, PartialEq)]
+pub(super) enum SetupEvent {
+    Start,
+    /// The local player chose Leave.
+    Leave,
+    PeerLeft,
+}
+
+/// Ready flags on the match setup screen. The host starts the match once
+/// both are set; any change to the settings clears the guest's flag.
+///
+/// Each change the host makes bumps a revision that the settings and every
+/// Ready carry, so a Ready sent for settings that have since changed is
+/// dropped when it arrives.
+#[derive(Default)]
+pub(super) struct ReadyCheck {
+    pub we_ready: bool,
+    pub peer_ready: bool,
+    revision: u32,
+}
+
+impl ReadyCheck {
+    pub(super) fn toggle(&mut self, conn: &mut dyn Transport) {
+        self.we_ready = !self.we_ready;
+        let _ = conn.send(&NetMessage::ReadyState(self.we_ready, self.revision));
+    }
+
+    /// Host only: sends the settings as they now stand.
+    pub(super) fn settings_changed(&mut self, conn: &mut dyn Transport, vs: &Settings) {
+        self.revision += 1;
+        let _ = conn.send(&NetMessage::LobbySettings(self.revision, *vs));
+        self.peer_ready = false;
+    }
+
+    /// Host only: starts the match if both sides are ready, handing out a
+    /// fresh session token and garbage seed. The seed is written to `vs`.
+    pub(super) fn try_start(&mut self, conn: &mut dyn Transport, vs: &mut Settings) -> bool {
+        if self.we_ready && self.peer_ready {
+            vs.garbage.seed = random_seed();
+            return conn.send(&NetMessage::StartMatch(random_seed(), vs.garbage.seed)).is_ok();
+        }
+        false
+    }
+
+    /// Drains every message that has arrived. Settings and the garbage seed
+    /// from the host are written to `vs`.
+    pub(super) fn poll(&mut self, conn: &mut dyn Transport, vs: &mut Settings) -> Option<SetupEvent> {
+        loop {
+            match conn.try_recv() {
+                Ok(Some(NetMessage::ReadyState(ready, revision))) => {
+                    if revision == self.revision {
+                        self.peer_ready = ready;
+                    }
+                }
+                Ok(Some(NetMessage::LobbySettings(revision, new))) => {
+                    *vs = new;
+                    self.revision = revision;
+                    self.we_ready = false;
+                }
+                Ok(Some(NetMessage::StartMatch(_, seed))) => {
+                    vs.garbage.seed = seed;
+                    return Some(SetupEvent::Start);
+                }
+                Ok(Some(NetMessage::Disconnect)) => return Some(SetupEvent::PeerLeft),
+                Ok(Some(_)) => {}
+                Ok(None) => return None,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return None,
+                Err(_) => return Some(SetupEvent::PeerLeft),
+            }
+        }
+    }
+}
+
+/// Steps row `row` of the match setup screen by `direction`.
+pub(super) fn adjust_match_setting(vs: &mut Settings, row: usize, direction: i32) {
+    match row {
+        0 => vs.level = (vs.level as i32 + direction).clamp(1, 20) as u32,
+        1 => vs.next_count = (vs.next_count as i32 + direction).clamp(0, MAX_NEXT_COUNT as i32) as usize,
+        2 => vs.lock_delay_ms = (vs.lock_delay_ms as i32 + direction * 100).clamp(0, 2000) as u32,
+        3 => vs.rotation = cycle(&RotationSystem::ALL, vs.rotation, direction),
+        4 => vs.hold_enabled = !vs.hold_enabled,
+        5 => vs.garbage.cancel = !vs.garbage.cancel,
+        6 => vs.garbage.table = cycle(&AttackTable::ALL, vs.garbage.table, direction),
+        7 => vs.garbage.style = cycle(&GarbageStyle::ALL, vs.garbage.style, direction),
+        8 => vs.garbage.delay_ms = (vs.garbage.delay_ms as i32 + direction * 100).clamp(0, 2000) as u32,
+        9 => match (vs.garbage.cap, direction) {
+            (Some(n), 1) if n >= MAX_GARBAGE_CAP => vs.garbage.cap = None,
+            (Some(n), 1) => vs.garbage.cap = Some(n + 1),
+            (Some(n), -1) if n > 1 => vs.garbage.cap = Some(n - 1),
+            (None, -1) => vs.garbage.cap = Some(MAX_GARBAGE_CAP),
+            _ => {}
+        },
+        10 => match (vs.garbage.margin_secs, direction) {
+            (None, 1) => vs.garbage.margin_secs = Some(MARGIN_STEP_SECS),
+            (Some(n), 1) => vs.garbage.margin_secs = Some((n + MARGIN_STEP_SECS).min(MAX_MARGIN_SECS)),
+            (Some(n), -1) if n <= MARGIN_STEP_SECS => vs.garbage.margin_secs = None,
+            (Some(n), -1) => vs.garbage.margin_secs = Some(n - MARGIN_STEP_SECS),
+            _ => {}
+        },
+        11 => vs.first_to = (vs.first_to as i32 + direction).clamp(1, MAX_FIRST_TO as i32) as u32,
+        12 => vs.verify = !vs.verify,
+        _ => {}
+    }
+}
+
+/// The screen both players sit on between connecting and the countdown.
+/// The host edits the match settings, the guest sees each change, and either
+/// side can toggle Ready.
+fn run_match_setup(
+    stdout: &mut io::Stdout,
+    music: &mut Option<audio::MusicPlayer>,
+    conn: &mut dyn Transport,
+    vs: &mut Settings,
+    is_host: bool,
+) -> io::Result<SetupEvent> {
+    let mut check = ReadyCheck::default();
+    if is_host {
+        check.settings_changed(conn, vs);
+    }
+    // The guest can only reach Ready and Leave.
+    let first = if is_host { 0 } else { SETUP_ROWS };
+    let mut sel = first;
+    loop {
+        let ready = if is_host {
+            [check.we_ready, check.peer_ready]
+        } else {
+            [check.peer_ready, check.we_ready]
+        };
+        render::versus::draw_match_setup(stdout, vs, is_host, ready, sel)?;
+
+        if let Some(event) = check.poll(conn, vs) {
+            return Ok(event);
+        }
+        if is_host && check.try_start(conn, vs) {
+            return Ok(SetupEvent::Start);
+        }
+
+        if event::poll(Duration::from_millis(50))? {
+            if let Some(code) = read_key()? {
+                match code {
+                    KeyCode::Up | KeyCode::Down => {
+                        sel = first + menu_nav(sel - first, SETUP_ROWS + 2 - first, code);
+                        play_menu_sfx(music, Sfx::MenuMove);
+                    }
+                    KeyCode::Left | KeyCode::Right | KeyCode::Enter if sel < SETUP_ROWS => {
+                        let direction = if code == KeyCode::Left { -1 } else { 1 };
+                        adjust_match_setting(vs, sel, direction);
+                        check.settings_changed(conn, vs);
+                        play_menu_sfx(music, Sfx::MenuMove);
+                    }
+                    KeyCode::Enter if sel == SETUP_ROWS => {
+                        check.toggle(conn);
+                        play_menu_sfx(music, Sfx::MenuSelect);
+                    }
+                    KeyCode::Enter | KeyCode::Esc => {
+                        play_menu_sfx(music, Sfx::MenuBack);
+                        let _ = conn.send(&NetMessage::Disconnect);
+                        return Ok(SetupEvent::Leave);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Host side of the countdown: sends 3, 2, 1 and then `GameStart`, calling
+/// `tick` after each count is sent.
+pub(super) fn host_countdown(
+    conn: &mut dyn Transport,
+    mut tick: impl FnMut(u8) -> io::Result<()>,
+) -> io::Result<()> {
+    for count in (1..=3).rev() {
+        conn.send(&NetMessage::Countdown(count))?;
+        tick(count)?;
+    }
+    conn.send(&NetMessage::GameStart)
+}
+
+/// Client side of the countdown: calls `tick` for each count the host sends.
+/// Returns false if the host left instead of starting.
+pub(super) fn follow_countdown(
+    conn: &mut dyn Transport,
+    mut tick: impl FnMut(u8) -> io::Result<()>,
+) -> io::Result<bool> {
+    loop {
+        match conn.recv_blocking()? {
+            NetMessage::Countdown(n) => tick(n)?,
+            NetMessage::GameStart => return Ok(true),
+            NetMessage::Disconnect => return Ok(false),
+            _ => {}
+        }
+    }
 }
 
 fn run_countdown(
     stdout: &mut io::Stdout,
-    conn: &mut Connection,
+    conn: &mut dyn Transport,
     is_host: bool,
+    set: &SetScore,
     music: &mut Option<audio::MusicPlayer>,
 ) -> io::Result<bool> {
+    let score = (!set.is_single()).then(|| set.from_side(is_host));
     if is_host {
-        for count in (1..=3).rev() {
-            conn.send(&NetMessage::Countdown(count))?;
-            render::versus::draw_versus_countdown(stdout, count)?;
+        host_countdown(conn, |count| {
+            render::versus::draw_versus_countdown(stdout, count, score)?;
             play_menu_sfx(music, Sfx::MenuMove);
             std::thread::sleep(Duration::from_secs(1));
-        }
-        conn.send(&NetMessage::GameStart)?;
+            Ok(())
+        })?;
+        Ok(true)
     } else {
-        render::versus::draw_versus_countdown(stdout, 0)?;
-        loop {
-            let msg = conn.recv_blocking()?;
-            match msg {
-                NetMessage::Countdown(n) => {
-                    render::versus::draw_versus_countdown(stdout, n)?;
-                    play_menu_sfx(music, Sfx::MenuMove);
-                }
-                NetMessage::GameStart => break,
-                NetMessage::Disconnect => return Ok(false),
-                _ => {}
-            }
-        }
+        render::versus::draw_versus_countdown(stdout, 0, score)?;
+        follow_countdown(conn, |count| {
+            render::versus::draw_versus_countdown(stdout, count, score)?;
+            play_menu_sfx(music, Sfx::MenuMove);
+            Ok(())
+        })
     }
-    Ok(true)
 }
 
 pub fn run_versus(
     stdout: &mut io::Stdout,
     music: &mut Option<audio::MusicPlayer>,
-    conn: &mut Connection,
+    conn: &mut dyn Transport,
     vs_settings: &Settings,
     is_host: bool,
+    settings: &Settings,
+    keymap: &Keymap,
 ) -> io::Result<bool> {
     execute!(stdout, terminal::Clear(terminal::ClearType::All))?;
+    let mut set = SetScore::new(vs_settings.first_to);
+    let mut games_played: u64 = 0;
 
     loop {
-        if !run_countdown(stdout, conn, is_host, music)? {
+        if !run_countdown(stdout, conn, is_host, &set, music)? {
             return Ok(false);
         }
+        let mut link = ResumableLink::new(&mut *conn);
+        let conn: &mut dyn Transport = &mut link;
 
         let mut game_settings = *vs_settings;
         game_settings.level_cap = Some(game_settings.level);
-        let mut game = Game::new(GameMode::Versus, &game_settings);
-        let mut garbage_queue = GarbageQueue::new();
-        let mut opponent_snapshot: Option<BoardSnapshot> = None;
+        // A verified game deals both sides the same pieces so each can
+        // replay the other's.
+        let seed = if vs_settings.verify {
+            piece_seed(vs_settings.garbage.seed, games_played)
+        } else {
+            random_seed()
+        };
+        let mut game = Game::new(GameMode::Versus, &game_settings, seed);
+        let mut garbage_queue = GarbageQueue::with_delay(Duration::from_millis(vs_settings.garbage.delay_ms as u64));
+        let mut opponent = Opponent::default();
+        let mut stats = MatchStats::default();
+        // A fresh run of holes each game, the same on both sides.
+        let hole_seed = vs_settings.garbage.seed.wrapping_add(games_played);
+        let mut holes = HoleGenerator::new(vs_settings.garbage.style, hole_seed);
+        games_played += 1;
 
-        let mut inp = InputState::new();
+        let mut inp = InputState::new(keymap, settings);
+        if vs_settings.verify {
+            inp.lockstep = Some(LockstepLog::default());
+            opponent.verifier = Some(Verifier::new(&game_settings, seed, hole_seed));
+        }
         let mut last_board_sync = Instant::now();
-        let mut opponent_dead = false;
         let mut we_died = false;
         let mut forfeit_sel: Option<usize> = None;
 
@@ -307,7 +578,7 @@ pub fn run_versus(
                 }
             }
 
-            if we_died || opponent_dead {
+            if we_died || opponent.dead {
                 break;
             }
 
@@ -320,66 +591,84 @@ pub fn run_versus(
                 };
                 render::versus::draw_versus_forfeit(stdout, bgm_on, sfx_on, sel)?;
             } else {
-                let pending = garbage_queue.total_pending() + game.garbage_anim_remaining();
+                let ready = garbage_queue.ready_lines(game.now());
                 render::versus::draw_versus(
                     stdout,
                     &game,
-                    &opponent_snapshot,
-                    pending,
+                    &opponent.snapshot,
+                    ready + game.garbage_anim_remaining(),
+                    garbage_queue.total_pending() - ready,
+                    opponent.heartbeat.rtt(),
                 )?;
             }
 
             if last_board_sync.elapsed() >= BOARD_SYNC_INTERVAL {
                 let snap = BoardSnapshot::from_game(&game, garbage_queue.total_pending());
-                let _ = conn.send(&NetMessage::BoardState(snap));
+                let update = opponent.encoder.encode(&snap);
+                let _ = conn.send(&NetMessage::BoardUpdate(update));
+                if let Some(log) = inp.lockstep.as_mut().filter(|log| !log.is_empty()) {
+                    let _ = conn.send(&NetMessage::Inputs(log.batch(&game, false)));
+                }
                 last_board_sync = Instant::now();
             }
+            opponent.heartbeat.tick(conn);
 
-            loop {
-                match conn.try_recv() {
-                    Ok(Some(msg)) => match msg {
-                        NetMessage::GarbageAttack(ga) => {
-                            garbage_queue.push(GarbageEvent {
-                                lines: ga.lines,
-                                hole_column: ga.hole_column,
-                            });
-                            if let Some(m) = music.as_ref() {
-                                m.play_sfx(Sfx::GarbageReceived);
-                            }
-                        }
-                        NetMessage::BoardState(snap) => {
-                            opponent_snapshot = Some(snap);
-                        }
-                        NetMessage::PlayerDead => {
-                            opponent_dead = true;
-                        }
-                        NetMessage::MatchResult(_) => {}
-                        NetMessage::Disconnect => {
-                            if let Some(m) = music.as_mut() {
-                                m.stop();
-                            }
-                            return Ok(false);
-                        }
-                        _ => {}
-                    },
-                    Ok(None) => break,
-                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
-                    Err(e) if e.kind() == io::ErrorKind::ConnectionAborted => {
-                        if let Some(m) = music.as_mut() {
-                            m.stop();
-                        }
-                        return Ok(false);
+            let peer = poll_peer(conn, &mut garbage_queue, game.now(), &mut opponent);
+            if peer.dropped {
+                if !wait_for_resume(stdout, music, conn)? {
+                    if let Some(m) = music.as_mut() {
+                        m.stop();
                     }
-                    Err(_) => break,
+                    if is_host {
+                        // Only the watchers hear this; it settles the match for them.
+                        let _ = conn.send(&NetMessage::MatchResult(MatchOutcome::Win));
+                    }
+                    show_timeout(stdout, music, is_host)?;
+                    return Ok(false);
+                }
+                opponent.heartbeat.restart();
+                game.reset_game_start();
+                inp.last_tick = Instant::now();
+                if game.lock_delay.is_some() {
+                    game.lock_delay = Some(game.now());
+                }
+                if let Some(d) = &mut inp.das {
+                    d.reset_timers();
+                }
+                // Boards go out straight away so both sides are back in sync.
+                last_board_sync = Instant::now() - BOARD_SYNC_INTERVAL;
+                execute!(stdout, terminal::Clear(terminal::ClearType::All))?;
+                continue;
+            }
+            if peer.disconnected {
+                if let Some(m) = music.as_mut() {
+                    m.stop();
+                }
+                return Ok(false);
+            }
+            if let Some(log) = &mut inp.lockstep {
+                for event in peer.received {
+                    log.push(&game, LockstepAction::Incoming(event));
+                }
+            }
+            if let Some((mismatch, whose)) = verification_failure(conn, &opponent) {
+                show_mismatch(stdout, music, mismatch, whose)?;
+                return Ok(false);
+            }
+            stats.attack_received += peer.lines;
+            if peer.attacks > 0 {
+                if let Some(m) = music.as_ref() {
+                    m.play_sfx(Sfx::GarbageReceived);
                 }
             }
 
-            if opponent_dead {
+            if opponent.dead {
                 break;
             }
 
             if game.is_animating() && !game.update_animation() {
                 game.finish_clear();
+                inp.record(&game, GameInput::ClearDone);
                 inp.last_tick = Instant::now();
             }
 
@@ -401,53 +690,11 @@ pub fn run_versus(
 
             if event::poll(timeout)? {
                 if let Some(code) = read_key()? {
-                    if let Some(sel) = forfeit_sel.as_mut() {
-                        match code {
-                            KeyCode::Up | KeyCode::Down => {
-                                *sel = menu_nav(*sel, 4, code);
-                                play_menu_sfx(music, Sfx::MenuMove);
-                            }
-                            KeyCode::Left | KeyCode::Right => match *sel {
-                                2 => toggle_bgm(music),
-                                3 => toggle_sfx(music),
-                                _ => {}
-                            }
-                            KeyCode::Enter => match *sel {
-                                0 => {
-                                    if let Some(m) = music.as_ref() {
-                                        m.play_sfx(Sfx::Resume);
-                                    }
-                                    forfeit_sel = None;
-                                    inp.last_tick = Instant::now();
-                                    if let Some(d) = &mut inp.das {
-                                        d.reset_timers();
-                                    }
-                                    execute!(stdout, terminal::Clear(terminal::ClearType::All))?;
-                                }
-                                1 => {
-                                    game.game_over = true;
-                                    forfeit_sel = None;
-                                }
-                                2 => toggle_bgm(music),
-                                3 => toggle_sfx(music),
-                                _ => {}
-                            },
-                            KeyCode::Esc => {
-                                if let Some(m) = music.as_ref() {
-                                    m.play_sfx(Sfx::Resume);
-                                }
-                                forfeit_sel = None;
-                                inp.last_tick = Instant::now();
-                                if let Some(d) = &mut inp.das {
-                                    d.reset_timers();
-                                }
-                                execute!(stdout, terminal::Clear(terminal::ClearType::All))?;
-                            }
-                            _ => {}
-                        }
+                    if forfeit_sel.is_some() {
+                        handle_forfeit_key(stdout, music, code, &mut forfeit_sel, &mut game, &mut inp)?;
                     } else {
                         match code {
-                            KeyCode::Esc => {
+                            c if inp.keymap.is_pause(c) => {
                                 if let Some(m) = music.as_ref() {
                                     m.play_sfx(Sfx::Pause);
                                 }
@@ -457,12 +704,16 @@ pub fn run_versus(
                                 if !game.is_animating() && !game.is_garbage_animating() {
                                     let hard_dropped = input::handle_game_key(other, &mut game, &mut inp, music);
                                     if hard_dropped {
-                                        process_post_lock(
+                                        let sent = process_post_lock(
                                             &mut game,
                                             &mut garbage_queue,
+                                            vs_settings.garbage,
+                                            &mut holes,
+                                            &mut stats,
                                             conn,
                                             music,
                                         )?;
+                                        note_lock(&game, &mut inp, &mut opponent, sent);
                                     }
                                 }
                             }
@@ -479,17 +730,31 @@ pub fn run_versus(
 
                 let locked = input::update_game_timers(&mut game, &mut inp, music);
                 if locked {
-                    process_post_lock(&mut game, &mut garbage_queue, conn, music)?;
+                    let sent = process_post_lock(
+                        &mut game,
+                        &mut garbage_queue,
+                        vs_settings.garbage,
+                        &mut holes,
+                        &mut stats,
+                        conn,
+                        music,
+                    )?;
+                    note_lock(&game, &mut inp, &mut opponent, sent);
                 }
             }
         }
 
+        if let Some((mismatch, whose)) = finish_verification(conn, &game, &mut inp, &mut garbage_queue, &mut opponent) {
+            show_mismatch(stdout, music, mismatch, whose)?;
+            return Ok(false);
+        }
+
         if let Some(m) = music.as_mut() {
             m.stop();
         }
 
         let won = if is_host {
-            if we_died && !opponent_dead {
+            if we_died && !opponent.dead {
                 let _ = conn.send(&NetMessage::MatchResult(MatchOutcome::Lose));
                 false
             } else {
@@ -497,8 +762,15 @@ pub fn run_versus(
                 true
             }
         } else {
-            opponent_dead && !we_died
+            opponent.dead && !we_died
         };
+        // Both sides keep the score; the host's count wins if they differ.
+        set.record(won == is_host);
+        if is_host {
+            let _ = conn.send(&NetMessage::SetScore(set));
+        } else if let Some(score) = opponent.set_score.take() {
+            set = score;
+        }
 
         if won {
             if let Some(m) = music.as_ref() {
@@ -508,10 +780,15 @@ pub fn run_versus(
             m.play_sfx(Sfx::VersusLose);
         }
 
-        let rematch = run_result_screen(stdout, music, conn, won)?;
+        stats.finish(game.elapsed);
+        let _ = conn.send(&NetMessage::MatchStats(stats.clone()));
+        let rematch = run_result_screen(stdout, music, conn, won, is_host, &mut set, &stats, opponent.stats.take())?;
 
         match rematch {
             ResultAction::Rematch => {
+                if set.host_won_set().is_some() {
+                    set = SetScore::new(vs_settings.first_to);
+                }
                 continue;
             }
             ResultAction::Menu => return Ok(false),
@@ -519,85 +796,459 @@ pub fn run_versus(
     }
 }
 
-fn process_post_lock(
+/// Keys on the pause overlay of a Versus match: resume, forfeit, or toggle audio.
+pub(super) fn handle_forfeit_key(
+    stdout: &mut io::Stdout,
+    music: &mut Option<audio::MusicPlayer>,
+    code: KeyCode,
+    forfeit_sel: &mut Option<usize>,
     game: &mut Game,
-    garbage_queue: &mut GarbageQueue,
-    conn: &mut Connection,
-    music: &Option<audio::MusicPlayer>,
+    inp: &mut InputState,
 ) -> io::Result<()> {
-    if let Some(action) = &game.last_action {
-        if action.cleared_lines > 0 {
-            let attack = calculate_attack(action);
-            if attack > 0 {
-                let remaining = garbage_queue.cancel(attack);
-                if remaining > 0 {
-                    use rand::Rng;
-                    let hole = rand::thread_rng().gen_range(0..BOARD_WIDTH);
-                    let _ = conn.send(&NetMessage::GarbageAttack(GarbageAttack {
-                        lines: remaining,
-                        hole_column: hole,
-                    }));
+    let Some(sel) = forfeit_sel.as_mut() else {
+        return Ok(());
+    };
+    let resume = match code {
+        KeyCode::Up | KeyCode::Down => {
+            *sel = menu_nav(*sel, 4, code);
+            play_menu_sfx(music, Sfx::MenuMove);
+            false
+        }
+        KeyCode::Left | KeyCode::Right => {
+            match *sel {
+                2 => toggle_bgm(music),
+                3 => toggle_sfx(music),
+                _ => {}
+            }
+            false
+        }
+        KeyCode::Enter => match *sel {
+            0 => true,
+            1 => {
+                game.game_over = true;
+                *forfeit_sel = None;
+                false
+            }
+            2 => {
+                toggle_bgm(music);
+                false
+            }
+            3 => {
+                toggle_sfx(music);
+                false
+            }
+            _ => false,
+        },
+        KeyCode::Esc => true,
+        _ => false,
+    };
+    if resume {
+        if let Some(m) = music.as_ref() {
+            m.play_sfx(Sfx::Resume);
+        }
+        *forfeit_sel = None;
+        inp.last_tick = Instant::now();
+        if let Some(d) = &mut inp.das {
+            d.reset_timers();
+        }
+        execute!(stdout, terminal::Clear(terminal::ClearType::All))?;
+    }
+    Ok(())
+}
+
+/// What the local side knows about the remote player during a match.
+#[derive(Default)]
+pub(super) struct Opponent {
+    pub snapshot: Option<BoardSnapshot>,
+    pub dead: bool,
+    pub heartbeat: Heartbeat,
+    pub decoder: BoardDecoder,
+    /// Our own board updates, built on what the opponent has acknowledged.
+    pub encoder: BoardEncoder,
+    /// Sent by the opponent as its game ends.
+    pub stats: Option<MatchStats>,
+    /// The host's set score, if it came in before the game loop let go.
+    pub set_score: Option<SetScore>,
+    /// Replays the opponent's inputs in a verified match.
+    pub verifier: Option<Verifier>,
+    /// The opponent's check of our game failed.
+    pub flagged: Option<Mismatch>,
+}
+
+/// What `poll_peer` picked up this frame.
+#[derive(Default)]
+pub(super) struct PeerPoll {
+    pub attacks: usize,
+    /// Garbage lines in those attacks.
+    pub lines: u32,
+    /// The attacks themselves, in the order they joined the queue.
+    pub received: Vec<GarbageEvent>,
+    /// The opponent said goodbye.
+    pub disconnected: bool,
+    /// The link failed without a goodbye; the match may yet resume.
+    pub dropped: bool,
+}
+
+/// Drains every message that has arrived: attacks go into `garbage_queue`
+/// as arriving at `now` on our game's clock, board states and deaths into
+/// `opponent`. A peer that has gone quiet for too long counts as a dropped
+/// link.
+pub(super) fn poll_peer(
+    conn: &mut dyn Transport,
+    garbage_queue: &mut GarbageQueue,
+    now: Instant,
+    opponent: &mut Opponent,
+) -> PeerPoll {
+    let mut poll = PeerPoll::default();
+    loop {
+        match conn.try_recv() {
+            Ok(Some(msg)) if opponent.heartbeat.heard(conn, &msg) => {}
+            Ok(Some(msg)) => match msg {
+                NetMessage::GarbageAttack(ga) => {
+                    let event = GarbageEvent {
+                        lines: ga.lines,
+                        hole_column: ga.hole_column,
+                    };
+                    garbage_queue.push_at(event, now);
+                    if let Some(verifier) = &mut opponent.verifier {
+                        verifier.claim(event);
+                    }
+                    poll.attacks += 1;
+                    poll.lines += ga.lines;
+                    poll.received.push(event);
                 }
+                NetMessage::BoardUpdate(update) => {
+                    if let Some(snap) = opponent.decoder.decode(&update) {
+                        let _ = conn.send(&NetMessage::BoardAck(update.seq));
+                        opponent.snapshot = Some(snap);
+                    }
+                }
+                NetMessage::BoardAck(seq) => opponent.encoder.ack(seq),
+                NetMessage::BoardState(snap) => {
+                    opponent.snapshot = Some(snap);
+                }
+                NetMessage::PlayerDead => {
+                    opponent.dead = true;
+                }
+                NetMessage::MatchStats(theirs) => opponent.stats = Some(theirs),
+                NetMessage::SetScore(score) => opponent.set_score = Some(score),
+                NetMessage::Inputs(batch) => {
+                    if let Some(verifier) = &mut opponent.verifier {
+                        verifier.feed(&batch);
+                    }
+                }
+                NetMessage::Mismatch(mismatch) => opponent.flagged = Some(mismatch),
+                NetMessage::MatchResult(_) => {}
+                NetMessage::Disconnect => {
+                    poll.disconnected = true;
+                    return poll;
+                }
+                _ => {}
+            },
+            Ok(None) => break,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+            Err(_) => {
+                poll.dropped = true;
+                return poll;
             }
-        } else {
-            apply_pending_garbage(game, garbage_queue, music);
         }
+    }
+    poll.dropped = opponent.heartbeat.timed_out();
+    poll
+}
+
+/// Holds the match on a "connection lost" screen while the link is brought
+/// back. False if `RESUME_GRACE` ran out first. Keys pressed meanwhile are
+/// thrown away so they do not land on the board afterwards.
+fn wait_for_resume(
+    stdout: &mut io::Stdout,
+    music: &mut Option<audio::MusicPlayer>,
+    conn: &mut dyn Transport,
+) -> io::Result<bool> {
+    if let Some(m) = music.as_mut() {
+        m.pause();
+    }
+    let deadline = Instant::now() + RESUME_GRACE;
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Ok(false);
+        }
+        render::versus::draw_reconnecting(stdout, left.as_secs() + 1)?;
+        if conn.reconnect() {
+            if let Some(m) = music.as_mut() {
+                m.resume();
+            }
+            return Ok(true);
+        }
+        if event::poll(Duration::from_millis(250))? {
+            read_key()?;
+        }
+    }
+}
+
+/// The match after `RESUME_GRACE` ran out. The host kept listening the whole
+/// time, so it is the guest, who had to dial back in, that loses.
+fn show_timeout(stdout: &mut io::Stdout, music: &mut Option<audio::MusicPlayer>, is_host: bool) -> io::Result<()> {
+    let (title, line, sfx) = if is_host {
+        ("YOU WIN", "Opponent timed out", Sfx::VersusWin)
     } else {
-        apply_pending_garbage(game, garbage_queue, music);
+        ("YOU LOSE", "Connection timed out", Sfx::VersusLose)
+    };
+    if let Some(m) = music.as_ref() {
+        m.play_sfx(sfx);
     }
-    Ok(())
+    render::versus::draw_lobby_screen(stdout, title, &[line], "", &["Menu"], 0)?;
+    loop {
+        if let Some(KeyCode::Enter | KeyCode::Esc) = read_key()? {
+            play_menu_sfx(music, Sfx::MenuBack);
+            return Ok(());
+        }
+    }
+}
+
+/// After a lock: a line clear counters pending garbage and sends what is left
+/// of the attack, anything else lets the pending garbage rise. The lock is
+/// counted in `stats`. Returns the attacks sent.
+pub(super) fn process_post_lock(
+    game: &mut Game,
+    garbage_queue: &mut GarbageQueue,
+    rules: GarbageRules,
+    holes: &mut HoleGenerator,
+    stats: &mut MatchStats,
+    conn: &mut dyn Transport,
+    music: &Option<audio::MusicPlayer>,
+) -> io::Result<Vec<GarbageAttack>> {
+    let attacks = outgoing_attack(game, garbage_queue, rules, holes, music);
+    stats.record_lock(game, rules, attacks.iter().map(|a| a.lines).sum());
+    for attack in &attacks {
+        let _ = conn.send(&NetMessage::GarbageAttack(attack.clone()));
+    }
+    Ok(attacks)
 }
 
-fn apply_pending_garbage(
+/// What a lock set off, noted for a verified match: the attacks it sent go
+/// to our check of the opponent, garbage it started raising to our log.
+fn note_lock(game: &Game, inp: &mut InputState, opponent: &mut Opponent, sent: Vec<GarbageAttack>) {
+    if let Some(verifier) = &mut opponent.verifier {
+        for attack in sent {
+            verifier.sent(GarbageEvent {
+                lines: attack.lines,
+                hole_column: attack.hole_column,
+            });
+        }
+    }
+    if let Some(log) = &mut inp.lockstep {
+        if game.is_garbage_animating() {
+            log.push(game, LockstepAction::Rise(game.garbage_anim_remaining()));
+        }
+    }
+}
+
+/// A failed check on either side of a verified match: ours of the opponent,
+/// which they are told about, or theirs of us. Comes with whose game it was.
+pub(super) fn verification_failure(conn: &mut dyn Transport, opponent: &Opponent) -> Option<(Mismatch, &'static str)> {
+    if let Some(mismatch) = opponent.verifier.as_ref().and_then(Verifier::failure) {
+        let _ = conn.send(&NetMessage::Mismatch(mismatch));
+        return Some((mismatch, "in opponent's game"));
+    }
+    opponent.flagged.map(|mismatch| (mismatch, "in your game"))
+}
+
+/// Sends our last batch and waits, up to `VERIFY_GRACE`, for the
+/// opponent's, so the end of the game gets checked like the rest of it.
+fn finish_verification(
+    conn: &mut dyn Transport,
+    game: &Game,
+    inp: &mut InputState,
+    garbage_queue: &mut GarbageQueue,
+    opponent: &mut Opponent,
+) -> Option<(Mismatch, &'static str)> {
+    let log = inp.lockstep.as_mut()?;
+    let _ = conn.send(&NetMessage::Inputs(log.batch(game, true)));
+    let deadline = Instant::now() + VERIFY_GRACE;
+    while opponent.flagged.is_none() && opponent.verifier.as_ref().is_some_and(|v| !v.is_finished()) {
+        let peer = poll_peer(conn, garbage_queue, game.now(), opponent);
+        if peer.disconnected || peer.dropped || Instant::now() >= deadline {
+            if let Some(verifier) = &mut opponent.verifier {
+                verifier.give_up();
+            }
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(16));
+    }
+    verification_failure(conn, opponent)
+}
+
+fn show_mismatch(
+    stdout: &mut io::Stdout,
+    music: &mut Option<audio::MusicPlayer>,
+    mismatch: Mismatch,
+    whose: &str,
+) -> io::Result<()> {
+    if let Some(m) = music.as_mut() {
+        m.stop();
+    }
+    execute!(stdout, terminal::Clear(terminal::ClearType::All))?;
+    render::versus::draw_lobby_screen(stdout, mismatch.title(), &[mismatch.label(), whose], "", &["Menu"], 0)?;
+    loop {
+        if let Some(KeyCode::Enter | KeyCode::Esc) = read_key()? {
+            play_menu_sfx(music, Sfx::MenuBack);
+            return Ok(());
+        }
+    }
+}
+
+/// The garbage a lock sends once the multiplier is applied and it has
+/// countered what was pending, one attack per run of lines that share a
+/// hole. A lock that clears nothing lets the ready garbage rise instead.
+pub(super) fn outgoing_attack(
+    game: &mut Game,
+    garbage_queue: &mut GarbageQueue,
+    rules: GarbageRules,
+    holes: &mut HoleGenerator,
+    music: &Option<audio::MusicPlayer>,
+) -> Vec<GarbageAttack> {
+    // `last_action` outlives the clear that set it; the combo counter only
+    // stays at 0 or above while locks keep clearing lines.
+    let cleared = game.combo >= 0;
+    match &game.last_action {
+        Some(action) if cleared && action.cleared_lines > 0 => {
+            let attack = rules.attack(action, game.elapsed);
+            let remaining = garbage_queue.counter(attack, rules);
+            holes
+                .deal(remaining)
+                .into_iter()
+                .map(|run| GarbageAttack {
+                    lines: run.lines,
+                    hole_column: run.hole_column,
+                })
+                .collect()
+        }
+        _ => {
+            apply_pending_garbage(game, garbage_queue, rules.cap, music);
+            Vec::new()
+        }
+    }
+}
+
+/// Raises the garbage that is ready, no more than `cap` lines of it.
+pub(super) fn apply_pending_garbage(
     game: &mut Game,
     garbage_queue: &mut GarbageQueue,
+    cap: Option<u32>,
     _music: &Option<audio::MusicPlayer>,
 ) {
-    let events = garbage_queue.drain_all();
+    let events = garbage_queue.take_ready(game.now(), cap);
     if events.is_empty() {
         return;
     }
     game.begin_garbage_rise(events);
 }
 
-enum ResultAction {
+#[derive(Debug, PartialEq)]
+pub(super) enum ResultAction {
     Rematch,
     Menu,
 }
 
+/// Rematch agreement on the result screen. Each side sends a request; the
+/// side that sees both requests first sends the accept. The opponent's stats
+/// and the host's set score are picked up here if they were still on their
+/// way when the game ended.
+#[derive(Default)]
+pub(super) struct Rematch {
+    we_requested: bool,
+    opponent_requested: bool,
+    pub their_stats: Option<MatchStats>,
+    pub set_score: Option<SetScore>,
+}
+
+impl Rematch {
+    /// Asks for a rematch. Settled at once if the opponent already asked.
+    pub(super) fn request(&mut self, conn: &mut dyn Transport) -> Option<ResultAction> {
+        let _ = conn.send(&NetMessage::RematchRequest);
+        self.we_requested = true;
+        if self.opponent_requested {
+            let _ = conn.send(&NetMessage::RematchAccept);
+            return Some(ResultAction::Rematch);
+        }
+        None
+    }
+
+    /// Handles at most one incoming message.
+    pub(super) fn poll(&mut self, conn: &mut dyn Transport) -> Option<ResultAction> {
+        match conn.try_recv() {
+            Ok(Some(NetMessage::RematchRequest)) => {
+                self.opponent_requested = true;
+                if self.we_requested {
+                    let _ = conn.send(&NetMessage::RematchAccept);
+                    return Some(ResultAction::Rematch);
+                }
+                None
+            }
+            Ok(Some(NetMessage::RematchAccept)) => Some(ResultAction::Rematch),
+            Ok(Some(NetMessage::MatchStats(theirs))) => {
+                self.their_stats = Some(theirs);
+                None
+            }
+            Ok(Some(NetMessage::SetScore(score))) => {
+                self.set_score = Some(score);
+                None
+            }
+            Ok(Some(NetMessage::Disconnect)) => Some(ResultAction::Menu),
+            Ok(_) => None,
+            Err(e) if e.kind() == io::ErrorKind::ConnectionAborted => Some(ResultAction::Menu),
+            Err(_) => None,
+        }
+    }
+}
+
+/// The result screen after each game. When the game decided a set, Continue
+/// leads on to the set result, where Rematch starts a new set; otherwise the
+/// first item starts the next game.
+#[allow(clippy::too_many_arguments)]
 fn run_result_screen(
     stdout: &mut io::Stdout,
     music: &mut Option<audio::MusicPlayer>,
-    conn: &mut Connection,
+    conn: &mut dyn Transport,
     won: bool,
+    is_host: bool,
+    set: &mut SetScore,
+    ours: &MatchStats,
+    theirs: Option<MatchStats>,
 ) -> io::Result<ResultAction> {
     let mut sel: usize = 0;
     let count: usize = 2;
-    let mut we_requested_rematch = false;
-    let mut opponent_requested_rematch = false;
+    let mut rematch = Rematch {
+        their_stats: theirs,
+        ..Rematch::default()
+    };
+    let mut set_screen = false;
 
     loop {
-        render::versus::draw_versus_result(stdout, won, sel)?;
+        if let Some(score) = rematch.set_score.take() {
+            *set = score;
+        }
+        let score = (!set.is_single()).then(|| set.from_side(is_host));
+        let set_over = !set.is_single() && set.host_won_set().is_some();
+        if set_screen {
+            let won_set = set.host_won_set() == Some(is_host);
+            render::versus::draw_set_result(stdout, won_set, set.from_side(is_host), sel)?;
+        } else {
+            let next = if set_over {
+                "Continue"
+            } else if set.is_single() {
+                "Rematch"
+            } else {
+                "Next Game"
+            };
+            let stats = Some((ours, rematch.their_stats.as_ref()));
+            render::versus::draw_versus_result(stdout, won, score, stats, next, sel)?;
+        }
 
-        match conn.try_recv() {
-            Ok(Some(NetMessage::RematchRequest)) => {
-                opponent_requested_rematch = true;
-                if we_requested_rematch {
-                    let _ = conn.send(&NetMessage::RematchAccept);
-                    return Ok(ResultAction::Rematch);
-                }
-            }
-            Ok(Some(NetMessage::RematchAccept)) => {
-                return Ok(ResultAction::Rematch);
-            }
-            Ok(Some(NetMessage::Disconnect)) => {
-                return Ok(ResultAction::Menu);
-            }
-            Ok(_) => {}
-            Err(e) if e.kind() == io::ErrorKind::ConnectionAborted => {
-                return Ok(ResultAction::Menu);
-            }
-            Err(_) => {}
+        if let Some(action) = rematch.poll(conn) {
+            return Ok(action);
         }
 
         if event::poll(Duration::from_millis(50))? {
@@ -608,35 +1259,22 @@ fn run_result_screen(
                         play_menu_sfx(music, Sfx::MenuMove);
                     }
                     KeyCode::Enter => match sel {
+                        0 if set_over && !set_screen => {
+                            play_menu_sfx(music, Sfx::MenuSelect);
+                            set_screen = true;
+                            execute!(stdout, terminal::Clear(terminal::ClearType::All))?;
+                        }
                         0 => {
                             play_menu_sfx(music, Sfx::MenuSelect);
-                            let _ = conn.send(&NetMessage::RematchRequest);
-                            we_requested_rematch = true;
-
-                            if opponent_requested_rematch {
-                                let _ = conn.send(&NetMessage::RematchAccept);
-                                return Ok(ResultAction::Rematch);
+                            if let Some(action) = rematch.request(conn) {
+                                return Ok(action);
                             }
 
                             render::versus::draw_versus_waiting_rematch(stdout, 0)?;
                             let mut wait_sel: usize = 0;
                             loop {
-                                match conn.try_recv() {
-                                    Ok(Some(NetMessage::RematchRequest)) => {
-                                        let _ = conn.send(&NetMessage::RematchAccept);
-                                        return Ok(ResultAction::Rematch);
-                                    }
-                                    Ok(Some(NetMessage::RematchAccept)) => {
-                                        return Ok(ResultAction::Rematch);
-                                    }
-                                    Ok(Some(NetMessage::Disconnect)) => {
-                                        return Ok(ResultAction::Menu);
-                                    }
-                                    Ok(_) => {}
-                                    Err(e) if e.kind() == io::ErrorKind::ConnectionAborted => {
-                                        return Ok(ResultAction::Menu);
-                                    }
-                                    Err(_) => {}
+                                if let Some(action) = rematch.poll(conn) {
+                                    return Ok(action);
                                 }
 
                                 if event::poll(Duration::from_millis(50))? {